@@ -0,0 +1,280 @@
+#![allow(dead_code)]
+
+use crate::{fixed_step::FixedStep, surface::WaterSurface, wave::GRAVITY};
+
+use nalgebra as na;
+
+/// Anything the bodies can float on.
+pub trait HeightField {
+    fn height_at(&self, x: f32, z: f32) -> f32;
+}
+
+impl HeightField for WaterSurface {
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        WaterSurface::height_at(self, x, z)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BuoyancySettings {
+    pub water_density: f32,
+    pub gravity: f32,
+    /// Linear drag per unit of submerged volume, in 1/s, slowing each hull sample moving through the water.
+    pub linear_drag: f32,
+    /// Rate at which spinning is damped when fully submerged, in 1/s.
+    pub angular_drag: f32,
+    /// Fixed step of the integrator, so a simulation replays the same whatever the frame rate.
+    pub time_step: f32,
+}
+
+impl Default for BuoyancySettings {
+    fn default() -> Self {
+        Self {
+            water_density: 1000.0,
+            gravity: GRAVITY,
+            linear_drag: 2.0,
+            angular_drag: 2.0,
+            time_step: 1.0 / 120.0,
+        }
+    }
+}
+
+/// Rigid body state, integrated with semi-implicit Euler. Forces and torques accumulate until
+/// the next `integrate`.
+#[derive(Clone, Debug)]
+pub struct RigidBody {
+    pub position: na::Point3<f32>,
+    pub orientation: na::UnitQuaternion<f32>,
+    pub linear_velocity: na::Vector3<f32>,
+    /// World space, in radians per second.
+    pub angular_velocity: na::Vector3<f32>,
+    pub mass: f32,
+    /// Principal moments of inertia along the body axes.
+    pub inertia: na::Vector3<f32>,
+    force: na::Vector3<f32>,
+    torque: na::Vector3<f32>,
+}
+
+impl RigidBody {
+    pub fn new(mass: f32, inertia: na::Vector3<f32>, position: na::Point3<f32>) -> Self {
+        Self {
+            position,
+            orientation: na::UnitQuaternion::identity(),
+            linear_velocity: na::Vector3::zeros(),
+            angular_velocity: na::Vector3::zeros(),
+            mass,
+            inertia,
+            force: na::Vector3::zeros(),
+            torque: na::Vector3::zeros(),
+        }
+    }
+
+    /// Solid box of uniform density.
+    pub fn cuboid(mass: f32, half_extents: na::Vector3<f32>, position: na::Point3<f32>) -> Self {
+        let size = half_extents * 2.0;
+        let squared = size.component_mul(&size);
+        let inertia = na::Vector3::new(
+            squared.y + squared.z,
+            squared.x + squared.z,
+            squared.x + squared.y,
+        ) * (mass / 12.0);
+        Self::new(mass, inertia, position)
+    }
+
+    pub fn isometry(&self) -> na::Isometry3<f32> {
+        na::Isometry3::from_parts(self.position.coords.into(), self.orientation)
+    }
+
+    pub fn point_velocity(&self, point: &na::Point3<f32>) -> na::Vector3<f32> {
+        self.linear_velocity + self.angular_velocity.cross(&(point - self.position))
+    }
+
+    pub fn apply_force(&mut self, force: na::Vector3<f32>) {
+        self.force += force;
+    }
+
+    pub fn apply_force_at_point(&mut self, force: na::Vector3<f32>, point: &na::Point3<f32>) {
+        self.force += force;
+        self.torque += (point - self.position).cross(&force);
+    }
+
+    pub fn apply_torque(&mut self, torque: na::Vector3<f32>) {
+        self.torque += torque;
+    }
+
+    /// World-space inertia tensor applied to `v`.
+    pub fn world_inertia_mul(&self, v: &na::Vector3<f32>) -> na::Vector3<f32> {
+        let local = self.orientation.inverse_transform_vector(v);
+        self.orientation.transform_vector(&self.inertia.component_mul(&local))
+    }
+
+    pub fn integrate(&mut self, dt: f32) {
+        self.linear_velocity += self.force * (dt / self.mass);
+        let local_torque = self.orientation.inverse_transform_vector(&self.torque);
+        let local_acceleration = local_torque.component_div(&self.inertia);
+        self.angular_velocity += self.orientation.transform_vector(&local_acceleration) * dt;
+
+        self.position += self.linear_velocity * dt;
+        self.orientation = na::UnitQuaternion::from_scaled_axis(self.angular_velocity * dt) * self.orientation;
+        self.force = na::Vector3::zeros();
+        self.torque = na::Vector3::zeros();
+    }
+}
+
+/// Volume of a body as cells around body-space sample points. Each cell displaces water in
+/// proportion to how far its sample is below the surface.
+#[derive(Clone, Debug)]
+pub struct Hull {
+    pub points: Vec<na::Point3<f32>>,
+    /// Volume of each cell.
+    pub cell_volume: f32,
+    /// Height of each cell, over which it goes from dry to fully submerged.
+    pub cell_height: f32,
+}
+
+impl Hull {
+    /// Box split into `samples` cells along each axis.
+    pub fn cuboid(half_extents: na::Vector3<f32>, samples: [u32; 3]) -> Self {
+        let size = half_extents * 2.0;
+        let cell = size.component_div(&na::Vector3::new(samples[0] as f32, samples[1] as f32, samples[2] as f32));
+        let mut points = Vec::with_capacity((samples[0] * samples[1] * samples[2]) as usize);
+        for y in 0..samples[1] {
+            for z in 0..samples[2] {
+                for x in 0..samples[0] {
+                    let index = na::Vector3::new(x as f32, y as f32, z as f32) + na::Vector3::repeat(0.5);
+                    points.push(na::Point3::from(index.component_mul(&cell) - half_extents));
+                }
+            }
+        }
+        Self {
+            points,
+            cell_volume: cell.x * cell.y * cell.z,
+            cell_height: cell.y,
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.cell_volume * self.points.len() as f32
+    }
+}
+
+/// A box-shaped body with the hull it floats on.
+#[derive(Clone, Debug)]
+pub struct FloatingBody {
+    pub body: RigidBody,
+    pub hull: Hull,
+    pub half_extents: na::Vector3<f32>,
+    /// World space force applied at `thrust_point` every step, like an engine or a tow line.
+    pub thrust: na::Vector3<f32>,
+    /// Body space.
+    pub thrust_point: na::Point3<f32>,
+}
+
+impl FloatingBody {
+    pub fn cuboid(density: f32, half_extents: na::Vector3<f32>, position: na::Point3<f32>) -> Self {
+        let volume = 8.0 * half_extents.x * half_extents.y * half_extents.z;
+        Self {
+            body: RigidBody::cuboid(density * volume, half_extents, position),
+            hull: Hull::cuboid(half_extents, [3, 2, 3]),
+            half_extents,
+            thrust: na::Vector3::zeros(),
+            thrust_point: na::Point3::origin(),
+        }
+    }
+
+    /// Maps the unit cube onto the box.
+    pub fn to_model_matrix(&self) -> na::Matrix4<f32> {
+        self.body.isometry().to_homogeneous() * na::Matrix4::new_nonuniform_scaling(&self.half_extents)
+    }
+
+    /// Fraction of the hull below the surface.
+    pub fn submerged(&self, water: &impl HeightField) -> f32 {
+        let isometry = self.body.isometry();
+        let wet: f32 = self.hull.points.iter()
+            .map(|point| self.cell_submersion(water, &(isometry * point)))
+            .sum();
+        wet / self.hull.points.len() as f32
+    }
+
+    fn cell_submersion(&self, water: &impl HeightField, point: &na::Point3<f32>) -> f32 {
+        let depth = water.height_at(point.x, point.z) - point.y;
+        (depth / self.hull.cell_height + 0.5).clamp(0.0, 1.0)
+    }
+
+    fn apply_water_forces(&mut self, water: &impl HeightField, settings: &BuoyancySettings) {
+        let isometry = self.body.isometry();
+        let mut wet = 0.0;
+        for point in &self.hull.points {
+            let point = isometry * point;
+            let submersion = self.cell_submersion(water, &point);
+            if submersion == 0.0 {
+                continue;
+            }
+            wet += submersion;
+            let displaced = settings.water_density * self.hull.cell_volume * submersion;
+            let buoyancy = na::Vector3::y() * displaced * settings.gravity;
+            let drag = -self.body.point_velocity(&point) * displaced * settings.linear_drag;
+            self.body.apply_force_at_point(buoyancy + drag, &point);
+        }
+        let submerged = wet / self.hull.points.len() as f32;
+        let damping = -self.body.world_inertia_mul(&self.body.angular_velocity) * settings.angular_drag * submerged;
+        self.body.apply_torque(damping);
+    }
+}
+
+/// Floating bodies stepped at a fixed rate on a height field. The same updates on the same
+/// water always give the same transforms.
+pub struct Buoyancy {
+    settings: BuoyancySettings,
+    bodies: Vec<FloatingBody>,
+    fixed_step: FixedStep,
+}
+
+impl Buoyancy {
+    pub fn new(settings: BuoyancySettings) -> Self {
+        Self {
+            settings,
+            bodies: Vec::new(),
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &BuoyancySettings {
+        &self.settings
+    }
+
+    pub fn bodies(&self) -> &[FloatingBody] {
+        &self.bodies
+    }
+
+    pub fn bodies_mut(&mut self) -> &mut [FloatingBody] {
+        &mut self.bodies
+    }
+
+    pub fn add_body(&mut self, body: FloatingBody) -> usize {
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    /// Runs as many fixed steps as fit in the time passed, carrying the remainder over.
+    pub fn update(&mut self, water: &impl HeightField, dt: f32) {
+        for _ in 0..self.fixed_step.advance(dt, self.settings.time_step) {
+            self.step(water);
+        }
+    }
+
+    pub fn step(&mut self, water: &impl HeightField) {
+        let settings = self.settings;
+        for floating in &mut self.bodies {
+            floating.body.apply_force(na::Vector3::y() * -settings.gravity * floating.body.mass);
+            floating.apply_water_forces(water, &settings);
+            let thrust_point = floating.body.isometry() * floating.thrust_point;
+            floating.body.apply_force_at_point(floating.thrust, &thrust_point);
+            floating.body.integrate(settings.time_step);
+        }
+    }
+
+    pub fn model_matrices(&self) -> Vec<na::Matrix4<f32>> {
+        self.bodies.iter().map(FloatingBody::to_model_matrix).collect()
+    }
+}
@@ -4,6 +4,7 @@ use std::f32::consts::FRAC_PI_2;
 
 use bytemuck::{Pod, Zeroable};
 use nalgebra as na;
+use winit::{event::ElementState, keyboard::KeyCode};
 
 #[rustfmt::skip]
 const OPENGL_TO_WGPU_MATRIX: na::Matrix4<f32> = na::Matrix4::new(
@@ -13,22 +14,523 @@ const OPENGL_TO_WGPU_MATRIX: na::Matrix4<f32> = na::Matrix4::new(
     0.0, 0.0, 0.0, 1.0,
 );
 
+// Turns depth around, so the near plane is at 1 and the far plane at 0.
+#[rustfmt::skip]
+const REVERSE_Z_MATRIX: na::Matrix4<f32> = na::Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, -1.0, 1.0,
+    0.0, 0.0, 0.0, 1.0,
+);
+
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
 #[derive(Debug)]
 pub struct Camera(pub na::Isometry3<f32>);
 
+impl Camera {
+    /// Camera at `eye` turned `yaw` radians from looking along +x towards +z, and pitched `pitch`
+    /// radians up.
+    pub fn new(eye: na::Point3<f32>, yaw: f32, pitch: f32) -> Self {
+        Self(na::Isometry3::look_at_rh(&eye, &(eye + direction(yaw, pitch)), &na::Vector3::y()))
+    }
+
+    pub fn forward(&self) -> na::Vector3<f32> {
+        self.0.inverse_transform_vector(&-na::Vector3::z())
+    }
+
+    pub fn yaw(&self) -> f32 {
+        let forward = self.forward();
+        forward.z.atan2(forward.x)
+    }
+
+    pub fn pitch(&self) -> f32 {
+        // Not asin, which loses the last bit of pitch near straight up or down.
+        let forward = self.forward();
+        forward.y.atan2(forward.xz().norm())
+    }
+
+    /// World-space ray through a point in normalized device coordinates, from the near plane.
+    pub fn ray(&self, projection: &Projection, ndc: na::Point2<f32>) -> (na::Point3<f32>, na::Vector3<f32>) {
+        let inverse = (projection.to_matrix() * self.0.to_homogeneous())
+            .try_inverse()
+            .expect("Failed to invert view projection matrix");
+        let near = inverse.transform_point(&na::Point3::new(ndc.x, ndc.y, projection.near_depth()));
+        // Halfway is short of infinity, which an infinite projection puts the far end at.
+        let further = inverse.transform_point(&na::Point3::new(ndc.x, ndc.y, 0.5));
+        (near, (further - near).normalize())
+    }
+
+    pub fn eye(&self) -> na::Point3<f32> {
+        self.0.inverse() * na::Point3::origin()
+    }
+
+    /// View matrix of the camera mirrored about the horizontal plane at `height`.
+    pub fn reflected_view(&self, height: f32) -> na::Matrix4<f32> {
+        let mirror = na::Matrix4::new_translation(&na::Vector3::new(0.0, height, 0.0))
+            * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(1.0, -1.0, 1.0))
+            * na::Matrix4::new_translation(&na::Vector3::new(0.0, -height, 0.0));
+        self.0.to_homogeneous() * mirror
+    }
+}
+
+/// Everything the shaders know about the camera they are drawn from, uploaded every frame.
+/// Matches `Camera` in camera.wgsl, which every shader that draws from a camera starts with.
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-pub struct Projection {
-    pub aspect: f32,
-    pub fovy: f32,
-    pub z_near: f32,
-    pub z_far: f32,
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+    pub view_proj: [[f32; 4]; 4],
+    pub inverse_view: [[f32; 4]; 4],
+    pub inverse_proj: [[f32; 4]; 4],
+    pub inverse_view_proj: [[f32; 4]; 4],
+    pub eye: [f32; 3],
+    /// Depth buffer value at the near plane, 1 when depth is reversed.
+    pub near_depth: f32,
+}
+
+impl CameraUniform {
+    pub fn new(view: na::Matrix4<f32>, proj: na::Matrix4<f32>, near_depth: f32) -> Self {
+        let inverse_view = view.try_inverse().expect("Failed to invert view matrix");
+        let inverse_proj = proj.try_inverse().expect("Failed to invert projection matrix");
+        Self {
+            view: view.into(),
+            proj: proj.into(),
+            view_proj: (proj * view).into(),
+            inverse_view: inverse_view.into(),
+            inverse_proj: inverse_proj.into(),
+            inverse_view_proj: (inverse_view * inverse_proj).into(),
+            eye: inverse_view.transform_point(&na::Point3::origin()).into(),
+            near_depth,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, projection: &Projection) -> Self {
+        Self::new(camera.0.to_homogeneous(), projection.to_matrix(), projection.near_depth())
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Camera Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+        ],
+    };
+}
+
+fn direction(yaw: f32, pitch: f32) -> na::Vector3<f32> {
+    let (sin_yaw, cos_yaw) = yaw.sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.sin_cos();
+    na::Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+}
+
+/// An angle in whichever unit is handier.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Angle {
+    Degrees(f32),
+    Radians(f32),
+}
+
+impl Angle {
+    pub fn radians(self) -> f32 {
+        match self {
+            Self::Degrees(degrees) => degrees.to_radians(),
+            Self::Radians(radians) => radians,
+        }
+    }
+}
+
+/// How view space is mapped to clip space, which also decides which way the depth buffer runs.
+/// Angles are in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Depth runs from 0 at `z_near` to 1 at `z_far`.
+    Perspective {
+        aspect: f32,
+        fovy: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+    /// A box `height` metres tall, with depth from 0 at `z_near` to 1 at `z_far`.
+    Orthographic {
+        aspect: f32,
+        height: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+    /// Perspective without a far plane. Depth runs from 1 at `z_near` down towards 0 at infinity,
+    /// which spreads a float depth buffer's precision over the distance far more evenly than the
+    /// other way round. A fixed-point one gains nothing, see `Texture::depth_format`.
+    InfiniteReverseZ {
+        aspect: f32,
+        fovy: f32,
+        z_near: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::perspective(1.0, Angle::Degrees(45.0), 0.1, 100.0)
+    }
 }
 
 impl Projection {
-    pub fn to_matrix(&self) -> na::Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * na::Matrix4::new_perspective(self.aspect, self.fovy, self.z_near, self.z_far)
+    pub fn perspective(aspect: f32, fovy: Angle, z_near: f32, z_far: f32) -> Self {
+        Self::Perspective {
+            aspect,
+            fovy: fovy.radians(),
+            z_near,
+            z_far,
+        }
+    }
+
+    pub fn orthographic(aspect: f32, height: f32, z_near: f32, z_far: f32) -> Self {
+        Self::Orthographic {
+            aspect,
+            height,
+            z_near,
+            z_far,
+        }
+    }
+
+    pub fn infinite_reverse_z(aspect: f32, fovy: Angle, z_near: f32) -> Self {
+        Self::InfiniteReverseZ {
+            aspect,
+            fovy: fovy.radians(),
+            z_near,
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        match *self {
+            Self::Perspective { aspect, .. } | Self::Orthographic { aspect, .. } | Self::InfiniteReverseZ { aspect, .. } => aspect,
+        }
+    }
+
+    pub fn set_aspect(&mut self, value: f32) {
+        match self {
+            Self::Perspective { aspect, .. } | Self::Orthographic { aspect, .. } | Self::InfiniteReverseZ { aspect, .. } => *aspect = value,
+        }
+    }
+
+    pub fn z_near(&self) -> f32 {
+        match *self {
+            Self::Perspective { z_near, .. } | Self::Orthographic { z_near, .. } | Self::InfiniteReverseZ { z_near, .. } => z_near,
+        }
+    }
+
+    /// Infinite when there is no far plane.
+    pub fn z_far(&self) -> f32 {
+        match *self {
+            Self::Perspective { z_far, .. } | Self::Orthographic { z_far, .. } => z_far,
+            Self::InfiniteReverseZ { .. } => f32::INFINITY,
+        }
+    }
+
+    /// Whether depth is 1 at the near plane and shrinks with distance.
+    pub fn reversed_depth(&self) -> bool {
+        matches!(self, Self::InfiniteReverseZ { .. })
+    }
+
+    pub fn near_depth(&self) -> f32 {
+        if self.reversed_depth() { 1.0 } else { 0.0 }
+    }
+
+    /// What the depth buffer is cleared to, the depth of the far plane.
+    pub fn far_depth(&self) -> f32 {
+        1.0 - self.near_depth()
+    }
+
+    /// Depth test passing what is nearer than the depth buffer.
+    pub fn depth_compare(&self) -> wgpu::CompareFunction {
+        if self.reversed_depth() { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less }
+    }
+
+    /// Depth test passing what is as near as the depth buffer or nearer.
+    pub fn depth_compare_equal(&self) -> wgpu::CompareFunction {
+        if self.reversed_depth() { wgpu::CompareFunction::GreaterEqual } else { wgpu::CompareFunction::LessEqual }
+    }
+
+    /// Depth buffer value at a view-space distance along the view axis. Mirrors `device_depth` in
+    /// `fluid_surface.wgsl`.
+    pub fn depth(&self, distance: f32) -> f32 {
+        let clip = self.to_matrix() * na::Vector4::new(0.0, 0.0, -distance, 1.0);
+        clip.z / clip.w
+    }
+
+    pub fn to_matrix(self) -> na::Matrix4<f32> {
+        self.to_device(self.to_opengl_matrix())
+    }
+
+    /// Projection with its near plane replaced by `clip_plane`, a view-space plane whose positive
+    /// side is kept. Uses Lengyel's oblique frustum, which skews the far plane instead of adding one.
+    pub fn to_oblique_matrix(self, clip_plane: na::Vector4<f32>) -> na::Matrix4<f32> {
+        let mut projection = self.to_opengl_matrix();
+        // The frustum corner furthest behind the plane, which has to stay inside the far plane.
+        let corner = projection.try_inverse().expect("Failed to invert projection matrix")
+            * na::Vector4::new(clip_plane.x.signum(), clip_plane.y.signum(), 1.0, 1.0);
+        let scaled = clip_plane * (2.0 / clip_plane.dot(&corner));
+        let near = scaled.transpose() - projection.row(3);
+        projection.set_row(2, &near);
+        self.to_device(projection)
+    }
+
+    // With depth from -1 at the near plane to 1 at the far plane, which the oblique frustum is
+    // worked out in.
+    fn to_opengl_matrix(self) -> na::Matrix4<f32> {
+        match self {
+            Self::Perspective { aspect, fovy, z_near, z_far } => na::Matrix4::new_perspective(aspect, fovy, z_near, z_far),
+            Self::Orthographic { aspect, height, z_near, z_far } => {
+                let (x, y) = (0.5 * height * aspect, 0.5 * height);
+                na::Matrix4::new_orthographic(-x, x, -y, y, z_near, z_far)
+            }
+            Self::InfiniteReverseZ { aspect, fovy, z_near } => {
+                let f = 1.0 / (0.5 * fovy).tan();
+                #[rustfmt::skip]
+                let matrix = na::Matrix4::new(
+                    f / aspect, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, -1.0, -2.0 * z_near,
+                    0.0, 0.0, -1.0, 0.0,
+                );
+                matrix
+            }
+        }
+    }
+
+    fn to_device(self, opengl: na::Matrix4<f32>) -> na::Matrix4<f32> {
+        let matrix = OPENGL_TO_WGPU_MATRIX * opengl;
+        if self.reversed_depth() { REVERSE_Z_MATRIX * matrix } else { matrix }
+    }
+}
+
+/// Orthographic view of a directional light over a box of the scene, for light- or shadow-space
+/// lookups. The light travels along `-direction`.
+#[derive(Clone, Copy, Debug)]
+pub struct LightCamera {
+    pub direction: na::Unit<na::Vector3<f32>>,
+    pub center: na::Point3<f32>,
+    /// Half the side of the square the light covers, seen from the light.
+    pub half_size: f32,
+    /// Depth of the covered box, centred on `center`.
+    pub depth: f32,
+}
+
+impl LightCamera {
+    pub fn to_view_matrix(self) -> na::Matrix4<f32> {
+        let eye = self.center + self.direction.into_inner() * self.depth * 0.5;
+        // Any up vector works for an orthographic light, as long as it is not parallel to it.
+        let up = if self.direction.y.abs() > 0.99 { na::Vector3::z() } else { na::Vector3::y() };
+        na::Matrix4::look_at_rh(&eye, &self.center, &up)
+    }
+
+    pub fn to_projection_matrix(self) -> na::Matrix4<f32> {
+        let s = self.half_size;
+        OPENGL_TO_WGPU_MATRIX * na::Matrix4::new_orthographic(-s, s, -s, s, 0.0, self.depth)
+    }
+
+    pub fn to_matrix(self) -> na::Matrix4<f32> {
+        self.to_projection_matrix() * self.to_view_matrix()
+    }
+
+    /// For drawing as seen from the light, whose depth runs forwards.
+    pub fn to_camera_uniform(self) -> CameraUniform {
+        CameraUniform::new(self.to_view_matrix(), self.to_projection_matrix(), 0.0)
+    }
+
+    /// World to light texture space, with xy in [0, 1] from the top left like a render target
+    /// and z the depth from the light.
+    pub fn to_shadow_matrix(self) -> na::Matrix4<f32> {
+        #[rustfmt::skip]
+        let bias = na::Matrix4::new(
+            0.5, 0.0, 0.0, 0.5,
+            0.0, -0.5, 0.0, 0.5,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        );
+        bias * self.to_matrix()
+    }
+}
+
+/// Fly-through controls: WASD moves along the view, space and shift straight up and down, and the
+/// mouse turns the camera, which can look at most straight up or down.
+#[derive(Debug)]
+pub struct CameraController {
+    /// Metres per second.
+    pub speed: f32,
+    /// Radians per pixel the mouse moves.
+    pub sensitivity: f32,
+    forward: f32,
+    backward: f32,
+    left: f32,
+    right: f32,
+    up: f32,
+    down: f32,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+}
+
+impl CameraController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            speed,
+            sensitivity,
+            forward: 0.0,
+            backward: 0.0,
+            left: 0.0,
+            right: 0.0,
+            up: 0.0,
+            down: 0.0,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+        }
+    }
+
+    /// Whether the key is one of the controls.
+    pub fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> bool {
+        let amount = if state.is_pressed() { 1.0 } else { 0.0 };
+        match key {
+            KeyCode::KeyW | KeyCode::ArrowUp => self.forward = amount,
+            KeyCode::KeyS | KeyCode::ArrowDown => self.backward = amount,
+            KeyCode::KeyA | KeyCode::ArrowLeft => self.left = amount,
+            KeyCode::KeyD | KeyCode::ArrowRight => self.right = amount,
+            KeyCode::Space => self.up = amount,
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => self.down = amount,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Mouse movement in pixels since the last update, with y growing downwards.
+    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
+        self.rotate_horizontal += dx as f32;
+        self.rotate_vertical += dy as f32;
+    }
+
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        let yaw = camera.yaw() + self.rotate_horizontal * self.sensitivity;
+        let pitch = (camera.pitch() - self.rotate_vertical * self.sensitivity).clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        let right = na::Vector3::new(-yaw.sin(), 0.0, yaw.cos());
+        let step = self.speed * dt;
+        let eye = camera.eye()
+            + direction(yaw, pitch) * (self.forward - self.backward) * step
+            + right * (self.right - self.left) * step
+            + na::Vector3::y() * (self.up - self.down) * step;
+        *camera = Camera::new(eye, yaw, pitch);
+    }
+}
+
+/// Which controller moves the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Fly,
+    Orbit,
+}
+
+/// Turns the camera around a target point, zooms towards it and pans it across the view. With
+/// `smoothing`, the camera eases towards where the input puts it instead of jumping there.
+#[derive(Debug)]
+pub struct OrbitController {
+    /// Radians per pixel the mouse moves, and the fraction of the distance panned per pixel.
+    pub sensitivity: f32,
+    /// Fraction of the distance zoomed in per line scrolled.
+    pub zoom_step: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Seconds for the camera to cover most of the way to where it is going, or 0 to follow at once.
+    pub smoothing: f32,
+    target: na::Point3<f32>,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    // What the camera shows now, easing towards the above.
+    current_target: na::Point3<f32>,
+    current_distance: f32,
+    current_yaw: f32,
+    current_pitch: f32,
+}
+
+impl OrbitController {
+    pub fn new(sensitivity: f32, zoom_step: f32, smoothing: f32) -> Self {
+        Self {
+            sensitivity,
+            zoom_step,
+            min_distance: 0.5,
+            max_distance: 100.0,
+            smoothing,
+            target: na::Point3::origin(),
+            distance: 10.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            current_target: na::Point3::origin(),
+            current_distance: 10.0,
+            current_yaw: 0.0,
+            current_pitch: 0.0,
+        }
+    }
+
+    pub fn target(&self) -> na::Point3<f32> {
+        self.target
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Takes over from wherever `camera` is, orbiting the point `distance` ahead of it so the view
+    /// does not change.
+    pub fn attach(&mut self, camera: &Camera, distance: f32) {
+        self.distance = distance.clamp(self.min_distance, self.max_distance);
+        self.target = camera.eye() + camera.forward() * self.distance;
+        self.yaw = camera.yaw();
+        self.pitch = camera.pitch();
+        self.current_target = self.target;
+        self.current_distance = self.distance;
+        self.current_yaw = self.yaw;
+        self.current_pitch = self.pitch;
+    }
+
+    /// Turns around the target by mouse movement in pixels, with y growing downwards. Turns the same
+    /// way as `CameraController`, so the view moves alike in both modes.
+    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
+        self.yaw += dx as f32 * self.sensitivity;
+        self.pitch = (self.pitch - dy as f32 * self.sensitivity).clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+    }
+
+    /// Drags the target with the mouse, so the scene follows the cursor.
+    pub fn process_pan(&mut self, dx: f64, dy: f64) {
+        let forward = direction(self.yaw, self.pitch);
+        let right = na::Vector3::new(-self.yaw.sin(), 0.0, self.yaw.cos());
+        let up = right.cross(&forward);
+        let scale = self.sensitivity * self.distance;
+        self.target += (-right * dx as f32 + up * dy as f32) * scale;
+    }
+
+    /// Zooms in for positive lines scrolled, by the same fraction of the distance per line.
+    pub fn process_scroll(&mut self, lines: f32) {
+        self.distance = (self.distance * (1.0 - self.zoom_step).powf(lines)).clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        let t = if self.smoothing > 0.0 { 1.0 - (-dt / self.smoothing).exp() } else { 1.0 };
+        self.current_target += (self.target - self.current_target) * t;
+        self.current_distance += (self.distance - self.current_distance) * t;
+        self.current_yaw += (self.yaw - self.current_yaw) * t;
+        self.current_pitch += (self.pitch - self.current_pitch) * t;
+        let eye = self.current_target - direction(self.current_yaw, self.current_pitch) * self.current_distance;
+        *camera = Camera::new(eye, self.current_yaw, self.current_pitch);
     }
 }
@@ -0,0 +1,11 @@
+// Matches CameraUniform in camera.rs. Each shader binds it where its own layout puts it.
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inverse_view: mat4x4<f32>,
+    inverse_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    eye: vec3<f32>,
+    near_depth: f32,
+}
@@ -0,0 +1,198 @@
+#![allow(dead_code)]
+
+use crate::{
+    ripple::RippleSettings,
+    texture::Texture,
+    vertex::{ModelInstance, ModelVertex, Vertex as _},
+};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt as _;
+
+const VOLUME_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Counts the hull faces in front of the water, up for front faces and down for back faces,
+/// so the water is inside a hull wherever the count ends up above zero.
+pub const HULL_MASK_STENCIL: wgpu::StencilState = wgpu::StencilState {
+    front: wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Always,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::IncrementWrap,
+    },
+    back: wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Always,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::DecrementWrap,
+    },
+    read_mask: 0xff,
+    write_mask: 0xff,
+};
+
+/// Draws water only where the hull mask left the stencil at the reference, zero.
+pub const OUTSIDE_HULLS_STENCIL: wgpu::StencilState = wgpu::StencilState {
+    front: wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Equal,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::Keep,
+    },
+    back: wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Equal,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::Keep,
+    },
+    read_mask: 0xff,
+    write_mask: 0,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct VolumeUniform {
+    size: f32,
+    water_level: f32,
+    _padding: [f32; 2],
+}
+
+/// How deep the floating bodies sit below the water level, seen from above over the ripple
+/// area, this frame and the last. The ripples are raised by however much deeper a body sank
+/// into each cell in between, so bodies push water aside as they move and bob.
+pub struct SubmergedVolume {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pub volume: Texture,
+    pub previous: Texture,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl SubmergedVolume {
+    pub fn new(device: &wgpu::Device, ripple_settings: &RippleSettings, water_level: f32) -> Self {
+        let n = ripple_settings.resolution;
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Submerged Volume Uniform Buffer"),
+            contents: bytemuck::bytes_of(&VolumeUniform {
+                size: ripple_settings.size,
+                water_level,
+                _padding: [0.0; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Submerged Volume Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    count: None,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Submerged Volume Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let volume = Texture::create_color_target(device, n, n, VOLUME_FORMAT, Some("Submerged Volume"));
+        let previous = Texture::create_color_target(device, n, n, VOLUME_FORMAT, Some("Previous Submerged Volume"));
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Submerged Volume Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("submerged.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Submerged Volume Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[ModelVertex::LAYOUT, ModelInstance::LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                // The faces above and below add up to the thickness in between.
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: VOLUME_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::RED,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            uniform_buffer,
+            bind_group,
+            volume,
+            previous,
+            pipeline,
+        }
+    }
+
+    /// Keeps the last volume and renders the current one from the bodies' instanced mesh.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        vertices: wgpu::BufferSlice,
+        indices: wgpu::BufferSlice,
+        index_count: u32,
+        instances: wgpu::BufferSlice,
+        instance_count: u32,
+    ) {
+        encoder.copy_texture_to_texture(
+            self.volume.texture.as_image_copy(),
+            self.previous.texture.as_image_copy(),
+            self.volume.texture.size(),
+        );
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Submerged Volume Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.volume.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertices);
+        render_pass.set_vertex_buffer(1, instances);
+        render_pass.set_index_buffer(indices, wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..index_count, 0, 0..instance_count);
+    }
+}
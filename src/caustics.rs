@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+
+use crate::{camera::LightCamera, texture::Texture};
+
+use wgpu::util::DeviceExt as _;
+
+/// Caustic light under the water, photon-mapped from the surface into a texture seen from the
+/// sun. A texel of 1 receives as much light as it would under flat water.
+pub struct Caustics {
+    light: LightCamera,
+    light_buffer: wgpu::Buffer,
+    /// Binds the light where the water shader expects its camera.
+    pub light_bind_group: wgpu::BindGroup,
+    shadow_buffer: wgpu::Buffer,
+    pub texture: Texture,
+}
+
+impl Caustics {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+    /// `camera_layout` is the layout of the `CameraUniform` the scene shaders use.
+    pub fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        light: LightCamera,
+        resolution: u32,
+    ) -> Self {
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Caustic Light Buffer"),
+            contents: bytemuck::bytes_of(&light.to_camera_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Caustic Light Bind Group"),
+            layout: camera_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: light_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let shadow_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Caustic Shadow Buffer"),
+            contents: bytemuck::bytes_of(&light.to_shadow_matrix()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let texture = Texture::create_color_target(device, resolution, resolution, Self::FORMAT, Some("Caustic Texture"));
+
+        Self {
+            light,
+            light_buffer,
+            light_bind_group,
+            shadow_buffer,
+            texture,
+        }
+    }
+
+    pub fn light(&self) -> &LightCamera {
+        &self.light
+    }
+
+    pub fn set_light(&mut self, queue: &wgpu::Queue, light: LightCamera) {
+        self.light = light;
+        queue.write_buffer(&self.light_buffer, 0, bytemuck::bytes_of(&light.to_camera_uniform()));
+        queue.write_buffer(&self.shadow_buffer, 0, bytemuck::bytes_of(&light.to_shadow_matrix()));
+    }
+
+    pub fn create_bind_group(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Caustic Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.shadow_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.texture.sampler),
+                },
+            ],
+        })
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Caustic Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            },
+        ],
+    };
+}
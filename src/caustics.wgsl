@@ -0,0 +1,40 @@
+// Photon-maps the water grid onto a plane below the surface, following Evan Wallace's WebGL
+// water: each triangle is refracted onto the plane, and its brightness is the ratio of its area
+// under flat water to its refracted area. Appended to water.wgsl, with `camera` bound to the light.
+
+// Depth below the rest level of the plane the caustics focus on.
+const CAUSTIC_DEPTH: f32 = 2.0;
+const AIR_TO_WATER: f32 = 1.0 / 1.33;
+
+struct Photon {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) flat_hit: vec3<f32>,
+    @location(1) hit: vec3<f32>,
+}
+
+fn hit_plane(origin: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    return origin + direction * ((origin.y + CAUSTIC_DEPTH) / -direction.y);
+}
+
+@vertex
+fn vs_caustics(vertex: Vertex) -> Photon {
+    let p = vertex.pos.xz;
+    let surface = displace(p);
+    let swell = normalize(surface.normal);
+    let detail = textureSampleLevel(ocean_normal, ocean_sampler, ocean_uv(p), 0.0).xyz;
+    let slope = swell.xz / swell.y + detail.xz / max(detail.y, 0.001) - ripple_at(p).yz;
+    let normal = normalize(vec3(slope.x, 1.0, slope.y));
+
+    var photon: Photon;
+    photon.flat_hit = hit_plane(vec3(p.x, 0.0, p.y), refract(-SUN_DIRECTION, vec3(0.0, 1.0, 0.0), AIR_TO_WATER));
+    photon.hit = hit_plane(surface.position, refract(-SUN_DIRECTION, normal, AIR_TO_WATER));
+    photon.pos = camera.view_proj * vec4(photon.hit, 1.0);
+    return photon;
+}
+
+@fragment
+fn fs_caustics(photon: Photon) -> @location(0) vec4<f32> {
+    let flat_area = length(dpdx(photon.flat_hit)) * length(dpdy(photon.flat_hit));
+    let area = length(dpdx(photon.hit)) * length(dpdy(photon.hit));
+    return vec4(flat_area / max(area, 1e-6), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,23 @@
+#![allow(dead_code)]
+
+/// Builds a compute pipeline from WGSL sources concatenated in order, with the entry point `main`.
+/// Shared declarations go in the first source, since WGSL has no includes.
+pub fn create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: Option<&wgpu::PipelineLayout>,
+    label: &str,
+    sources: &[&str],
+) -> wgpu::ComputePipeline {
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(sources.concat().into()),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout,
+        module: &shader_module,
+        entry_point: Some("main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    })
+}
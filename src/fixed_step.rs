@@ -0,0 +1,30 @@
+#![allow(dead_code)]
+
+// Steps a single update may take before the rest of its time is dropped, so a stalled frame
+// does not make the next one even slower.
+pub const MAX_STEPS_PER_UPDATE: u32 = 8;
+
+/// Splits the time passed into fixed steps, carrying the remainder over to the next update.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixedStep {
+    accumulator: f32,
+}
+
+impl FixedStep {
+    /// How many steps of `time_step` seconds fit in `dt` more seconds, at most
+    /// `MAX_STEPS_PER_UPDATE`.
+    pub fn advance(&mut self, dt: f32, time_step: f32) -> u32 {
+        self.accumulator += dt;
+        let steps = (self.accumulator / time_step) as u32;
+        self.accumulator -= steps as f32 * time_step;
+        if steps > MAX_STEPS_PER_UPDATE {
+            self.accumulator = 0.0;
+            return MAX_STEPS_PER_UPDATE;
+        }
+        steps
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
@@ -0,0 +1,46 @@
+const SUN_DIRECTION: vec3<f32> = vec3(0.4082483, 0.8164966, 0.4082483);
+const WOOD_COLOR: vec3<f32> = vec3(0.45, 0.3, 0.16);
+const PLANKS: f32 = 4.0;
+
+struct Vertex {
+    @location(0) pos: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+}
+
+struct Instance {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> Fragment {
+    let model = mat4x4(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    // The model is a rotation times a scale, so dividing by the squared scale turns it into
+    // the inverse transpose for normals.
+    let scale = vec3(dot(model[0].xyz, model[0].xyz), dot(model[1].xyz, model[1].xyz), dot(model[2].xyz, model[2].xyz));
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * model * vec4(vertex.pos, 1.0);
+    fragment.normal = (model * vec4(vertex.normal / scale, 0.0)).xyz;
+    fragment.uv = vertex.uv;
+    return fragment;
+}
+
+@fragment
+fn fs_main(fragment: Fragment) -> @location(0) vec4<f32> {
+    let seam = fract(fragment.uv.y * PLANKS);
+    let plank = 0.75 + 0.25 * smoothstep(0.0, 0.08, seam) * smoothstep(1.0, 0.92, seam);
+    let diffuse = max(dot(normalize(fragment.normal), SUN_DIRECTION), 0.0);
+    return vec4(WOOD_COLOR * plank * (0.3 + 0.7 * diffuse), 1.0);
+}
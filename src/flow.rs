@@ -0,0 +1,352 @@
+#![allow(dead_code)]
+
+use crate::{
+    compute::create_compute_pipeline,
+    shallow_water::{GpuShallowWater, ShallowWater},
+    texture::Texture,
+};
+
+use std::f32::consts::TAU;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+const WORKGROUP_SIZE: u32 = 8;
+const DETAIL_RESOLUTION: u32 = 128;
+const DETAIL_WAVES: u32 = 16;
+const FOAM_WAVES: u32 = 8;
+// Root mean square slope of the detail normals.
+const DETAIL_SLOPE: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FlowSettings {
+    /// World xz position of the centre of the square the map covers.
+    pub center: na::Point2<f32>,
+    /// Side length in metres of the square the map covers.
+    pub size: f32,
+    /// Speed in m/s of a full-scale flow vector. Texels encode the flow from -1 to 1 in each channel.
+    pub speed: f32,
+    /// Repeats per metre of the advected detail normals and foam.
+    pub tiling: f32,
+}
+
+impl Default for FlowSettings {
+    fn default() -> Self {
+        Self {
+            center: na::Point2::origin(),
+            size: 16.0,
+            speed: 2.0,
+            tiling: 0.5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FlowUniform {
+    center: [f32; 2],
+    size: f32,
+    speed: f32,
+    tiling: f32,
+    time: f32,
+    _padding: [f32; 2],
+}
+
+impl FlowUniform {
+    fn new(settings: FlowSettings, time: f32) -> Self {
+        Self {
+            center: settings.center.into(),
+            size: settings.size,
+            speed: settings.speed,
+            tiling: settings.tiling,
+            time,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+// Writes the velocity of a shallow-water simulation into the flow map every frame.
+struct FlowGenerator {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    resolution: u32,
+}
+
+/// A flow map over a square of water, with the detail normals and foam it advects. The water
+/// shader blends two copies of the detail, each dragged along the flow and reset half a cycle
+/// apart, so the texture keeps moving without stretching.
+pub struct FlowMap {
+    settings: FlowSettings,
+    uniform_buffer: wgpu::Buffer,
+    pub texture: Texture,
+    sampler: wgpu::Sampler,
+    detail: Texture,
+    generator: Option<FlowGenerator>,
+    time: f32,
+}
+
+impl FlowMap {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    /// Loads a flow map from an encoded image, its first row along -z.
+    pub fn from_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8], settings: FlowSettings) -> Self {
+        let texture = Texture::from_bytes(device, queue, bytes, Self::FORMAT, Some("Flow Map"));
+        Self::new(device, queue, texture, settings)
+    }
+
+    /// Flow map of the current velocity of `water`, covering its simulated area.
+    pub fn from_shallow_water(device: &wgpu::Device, queue: &wgpu::Queue, water: &ShallowWater, settings: FlowSettings) -> Self {
+        let settings = FlowSettings {
+            center: water.settings().center,
+            size: water.settings().size,
+            ..settings
+        };
+        let image = velocity_image(water, settings.speed);
+        let texture = Texture::from_image(device, queue, &image.into(), Self::FORMAT, Some("Flow Map"));
+        Self::new(device, queue, texture, settings)
+    }
+
+    /// Flow map following `water` on the GPU, refreshed by every `dispatch`.
+    pub fn from_gpu_shallow_water(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        water: &GpuShallowWater,
+        settings: FlowSettings,
+    ) -> Self {
+        let settings = FlowSettings {
+            center: water.settings().center,
+            size: water.settings().size,
+            ..settings
+        };
+        let resolution = water.settings().resolution;
+        let texture = Texture::create_storage_texture(device, resolution, resolution, Self::FORMAT, Some("Flow Map"));
+        let mut flow = Self::new(device, queue, texture, settings);
+
+        let pipeline = create_compute_pipeline(device, None, "Flow Velocity", &[include_str!("flow_velocity.wgsl")]);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Flow Velocity Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: flow.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&water.output.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&flow.texture.view),
+                },
+            ],
+        });
+        flow.generator = Some(FlowGenerator {
+            pipeline,
+            bind_group,
+            resolution,
+        });
+        flow
+    }
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, texture: Texture, settings: FlowSettings) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Flow Uniform Buffer"),
+            contents: bytemuck::bytes_of(&FlowUniform::new(settings, 0.0)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // The map is not repeated, so flow past its edges is that of the nearest edge texel.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Flow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let detail = Texture {
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Flow Detail Sampler"),
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            ..Texture::from_image(device, queue, &detail_image(DETAIL_RESOLUTION).into(), Self::FORMAT, Some("Flow Detail"))
+        };
+
+        Self {
+            settings,
+            uniform_buffer,
+            texture,
+            sampler,
+            detail,
+            generator: None,
+            time: 0.0,
+        }
+    }
+
+    pub fn settings(&self) -> &FlowSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: FlowSettings) {
+        self.settings = settings;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&FlowUniform::new(settings, self.time)));
+    }
+
+    /// Re-encodes the velocity of `water` into a map made by `from_shallow_water`.
+    pub fn write_shallow_water(&self, queue: &wgpu::Queue, water: &ShallowWater) {
+        let image = velocity_image(water, self.settings.speed);
+        queue.write_texture(
+            self.texture.texture.as_image_copy(),
+            &image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(image.width() * 4),
+                rows_per_image: Some(image.height()),
+            },
+            self.texture.texture.size(),
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32) {
+        self.time = time;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&FlowUniform::new(self.settings, time)));
+    }
+
+    /// Refreshes a map following a GPU simulation. Does nothing for other maps.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(generator) = &self.generator else {
+            return;
+        };
+        let groups = generator.resolution.div_ceil(WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Flow Velocity Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&generator.pipeline);
+        compute_pass.set_bind_group(0, &generator.bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+    }
+
+    pub fn create_bind_group(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Flow Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&self.detail.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&self.detail.sampler),
+                },
+            ],
+        })
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Flow Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            },
+        ],
+    };
+}
+
+// Same encoding as the GPU generator: velocity over `speed`, clamped and mapped to [0, 1].
+fn velocity_image(water: &ShallowWater, speed: f32) -> image::RgbaImage {
+    let n = water.settings().resolution;
+    image::RgbaImage::from_fn(n, n, |x, z| {
+        let flow = water.velocity(x, z) / speed;
+        let [r, g] = [flow.x, flow.y].map(|f| ((f.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8);
+        image::Rgba([r, g, 0, 255])
+    })
+}
+
+// Tileable detail: rg and b hold the normal of a sum of waves with whole numbers of periods
+// across the texture, with xz mapped to [0, 1], and a holds foam streaks.
+fn detail_image(resolution: u32) -> image::RgbaImage {
+    let waves = |count: u32, seed: u32| {
+        (0..count).map(move |i| {
+            let hash = (i + seed).wrapping_mul(2654435761);
+            let k = na::Vector2::new((hash % 13) as f32 - 6.0, ((hash >> 8) % 13) as f32 - 6.0);
+            let k = if k == na::Vector2::zeros() { na::Vector2::new(1.0, 0.0) } else { k };
+            let phase = (hash >> 16) as f32 / 65536.0 * TAU;
+            (k, phase)
+        })
+    };
+    let detail = waves(DETAIL_WAVES, 0).collect::<Vec<_>>();
+    let foam = waves(FOAM_WAVES, DETAIL_WAVES).collect::<Vec<_>>();
+
+    image::RgbaImage::from_fn(resolution, resolution, |x, y| {
+        let uv = na::Vector2::new(x as f32, y as f32) / resolution as f32;
+        // Amplitudes fall off with frequency, so every wave contributes the same slope.
+        let slope = detail.iter()
+            .map(|(k, phase)| k.normalize() * (TAU * k.dot(&uv) + phase).cos())
+            .sum::<na::Vector2<f32>>() * DETAIL_SLOPE / (DETAIL_WAVES as f32 * 0.5).sqrt();
+        let normal = na::Vector3::new(-slope.x, 1.0, -slope.y).normalize();
+        let streaks = foam.iter()
+            .map(|(k, phase)| (TAU * k.dot(&uv) + phase).sin())
+            .sum::<f32>() / (FOAM_WAVES as f32).sqrt();
+        let foam = ((streaks - 0.3) / 1.2).clamp(0.0, 1.0);
+        let unorm = |v: f32| ((v * 0.5 + 0.5) * 255.0).round() as u8;
+        image::Rgba([unorm(normal.x), unorm(normal.z), (normal.y * 255.0).round() as u8, (foam * 255.0).round() as u8])
+    })
+}
@@ -0,0 +1,42 @@
+// Seconds before each copy of the detail jumps back to where it started.
+const FLOW_CYCLE: f32 = 1.0;
+
+struct Flow {
+    center: vec2<f32>,
+    size: f32,
+    speed: f32,
+    tiling: f32,
+    time: f32,
+}
+
+@group(2) @binding(0)
+var<uniform> flow: Flow;
+@group(2) @binding(1)
+var flow_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var flow_sampler: sampler;
+// rg = normal xz and b = normal y, a = foam.
+@group(2) @binding(3)
+var detail_texture: texture_2d<f32>;
+@group(2) @binding(4)
+var detail_sampler: sampler;
+
+fn flow_velocity(p: vec2<f32>) -> vec2<f32> {
+    let uv = (p - flow.center) / flow.size + 0.5;
+    return (textureSample(flow_texture, flow_sampler, uv).xy * 2.0 - 1.0) * flow.speed;
+}
+
+// Detail normal and foam at world position `p`, carried along the flow. Two copies drift half a
+// cycle apart and each fades out as it jumps back, which hides the jump.
+fn flow_detail(p: vec2<f32>) -> vec4<f32> {
+    let velocity = flow_velocity(p);
+    let phase = fract(flow.time / FLOW_CYCLE);
+    let first = textureSample(detail_texture, detail_sampler, (p - velocity * phase * FLOW_CYCLE) * flow.tiling);
+    let second = textureSample(
+        detail_texture,
+        detail_sampler,
+        (p - velocity * fract(phase + 0.5) * FLOW_CYCLE) * flow.tiling + 0.5,
+    );
+    let detail = mix(first, second, abs(1.0 - 2.0 * phase));
+    return vec4(normalize(vec3(detail.x * 2.0 - 1.0, detail.z, detail.y * 2.0 - 1.0)), detail.w);
+}
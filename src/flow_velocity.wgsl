@@ -0,0 +1,24 @@
+struct Flow {
+    center: vec2<f32>,
+    size: f32,
+    speed: f32,
+    tiling: f32,
+    time: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> flow: Flow;
+// x = surface height, y = depth, zw = velocity.
+@group(0) @binding(1)
+var water_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var output: texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(output)) {
+        return;
+    }
+    let velocity = textureLoad(water_texture, id.xy, 0).zw;
+    textureStore(output, id.xy, vec4(clamp(velocity / flow.speed, vec2(-1.0), vec2(1.0)) * 0.5 + 0.5, 0.0, 1.0));
+}
@@ -0,0 +1,63 @@
+// Bilateral blur of the fluid depth along one screen axis, run across and then down the screen.
+// Depths far apart are not averaged, so separate sheets of fluid keep their silhouettes.
+
+// Radius in pixels the blur is capped at, close to the camera.
+const MAX_FILTER_RADIUS: i32 = 16;
+
+struct FluidSurface {
+    focal_length: f32,
+    filter_radius: f32,
+    depth_falloff: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> fluid: FluidSurface;
+// Bits of the view depth, or zero where no particle was drawn.
+@group(0) @binding(1)
+var input: texture_2d<u32>;
+
+// A single triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+fn depth_at(pixel: vec2<i32>) -> f32 {
+    return bitcast<f32>(textureLoad(input, pixel, 0).r);
+}
+
+fn smooth_depth(pixel: vec2<i32>, step: vec2<i32>) -> u32 {
+    let depth = depth_at(pixel);
+    if depth <= 0.0 {
+        return 0u;
+    }
+    // The blur covers the same distance in the world however far away the fluid is.
+    let radius = min(i32(fluid.filter_radius * fluid.focal_length / depth), MAX_FILTER_RADIUS);
+    let sigma = max(0.5 * f32(radius), 0.5);
+    let size = vec2<i32>(textureDimensions(input));
+    var sum = 0.0;
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i++) {
+        let sample = depth_at(clamp(pixel + step * i, vec2(0), size - 1));
+        if sample <= 0.0 {
+            continue;
+        }
+        let spatial = f32(i) / sigma;
+        let range = (sample - depth) / fluid.depth_falloff;
+        let weight = exp(-0.5 * (spatial * spatial + range * range));
+        sum += sample * weight;
+        weight_sum += weight;
+    }
+    return bitcast<u32>(sum / weight_sum);
+}
+
+@fragment
+fn fs_horizontal(@builtin(position) pos: vec4<f32>) -> @location(0) u32 {
+    return smooth_depth(vec2<i32>(pos.xy), vec2(1, 0));
+}
+
+@fragment
+fn fs_vertical(@builtin(position) pos: vec4<f32>) -> @location(0) u32 {
+    return smooth_depth(vec2<i32>(pos.xy), vec2(0, 1));
+}
@@ -0,0 +1,506 @@
+#![allow(dead_code)]
+
+use crate::{
+    camera::Projection,
+    sph::SphParticle,
+    texture::Texture,
+    vertex::Vertex as _,
+};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt as _;
+
+// Holds the bits of a float, as 32-bit float targets are not renderable everywhere.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+const THICKNESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+// Matches PARTICLE_RADIUS in sph.wgsl.
+const PARTICLE_RADIUS: f32 = 0.04;
+// Distance in metres the depth is blurred over, enough to merge neighbouring particles.
+const FILTER_RADIUS: f32 = 3.0 * PARTICLE_RADIUS;
+// Depths further apart than this are kept from blurring into each other.
+const DEPTH_FALLOFF: f32 = 2.0 * PARTICLE_RADIUS;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FluidSurfaceUniform {
+    // Pixels per metre at a metre from the camera.
+    focal_length: f32,
+    filter_radius: f32,
+    depth_falloff: f32,
+    _padding: f32,
+}
+
+/// Renders particle fluid as a continuous surface in screen space: the particles' depth and
+/// thickness are splatted, the depth is smoothed with a bilateral blur, and the composite pass
+/// rebuilds normals from it and shades it with the water's refraction and reflection.
+pub struct FluidSurface {
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    depth: Texture,
+    depth_buffer: Texture,
+    thickness: Texture,
+    blurred: Texture,
+    smoothed: Texture,
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup,
+    depth_pipeline: wgpu::RenderPipeline,
+    thickness_pipeline: wgpu::RenderPipeline,
+    horizontal_pipeline: wgpu::RenderPipeline,
+    vertical_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    particle_module: wgpu::ShaderModule,
+    composite_module: wgpu::ShaderModule,
+    particle_layout: wgpu::PipelineLayout,
+    composite_layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    height: u32,
+    far_depth: f32,
+}
+
+impl FluidSurface {
+    const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Fluid Surface Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Uint,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+        ],
+    };
+
+    /// `camera_layout` is the camera group the particles are drawn with, and `view_layout` the
+    /// water's view group, which the composite shares. Depth is tested the way `projection` runs it.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        camera_layout: &wgpu::BindGroupLayout,
+        view_layout: &wgpu::BindGroupLayout,
+        projection: &Projection,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fluid Surface Uniform Buffer"),
+            contents: bytemuck::bytes_of(&FluidSurfaceUniform {
+                focal_length: config.height as f32,
+                filter_radius: FILTER_RADIUS,
+                depth_falloff: DEPTH_FALLOFF,
+                _padding: 0.0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&Self::BIND_GROUP_LAYOUT_DESCRIPTOR);
+
+        let particle_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl([include_str!("camera.wgsl"), include_str!("sph.wgsl")].concat().into()),
+        });
+        let particle_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[camera_layout],
+            push_constant_ranges: &[],
+        });
+        // Every particle adds to the thickness, while only the nearest one's depth is kept.
+        let thickness_pipeline = create_pipeline(
+            device,
+            &particle_layout,
+            &particle_module,
+            "vs_main",
+            "fs_thickness",
+            &[SphParticle::LAYOUT],
+            THICKNESS_FORMAT,
+            Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+            None,
+        );
+
+        let smooth_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Smoothing Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("fluid_smooth.wgsl").into()),
+        });
+        let smooth_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let horizontal_pipeline = create_pipeline(
+            device,
+            &smooth_layout,
+            &smooth_module,
+            "vs_main",
+            "fs_horizontal",
+            &[],
+            DEPTH_FORMAT,
+            None,
+            None,
+        );
+        let vertical_pipeline = create_pipeline(
+            device,
+            &smooth_layout,
+            &smooth_module,
+            "vs_main",
+            "fs_vertical",
+            &[],
+            DEPTH_FORMAT,
+            None,
+            None,
+        );
+
+        let composite_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fluid Surface Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                [include_str!("camera.wgsl"), include_str!("water_shading.wgsl"), include_str!("fluid_surface.wgsl")]
+                    .concat()
+                    .into(),
+            ),
+        });
+        let composite_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[view_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let (depth_pipeline, composite_pipeline) = create_depth_tested_pipelines(
+            device,
+            &particle_module,
+            &particle_layout,
+            &composite_module,
+            &composite_layout,
+            config.format,
+            projection,
+        );
+
+        let targets = Targets::new(device, config);
+        let [horizontal_bind_group, vertical_bind_group, composite_bind_group] =
+            targets.create_bind_groups(device, &bind_group_layout, &uniform_buffer);
+
+        Self {
+            uniform_buffer,
+            bind_group_layout,
+            depth: targets.depth,
+            depth_buffer: targets.depth_buffer,
+            thickness: targets.thickness,
+            blurred: targets.blurred,
+            smoothed: targets.smoothed,
+            horizontal_bind_group,
+            vertical_bind_group,
+            composite_bind_group,
+            depth_pipeline,
+            thickness_pipeline,
+            horizontal_pipeline,
+            vertical_pipeline,
+            composite_pipeline,
+            particle_module,
+            composite_module,
+            particle_layout,
+            composite_layout,
+            format: config.format,
+            height: config.height,
+            far_depth: projection.far_depth(),
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        let targets = Targets::new(device, config);
+        [self.horizontal_bind_group, self.vertical_bind_group, self.composite_bind_group] =
+            targets.create_bind_groups(device, &self.bind_group_layout, &self.uniform_buffer);
+        self.depth = targets.depth;
+        self.depth_buffer = targets.depth_buffer;
+        self.thickness = targets.thickness;
+        self.blurred = targets.blurred;
+        self.smoothed = targets.smoothed;
+        self.height = config.height;
+    }
+
+    /// Rebuilds the depth-tested passes, for a projection that may run depth the other way.
+    pub fn set_projection(&mut self, device: &wgpu::Device, projection: &Projection) {
+        (self.depth_pipeline, self.composite_pipeline) = create_depth_tested_pipelines(
+            device,
+            &self.particle_module,
+            &self.particle_layout,
+            &self.composite_module,
+            &self.composite_layout,
+            self.format,
+            projection,
+        );
+        self.far_depth = projection.far_depth();
+    }
+
+    /// Keeps the blur the same size in the world as the projection changes.
+    pub fn update(&self, queue: &wgpu::Queue, projection: &Projection) {
+        let focal_length = 0.5 * self.height as f32 * projection.to_matrix()[(1, 1)];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&focal_length));
+    }
+
+    /// Splats and smooths the particles, then shades them over `scene`. The view bind group must
+    /// refer to a copy of the scene and its depth, as the composite draws over the originals.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: wgpu::BufferSlice,
+        particle_count: u32,
+        camera_bind_group: &wgpu::BindGroup,
+        view_bind_group: &wgpu::BindGroup,
+        scene: &Texture,
+        depth_texture: &Texture,
+    ) {
+        let mut depth_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fluid Depth Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.depth.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_buffer.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.far_depth),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        depth_pass.set_pipeline(&self.depth_pipeline);
+        depth_pass.set_bind_group(0, camera_bind_group, &[]);
+        depth_pass.set_vertex_buffer(0, particles);
+        depth_pass.draw(0..6, 0..particle_count);
+        drop(depth_pass);
+
+        let mut thickness_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fluid Thickness Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.thickness.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        thickness_pass.set_pipeline(&self.thickness_pipeline);
+        thickness_pass.set_bind_group(0, camera_bind_group, &[]);
+        thickness_pass.set_vertex_buffer(0, particles);
+        thickness_pass.draw(0..6, 0..particle_count);
+        drop(thickness_pass);
+
+        for (pipeline, bind_group, target, label) in [
+            (&self.horizontal_pipeline, &self.horizontal_bind_group, &self.blurred, "Fluid Horizontal Smoothing Pass"),
+            (&self.vertical_pipeline, &self.vertical_bind_group, &self.smoothed, "Fluid Vertical Smoothing Pass"),
+        ] {
+            let mut smooth_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    }
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            smooth_pass.set_pipeline(pipeline);
+            smooth_pass.set_bind_group(0, bind_group, &[]);
+            smooth_pass.draw(0..3, 0..1);
+        }
+
+        let mut composite_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fluid Surface Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &scene.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        composite_pass.set_pipeline(&self.composite_pipeline);
+        composite_pass.set_bind_group(0, view_bind_group, &[]);
+        composite_pass.set_bind_group(1, &self.composite_bind_group, &[]);
+        composite_pass.draw(0..3, 0..1);
+    }
+}
+
+// The screen-sized targets, recreated together whenever the window is resized.
+struct Targets {
+    depth: Texture,
+    depth_buffer: Texture,
+    thickness: Texture,
+    blurred: Texture,
+    smoothed: Texture,
+}
+
+impl Targets {
+    fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let (width, height) = (config.width, config.height);
+        Self {
+            depth: Texture::create_color_target(device, width, height, DEPTH_FORMAT, Some("Fluid Depth")),
+            depth_buffer: Texture::create_depth_texture(device, config, Some("Fluid Depth Buffer")),
+            thickness: Texture::create_color_target(device, width, height, THICKNESS_FORMAT, Some("Fluid Thickness")),
+            blurred: Texture::create_color_target(device, width, height, DEPTH_FORMAT, Some("Blurred Fluid Depth")),
+            smoothed: Texture::create_color_target(device, width, height, DEPTH_FORMAT, Some("Smoothed Fluid Depth")),
+        }
+    }
+
+    // Each pass reads the depth the one before it wrote.
+    fn create_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 3] {
+        [&self.depth, &self.blurred, &self.smoothed].map(|depth| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Fluid Surface Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&depth.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&self.thickness.view),
+                    },
+                ],
+            })
+        })
+    }
+}
+
+// The particles' depth, and the composite, which is tested against the scene's depth and writes
+// it for the post pass too.
+fn create_depth_tested_pipelines(
+    device: &wgpu::Device,
+    particle_module: &wgpu::ShaderModule,
+    particle_layout: &wgpu::PipelineLayout,
+    composite_module: &wgpu::ShaderModule,
+    composite_layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    projection: &Projection,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let depth_pipeline = create_pipeline(
+        device,
+        particle_layout,
+        particle_module,
+        "vs_main",
+        "fs_depth",
+        &[SphParticle::LAYOUT],
+        DEPTH_FORMAT,
+        None,
+        Some(projection.depth_compare()),
+    );
+    let composite_pipeline = create_pipeline(
+        device,
+        composite_layout,
+        composite_module,
+        "vs_fluid",
+        "fs_fluid",
+        &[],
+        format,
+        Some(wgpu::BlendState::REPLACE),
+        Some(projection.depth_compare()),
+    );
+    (depth_pipeline, composite_pipeline)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    vertex_entry_point: &str,
+    fragment_entry_point: &str,
+    buffers: &[wgpu::VertexBufferLayout],
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+    depth_compare: Option<wgpu::CompareFunction>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some(vertex_entry_point),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: depth_compare.map(|depth_compare| wgpu::DepthStencilState {
+            format: Texture::depth_format(device),
+            depth_write_enabled: true,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
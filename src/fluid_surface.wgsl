@@ -0,0 +1,114 @@
+// Shades the smoothed fluid depth as a water surface. Appended to water_shading.wgsl, for the
+// same absorption, scattering and reflection as the open water.
+
+// Sharpness of the sun's highlight on the fluid.
+const FLUID_SHININESS: f32 = 64.0;
+
+struct FluidOutput {
+    @builtin(frag_depth) depth: f32,
+    @location(0) color: vec4<f32>,
+}
+
+// The water's view group, but for the projected grid the fluid has no use for.
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var reflection_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var screen_sampler: sampler;
+@group(0) @binding(3)
+var refraction_texture: texture_2d<f32>;
+@group(0) @binding(4)
+var scene_depth: texture_2d<f32>;
+
+// Binding 0 of this group holds the smoothing settings, which the composite has no use for.
+// Bits of the smoothed view depth, or zero where there is no fluid.
+@group(1) @binding(1)
+var fluid_depth: texture_2d<u32>;
+@group(1) @binding(2)
+var fluid_thickness: texture_2d<f32>;
+
+// A single triangle covering the screen.
+@vertex
+fn vs_fluid(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+// Depth buffer value at a view-space distance along the view axis, the inverse of linear_depth.
+fn device_depth(distance: f32) -> f32 {
+    let clip = camera.proj * vec4(0.0, 0.0, -distance, 1.0);
+    return clip.z / clip.w;
+}
+
+fn fluid_distance(pixel: vec2<i32>) -> f32 {
+    return bitcast<f32>(textureLoad(fluid_depth, pixel, 0).r);
+}
+
+fn fluid_position(pixel: vec2<i32>) -> vec3<f32> {
+    let uv = (vec2<f32>(pixel) + 0.5) / vec2<f32>(textureDimensions(fluid_depth));
+    let depth = device_depth(fluid_distance(pixel));
+    let position = camera.inverse_view_proj * vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    return position.xyz / position.w;
+}
+
+// The shorter of the differences to the neighbours on either side along `step`, so normals do
+// not bend over where the fluid in front ends. Neighbours without fluid are skipped.
+fn fluid_tangent(pixel: vec2<i32>, step: vec2<i32>, position: vec3<f32>) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(fluid_depth));
+    let next = clamp(pixel + step, vec2(0), size - 1);
+    let previous = clamp(pixel - step, vec2(0), size - 1);
+    var forward = vec3(0.0);
+    var backward = vec3(0.0);
+    if fluid_distance(next) > 0.0 {
+        forward = fluid_position(next) - position;
+    }
+    if fluid_distance(previous) > 0.0 {
+        backward = position - fluid_position(previous);
+    }
+    if all(backward == vec3(0.0)) || (any(forward != vec3(0.0)) && dot(forward, forward) < dot(backward, backward)) {
+        return forward;
+    }
+    return backward;
+}
+
+@fragment
+fn fs_fluid(@builtin(position) pos: vec4<f32>) -> FluidOutput {
+    let pixel = vec2<i32>(pos.xy);
+    let distance = fluid_distance(pixel);
+    if distance <= 0.0 {
+        discard;
+    }
+    let position = fluid_position(pixel);
+    let view = normalize(camera.eye - position);
+    // Down the screen crossed with across it points back at the camera.
+    let cross_product = cross(fluid_tangent(pixel, vec2(0, 1), position), fluid_tangent(pixel, vec2(1, 0), position));
+    var normal = view;
+    if dot(cross_product, cross_product) > 0.0 {
+        normal = normalize(cross_product);
+        normal = select(normal, -normal, dot(normal, view) < 0.0);
+    }
+
+    let diffuse = max(dot(normal, SUN_DIRECTION), 0.0);
+    let light = 0.3 + 0.7 * diffuse;
+    // The scene behind is looked up where the refracted ray leaves the fluid, unless that is
+    // in front of the fluid.
+    let thickness = textureLoad(fluid_thickness, pixel, 0).r;
+    let size = vec2<f32>(textureDimensions(refraction_texture));
+    let uv = pos.xy / size;
+    let exit = camera.view_proj * vec4(position + refract(-view, normal, 1.0 / WATER_TO_AIR) * thickness, 1.0);
+    var refracted_uv = vec2(0.5, -0.5) * exit.xy / exit.w + 0.5;
+    if scene_distance(refracted_uv) < distance {
+        refracted_uv = uv;
+    }
+    let background = textureSampleLevel(refraction_texture, screen_sampler, refracted_uv, 0.0).rgb;
+    let transmittance = exp(-ABSORPTION * thickness);
+    let transmitted = background * transmittance + SCATTER_COLOR * light * (1.0 - transmittance);
+
+    // Grazing views reflect more than the open water's flat share, which looks down on it.
+    let fresnel = BASE_REFLECTANCE + (1.0 - BASE_REFLECTANCE) * pow(1.0 - max(dot(normal, view), 0.0), 5.0);
+    let reflected = textureSampleLevel(reflection_texture, screen_sampler, uv + normal.xz * REFLECTION_DISTORTION, 0.0).rgb;
+    let specular = pow(max(dot(reflect(-view, normal), SUN_DIRECTION), 0.0), FLUID_SHININESS);
+    let color = mix(transmitted, reflected, max(REFLECTIVITY, fresnel)) + vec3(specular);
+    return FluidOutput(device_depth(distance), vec4(color, 1.0));
+}
@@ -1,32 +1,235 @@
 #![allow(dead_code)]
 
+mod buoyancy;
 mod camera;
+mod carving;
+mod caustics;
+mod compute;
+mod fixed_step;
+mod flow;
+mod fluid_surface;
+mod marching_cubes;
+mod ocean;
+mod projected_grid;
+mod quadtree;
+mod rain;
+mod reflection;
+mod renderer;
+mod ripple;
+mod rng;
+mod shallow_water;
+mod spectrum;
+mod sph;
+mod splash;
+mod surface;
+mod surface_detail;
 mod texture;
+mod underwater;
 mod vertex;
+mod wake;
+mod wave;
 
-use camera::*;
-use texture::*;
-use vertex::*;
+use renderer::*;
 
-use std::sync::Arc;
+pub use buoyancy::{Buoyancy, BuoyancySettings, FloatingBody, HeightField, Hull, RigidBody};
+pub use camera::{Angle, Camera, CameraController, CameraMode, CameraUniform, OrbitController, Projection};
+pub use carving::SubmergedVolume;
+pub use flow::{FlowMap, FlowSettings};
+pub use fluid_surface::FluidSurface;
+pub use marching_cubes::{DensityGrid, GpuMarchingCubes};
+pub use ocean::{FoamSettings, Ocean, OceanSettings};
+pub use projected_grid::{ProjectedGridUniform, WaterGrid};
+pub use quadtree::{QuadtreeSettings, WaterPatch, PATCH_RESOLUTION, STITCH_NEG_X, STITCH_NEG_Z, STITCH_POS_X, STITCH_POS_Z};
+pub use rain::{Rain, RainDrop, RainSettings};
+pub use ripple::{RippleBoundary, RippleSettings, Ripples, MAX_IMPULSES};
+pub use shallow_water::{GpuShallowWater, ShallowWater, ShallowWaterSettings, WaterSource};
+pub use spectrum::{SpectrumSettings, WaveSpectrum};
+pub use sph::{GpuSph, Sph, SphParticle, SphSettings};
+pub use surface::WaterSurface;
+pub use vertex::ModelVertex;
+pub use wake::{WakeEmitter, WakeSettings, Wakes};
+pub use wave::{GerstnerWave, WaveUniform};
+
+use std::{sync::Arc, time::Instant};
 
 use nalgebra as na;
 use pollster::FutureExt as _;
-use wgpu::util::DeviceExt as _;
 use winit::{
     application::ApplicationHandler,
-    dpi::PhysicalSize,
-    event::{ElementState, KeyEvent, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::ActiveEventLoop,
     keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowId},
 };
 
 const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize { width: 1280, height: 720 };
+const CLICK_IMPULSE: f32 = 0.3;
+const DRAG_IMPULSE: f32 = 0.03;
+const CAMERA_SPEED: f32 = 4.0;
+const CAMERA_SENSITIVITY: f32 = 0.004;
+const ORBIT_ZOOM_STEP: f32 = 0.1;
+const ORBIT_SMOOTHING: f32 = 0.1;
+// How far ahead of the camera the orbit target is put when switching to orbiting.
+const ORBIT_DISTANCE: f32 = 8.0;
+// Scrolling by pixels, as touchpads do, counts this many pixels as a line.
+const PIXELS_PER_LINE: f64 = 40.0;
 
+/// The window and everything in it. Settings made before the window is created are kept and
+/// applied once it is.
 #[derive(Default)]
 pub struct App {
     state: Option<State>,
+    settings: Settings,
+    on_update: Option<Box<UpdateHook>>,
+}
+
+type UpdateHook = dyn FnMut(&mut App, f32);
+
+// Everything set on the app, kept to set up its state when the window is created.
+#[derive(Default)]
+struct Settings {
+    waves: Option<Vec<GerstnerWave>>,
+    spectrum: Option<SpectrumSettings>,
+    foam: Option<FoamSettings>,
+    flow: Option<FlowSettings>,
+    water_grid: WaterGrid,
+    camera_mode: CameraMode,
+    projection: Projection,
+    basin: Option<ShallowWater>,
+    fluid: Option<Sph>,
+    rain_intensity: f32,
+    bodies: Vec<FloatingBody>,
+}
+
+impl App {
+    /// `None` until the window is created. From then on it can be read in `on_update`.
+    pub fn water_surface(&self) -> Option<&WaterSurface> {
+        self.state.as_ref().map(|state| state.renderer.water_surface())
+    }
+
+    /// Seconds the app has been running, as of the last frame.
+    pub fn time(&self) -> f32 {
+        self.state.as_ref().map_or(0.0, |state| state.time)
+    }
+
+    /// Calls `hook` before every frame with the seconds since the last one, so the app can be
+    /// changed while it runs.
+    pub fn on_update(&mut self, hook: impl FnMut(&mut App, f32) + 'static) {
+        self.on_update = Some(Box::new(hook));
+    }
+
+    pub fn set_waves(&mut self, waves: &[GerstnerWave]) {
+        self.settings.waves = Some(waves.to_vec());
+        if let Some(state) = self.state.as_mut() {
+            state.set_waves(waves);
+        }
+    }
+
+    pub fn set_spectrum(&mut self, spectrum: SpectrumSettings) {
+        self.settings.spectrum = Some(spectrum);
+        if let Some(state) = self.state.as_mut() {
+            state.set_spectrum(spectrum);
+        }
+    }
+
+    pub fn set_foam(&mut self, foam: FoamSettings) {
+        self.settings.foam = Some(foam);
+        if let Some(state) = self.state.as_mut() {
+            state.set_foam(foam);
+        }
+    }
+
+    pub fn set_flow(&mut self, flow: FlowSettings) {
+        self.settings.flow = Some(flow);
+        if let Some(state) = self.state.as_mut() {
+            state.set_flow(flow);
+        }
+    }
+
+    pub fn set_water_grid(&mut self, grid: WaterGrid) {
+        self.settings.water_grid = grid;
+        if let Some(state) = self.state.as_mut() {
+            state.set_water_grid(grid);
+        }
+    }
+
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.settings.camera_mode = mode;
+        if let Some(state) = self.state.as_mut() {
+            state.set_camera_mode(mode);
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.settings.projection = projection;
+        if let Some(state) = self.state.as_mut() {
+            state.set_projection(projection);
+        }
+    }
+
+    /// Floats `body` on the water, returning its index in `bodies_mut`.
+    pub fn add_body(&mut self, body: FloatingBody) -> usize {
+        if let Some(state) = self.state.as_mut() {
+            state.renderer.add_body(&state.device, body.clone());
+        }
+        self.settings.bodies.push(body);
+        self.settings.bodies.len() - 1
+    }
+
+    /// The bodies as they float, or as they will start out until the window is created.
+    pub fn bodies_mut(&mut self) -> &mut [FloatingBody] {
+        match self.state.as_mut() {
+            Some(state) => state.renderer.bodies_mut(),
+            None => &mut self.settings.bodies,
+        }
+    }
+
+    /// From 0 for no rain to 1 for a downpour. It stays dry unless set.
+    pub fn set_rain_intensity(&mut self, intensity: f32) {
+        self.settings.rain_intensity = intensity;
+        if let Some(state) = self.state.as_mut() {
+            state.renderer.set_rain_intensity(intensity);
+        }
+    }
+
+    /// Simulates `basin` beside the ocean, or nothing there with `None`.
+    pub fn set_basin(&mut self, basin: Option<ShallowWater>) {
+        if let Some(state) = self.state.as_mut() {
+            state.set_basin(basin.as_ref());
+        }
+        self.settings.basin = basin;
+    }
+
+    /// Simulates the particles of `fluid` and draws them as a surface, or no fluid with `None`.
+    pub fn set_fluid(&mut self, fluid: Option<Sph>) {
+        if let Some(state) = self.state.as_mut() {
+            state.renderer.set_fluid(&state.device, fluid.as_ref());
+        }
+        self.settings.fluid = fluid;
+    }
+
+    /// Starts the fluid over from the particles it was set with.
+    pub fn reset_fluid(&mut self) {
+        if let (Some(state), Some(fluid)) = (self.state.as_mut(), &self.settings.fluid) {
+            state.renderer.reset_fluid(&state.queue, fluid.particles());
+        }
+    }
+
+    // The hook goes first, so whatever it changes shows in this frame.
+    fn update(&mut self, dt: f32) {
+        if let Some(mut hook) = self.on_update.take() {
+            hook(self, dt);
+            // Unless the hook replaced itself.
+            if self.on_update.is_none() {
+                self.on_update = Some(hook);
+            }
+        }
+        if let Some(state) = self.state.as_mut() {
+            state.update(dt);
+            state.redraw();
+        }
+    }
 }
 
 impl ApplicationHandler for App {
@@ -36,7 +239,7 @@ impl ApplicationHandler for App {
             .with_inner_size(WINDOW_SIZE);
         let window = event_loop.create_window(window_attributes)
             .expect("Failed to create window");
-        self.state = Some(State::new(window));
+        self.state = Some(State::new(window, &self.settings));
     }
 
     fn window_event(
@@ -59,16 +262,81 @@ impl ApplicationHandler for App {
             } => {
                 event_loop.exit();
             }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(KeyCode::Tab),
+                    repeat: false,
+                    ..
+                },
+                ..
+            } => {
+                let mode = match state.camera_mode {
+                    CameraMode::Fly => CameraMode::Orbit,
+                    CameraMode::Orbit => CameraMode::Fly,
+                };
+                state.set_camera_mode(mode);
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent {
+                    state: key_state,
+                    physical_key: PhysicalKey::Code(key),
+                    ..
+                },
+                ..
+            } => {
+                state.camera_controller.process_keyboard(key, key_state);
+            }
             WindowEvent::Resized(new_size) => {
                 state.resize(new_size);
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                state.cursor = position;
+                if state.mouse_pressed {
+                    state.disturb_water(DRAG_IMPULSE);
+                }
+            },
+            WindowEvent::MouseInput { state: button_state, button: MouseButton::Left, .. } => {
+                state.mouse_pressed = button_state.is_pressed();
+                if state.mouse_pressed {
+                    state.disturb_water(CLICK_IMPULSE);
+                }
+            },
+            // Looking around while the right button is held, so the left one still disturbs the water.
+            WindowEvent::MouseInput { state: button_state, button: MouseButton::Right, .. } => {
+                state.looking = button_state.is_pressed();
+            },
+            WindowEvent::MouseInput { state: button_state, button: MouseButton::Middle, .. } => {
+                state.panning = button_state.is_pressed();
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+                };
+                state.orbit_controller.process_scroll(lines);
+            },
             WindowEvent::RedrawRequested => {
-                state.update();
-                state.redraw();
+                let now = Instant::now();
+                let dt = (now - state.last_update).as_secs_f32();
+                state.last_update = now;
+                self.update(dt);
             },
             _ => (),
         }
     }
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, event: DeviceEvent) {
+        let Some(state) = self.state.as_mut() else { return };
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            match state.camera_mode {
+                CameraMode::Fly if state.looking => state.camera_controller.process_mouse(dx, dy),
+                CameraMode::Orbit if state.looking => state.orbit_controller.process_mouse(dx, dy),
+                CameraMode::Orbit if state.panning => state.orbit_controller.process_pan(dx, dy),
+                _ => (),
+            }
+        }
+    }
 }
 
 struct State {
@@ -77,20 +345,20 @@ struct State {
     config: wgpu::SurfaceConfiguration,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    index_count: u32,
-    texture_bind_group: wgpu::BindGroup,
-    projection: Projection,
-    projection_buffer: wgpu::Buffer,
-    projection_bind_group: wgpu::BindGroup,
-    camera: Camera,
-    depth_texture: Texture,
+    renderer: Renderer,
+    cursor: PhysicalPosition<f64>,
+    mouse_pressed: bool,
+    camera_mode: CameraMode,
+    camera_controller: CameraController,
+    orbit_controller: OrbitController,
+    looking: bool,
+    panning: bool,
+    last_update: Instant,
+    time: f32,
 }
 
 impl State {
-    fn new(window: Window) -> Self {
+    fn new(window: Window, settings: &Settings) -> Self {
         let window = Arc::new(window);
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
@@ -106,7 +374,7 @@ impl State {
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                required_features: adapter.features() & wgpu::Features::DEPTH32FLOAT_STENCIL8,
                 required_limits: wgpu::Limits::default(),
                 memory_hints: wgpu::MemoryHints::default(),
             },
@@ -131,189 +399,122 @@ impl State {
             view_formats: vec![],
         };
 
-        let texture_bind_group_layout = device.create_bind_group_layout(&Texture::BIND_GROUP_LAYOUT_DESCRIPTOR);
-        let trollface = Texture::from_bytes(
-            &device,
-            &queue,
-            include_bytes!("Trollface.png"),
-            Some("Trollface"),
-        );
-        let trollface_bind_group = trollface.create_bind_group(&device, &texture_bind_group_layout);
-
-        let depth_texture = Texture::create_depth_texture(&device, &config, Some("Depth Texture"));
-
-        let projection_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Projection Bind Group Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    count: None,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                },
-            ],
-        });
-        let camera = Camera(na::Isometry3::look_at_rh(
-            &na::Point3::new(0.0, 1.0, 2.0),
-            &na::Point3::new(0.0, 0.0, 0.0),
-            &na::Vector3::y(),
-        ));
-        let projection = Projection {
-            aspect: WINDOW_SIZE.width as f32 / WINDOW_SIZE.height as f32,
-            fovy: 45.0,
-            z_near: 0.1,
-            z_far: 100.0,
-        };
-        let mvp = projection.to_matrix() * camera.0.to_matrix();
-        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Projection Buffer"),
-            contents: bytemuck::bytes_of(&mvp),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Projection Bind Group"),
-            layout: &projection_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: projection_buffer.as_entire_binding(),
-                },
-            ],
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&TextureVertex::SQUARE_VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&TextureVertex::SQUARE_INDICES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        let index_count = 6;
+        surface.configure(&device, &config);
+        let renderer = Renderer::new(&device, &queue, &config, settings.projection);
 
-        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&texture_bind_group_layout, &projection_bind_group_layout],
-            push_constant_ranges: &[],
-        });
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader_module,
-                entry_point: Some("vs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                buffers: &[TextureVertex::LAYOUT],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader_module,
-                entry_point: Some("fs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multiview: None,
-            cache: None,
-        });
-
-        Self {
+        let mut state = Self {
             window,
             surface,
             config,
             device,
             queue,
-            pipeline,
-            vertex_buffer,
-            index_buffer,
-            index_count,
-            texture_bind_group: trollface_bind_group,
-            projection,
-            projection_buffer,
-            projection_bind_group,
-            camera,
-            depth_texture,
+            renderer,
+            cursor: PhysicalPosition::default(),
+            mouse_pressed: false,
+            camera_mode: CameraMode::default(),
+            camera_controller: CameraController::new(CAMERA_SPEED, CAMERA_SENSITIVITY),
+            orbit_controller: OrbitController::new(CAMERA_SENSITIVITY, ORBIT_ZOOM_STEP, ORBIT_SMOOTHING),
+            looking: false,
+            panning: false,
+            last_update: Instant::now(),
+            time: 0.0,
+        };
+        state.apply(settings);
+        state
+    }
+
+    fn apply(&mut self, settings: &Settings) {
+        if let Some(waves) = &settings.waves {
+            self.set_waves(waves);
+        }
+        if let Some(spectrum) = settings.spectrum {
+            self.set_spectrum(spectrum);
+        }
+        if let Some(foam) = settings.foam {
+            self.set_foam(foam);
+        }
+        if let Some(flow) = settings.flow {
+            self.set_flow(flow);
+        }
+        self.set_water_grid(settings.water_grid);
+        self.set_camera_mode(settings.camera_mode);
+        self.set_basin(settings.basin.as_ref());
+        self.renderer.set_fluid(&self.device, settings.fluid.as_ref());
+        self.renderer.set_rain_intensity(settings.rain_intensity);
+        for body in &settings.bodies {
+            self.renderer.add_body(&self.device, body.clone());
         }
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
         self.config.width = size.width;
         self.config.height = size.height;
-        self.projection.aspect = size.width as f32 / size.height as f32;
         self.surface.configure(&self.device, &self.config);
+        self.renderer.resize(&self.device, &self.config);
     }
 
     fn redraw(&mut self) {
         self.window.request_redraw();
         let output = self.surface.get_current_texture().unwrap();
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
-                    store: wgpu::StoreOp::Store,
-                }
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-        });
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.projection_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
-
-        drop(render_pass);
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.renderer.render(&self.device, &self.queue, &view);
         output.present();
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        match self.camera_mode {
+            CameraMode::Fly => self.camera_controller.update_camera(self.renderer.camera_mut(), dt),
+            CameraMode::Orbit => self.orbit_controller.update_camera(self.renderer.camera_mut(), dt),
+        }
+        self.renderer.update(&self.queue, dt);
+    }
+
+    fn disturb_water(&mut self, strength: f32) {
+        let size = self.window.inner_size();
+        let ndc = na::Point2::new(
+            2.0 * self.cursor.x as f32 / size.width as f32 - 1.0,
+            1.0 - 2.0 * self.cursor.y as f32 / size.height as f32,
+        );
+        self.renderer.disturb_water(ndc, strength);
+    }
+
+    fn set_waves(&mut self, waves: &[GerstnerWave]) {
+        self.renderer.set_waves(&self.queue, waves);
+    }
+
+    fn set_spectrum(&mut self, spectrum: SpectrumSettings) {
+        self.renderer.set_spectrum(&self.queue, spectrum);
+    }
+
+    fn set_foam(&mut self, foam: FoamSettings) {
+        self.renderer.set_foam(&self.queue, foam);
+    }
+
+    fn set_flow(&mut self, flow: FlowSettings) {
+        self.renderer.set_flow(&self.queue, flow);
+    }
+
+    fn set_basin(&mut self, basin: Option<&ShallowWater>) {
+        self.renderer.set_basin(&self.device, &self.queue, basin);
+    }
+
+    fn set_water_grid(&mut self, grid: WaterGrid) {
+        self.renderer.set_water_grid(grid);
+    }
+
+    // The fly controller reads its view from the camera, and the orbit one starts from it.
+    fn set_camera_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.camera_mode != mode {
+            self.orbit_controller.attach(self.renderer.camera(), ORBIT_DISTANCE);
+        }
+        self.camera_mode = mode;
+    }
+
+    fn set_projection(&mut self, projection: Projection) {
+        self.renderer.set_projection(&self.device, &self.config, projection);
     }
 }
@@ -1,10 +1,101 @@
-use water::App;
+use std::f32::consts::TAU;
+
+use nalgebra as na;
+use water::{App, FloatingBody, ShallowWater, ShallowWaterSettings, Sph, SphSettings, WaterSource};
 use winit::event_loop::{ControlFlow, EventLoop};
 
+const BASIN_SIZE: f32 = 16.0;
+const BASIN_RESOLUTION: u32 = 128;
+// Just past the edge of the ocean, which is 32 m across.
+const BASIN_CENTER: na::Point2<f32> = na::Point2::new(16.0 + BASIN_SIZE * 0.5 + 2.0, 0.0);
+const RESERVOIR_LEVEL: f32 = 2.5;
+// Seconds between the peaks of the passing showers.
+const STORM_PERIOD: f32 = 60.0;
+// The hull-like box motors around a circle, towed from its bow so it keeps pointing ahead.
+const BOAT_CIRCLE_RADIUS: f32 = 7.0;
+const BOAT_ANGULAR_SPEED: f32 = 0.35;
+const BOAT_STIFFNESS: f32 = 4.0;
+const BOAT_DAMPING: f32 = 4.0;
+const BOAT_MAX_ACCELERATION: f32 = 8.0;
+// A column of fluid collapsing in a tank left of the camera, started over every so often.
+const FLUID_TANK_MIN: na::Point3<f32> = na::Point3::new(-4.0, 0.4, 3.0);
+const FLUID_TANK_MAX: na::Point3<f32> = na::Point3::new(-3.0, 1.4, 3.6);
+const FLUID_COLUMN_MAX: na::Point3<f32> = na::Point3::new(-3.7, 0.9, 3.6);
+const FLUID_RESET_PERIOD: f32 = 10.0;
+
 fn main() {
     env_logger::init();
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
     let mut app = App::default();
+    app.set_basin(Some(dam_break()));
+    app.set_fluid(Some(Sph::block(
+        SphSettings {
+            bounds_min: FLUID_TANK_MIN,
+            bounds_max: FLUID_TANK_MAX,
+            ..Default::default()
+        },
+        FLUID_TANK_MIN,
+        FLUID_COLUMN_MAX,
+    )));
+    // A crate, a hull-like box and a plank drifting in front of the camera.
+    app.add_body(FloatingBody::cuboid(400.0, na::Vector3::new(0.5, 0.5, 0.5), na::Point3::new(-2.5, 1.0, 3.0)));
+    let boat = app.add_body(FloatingBody::cuboid(300.0, na::Vector3::new(1.6, 0.35, 0.6), na::Point3::new(2.0, 0.5, 0.0)));
+    app.add_body(FloatingBody::cuboid(600.0, na::Vector3::new(1.0, 0.05, 0.15), na::Point3::new(0.5, 0.5, 5.0)));
+    app.on_update(move |app, dt| {
+        let time = app.time();
+        // Showers build up and die away, with dry spells in between.
+        let storm = 0.5 - 0.5 * (TAU * time / STORM_PERIOD).cos();
+        app.set_rain_intensity(storm * storm);
+        steer_boat(&mut app.bodies_mut()[boat], time);
+        // The time is that of the last frame, so this frame ends at `time + dt`.
+        if ((time + dt) / FLUID_RESET_PERIOD).floor() > (time / FLUID_RESET_PERIOD).floor() {
+            app.reset_fluid();
+        }
+    });
     event_loop.run_app(&mut app).unwrap();
 }
+
+// Tows the boat towards a point going round the circle, pulling harder the further behind it falls.
+fn steer_boat(boat: &mut FloatingBody, time: f32) {
+    let angle = BOAT_ANGULAR_SPEED * time;
+    let (sin, cos) = angle.sin_cos();
+    let target = na::Vector3::new(cos, 0.0, sin) * BOAT_CIRCLE_RADIUS;
+    let target_velocity = na::Vector3::new(-sin, 0.0, cos) * BOAT_CIRCLE_RADIUS * BOAT_ANGULAR_SPEED;
+    let horizontal = na::Vector3::new(1.0, 0.0, 1.0);
+    let error = (target - boat.body.position.coords).component_mul(&horizontal);
+    let velocity_error = (target_velocity - boat.body.linear_velocity).component_mul(&horizontal);
+    let acceleration = BOAT_STIFFNESS * error + BOAT_DAMPING * velocity_error;
+    boat.thrust = acceleration.cap_magnitude(BOAT_MAX_ACCELERATION) * boat.body.mass;
+    boat.thrust_point = na::Point3::new(boat.half_extents.x, 0.0, 0.0);
+}
+
+// A river feeds the valley from upstream and drains into the ground at the far end.
+fn dam_break() -> ShallowWater {
+    let settings = ShallowWaterSettings {
+        resolution: BASIN_RESOLUTION,
+        size: BASIN_SIZE,
+        center: BASIN_CENTER,
+        ..Default::default()
+    };
+    let mut basin = ShallowWater::from_fn(settings, dam_break_terrain);
+    basin.add_source(WaterSource {
+        position: BASIN_CENTER + na::Vector2::new(0.0, -7.0),
+        radius: 1.0,
+        rate: 0.5,
+    });
+    basin.add_source(WaterSource {
+        position: BASIN_CENTER + na::Vector2::new(0.0, 7.0),
+        radius: 1.5,
+        rate: -0.5,
+    });
+    basin
+}
+
+// A valley sloping down along +z, with a reservoir held at the upstream end when the dam breaks.
+fn dam_break_terrain(p: na::Point2<f32>) -> (f32, f32) {
+    let local = p - BASIN_CENTER;
+    let bed = 1.0 - 0.06 * local.y + 0.03 * local.x * local.x;
+    let depth = if local.y < -4.0 { (RESERVOIR_LEVEL - bed).max(0.0) } else { 0.0 };
+    (bed, depth)
+}
@@ -0,0 +1,475 @@
+#![allow(dead_code)]
+
+use crate::{
+    compute::create_compute_pipeline,
+    sph::{poly6, SphParticle, SphSettings},
+    vertex::ModelVertex,
+};
+
+use std::sync::OnceLock;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+const WORKGROUP_SIZE: u32 = 4;
+// Ends a cell's list of edges in the triangle table.
+const NO_EDGE: u8 = 15;
+// At most five triangles cross a cell.
+const MAX_CELL_INDICES: u32 = 15;
+
+// Corner c of a cell is offset along x, y and z by bits 0, 1 and 2 of c. Edge 4a + k runs
+// along axis a, from the corner whose other two offsets are the bits of k, lowest axis first.
+// mc_common.wgsl numbers them the same way.
+fn corner_offset(corner: usize) -> [u32; 3] {
+    [0, 1, 2].map(|axis| (corner as u32 >> axis) & 1)
+}
+
+fn edge_start(edge: usize) -> [u32; 3] {
+    let (low, high) = ((edge & 1) as u32, ((edge >> 1) & 1) as u32);
+    match edge / 4 {
+        0 => [0, low, high],
+        1 => [low, 0, high],
+        _ => [low, high, 0],
+    }
+}
+
+fn edge_between(a: usize, b: usize) -> usize {
+    let axis = (a ^ b).trailing_zeros() as usize;
+    let others: Vec<_> = (0..3).filter(|&i| i != axis).map(|i| (a >> i) & 1).collect();
+    4 * axis + others[0] + 2 * others[1]
+}
+
+// Corners around each face of a cell.
+const FACES: [[usize; 4]; 6] = [[0, 2, 6, 4], [1, 3, 7, 5], [0, 1, 5, 4], [2, 3, 7, 6], [0, 1, 3, 2], [4, 5, 7, 6]];
+
+/// Edges of the triangles for each combination of corners inside the surface, three per
+/// triangle, wound counter-clockwise seen from outside.
+fn triangle_table() -> &'static [[u8; 16]; 256] {
+    static TABLE: OnceLock<[[u8; 16]; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [[NO_EDGE; 16]; 256];
+        for (case, entry) in table.iter_mut().enumerate() {
+            for (i, edge) in cell_triangles(case).into_iter().enumerate() {
+                entry[i] = edge as u8;
+            }
+        }
+        table
+    })
+}
+
+// The surface crosses each face of the cell in one or two segments between the face's crossed
+// edges. Where it crosses all four, the inside corners are cut off on their own, and since the
+// choice only depends on the face, neighbouring cells agree on it and the mesh has no holes.
+// Joined up, the segments make closed loops, which are fanned into triangles.
+fn cell_triangles(case: usize) -> Vec<usize> {
+    let inside = |corner: usize| (case >> corner) & 1 == 1;
+    let mut links: [Vec<usize>; 12] = Default::default();
+    let mut link = |a: usize, b: usize| {
+        links[a].push(b);
+        links[b].push(a);
+    };
+    for face in FACES {
+        let edges: [_; 4] = std::array::from_fn(|i| edge_between(face[i], face[(i + 1) % 4]));
+        let crossed: Vec<_> = (0..4).filter(|&i| inside(face[i]) != inside(face[(i + 1) % 4])).collect();
+        match crossed.len() {
+            2 => link(edges[crossed[0]], edges[crossed[1]]),
+            4 => {
+                for i in (0..4).filter(|&i| inside(face[i])) {
+                    link(edges[(i + 3) % 4], edges[i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let corner_position = |corner: usize| na::Vector3::from(corner_offset(corner).map(|x| x as f32));
+    let mut visited = [false; 12];
+    let mut triangles = Vec::new();
+    for start in (0..12).filter(|&edge| !links[edge].is_empty()) {
+        if visited[start] {
+            continue;
+        }
+        let mut polygon = vec![start];
+        visited[start] = true;
+        let (mut previous, mut current) = (usize::MAX, start);
+        loop {
+            let next = if links[current][0] != previous { links[current][0] } else { links[current][1] };
+            if next == start {
+                break;
+            }
+            polygon.push(next);
+            visited[next] = true;
+            (previous, current) = (current, next);
+        }
+
+        // Turn the loop to face the outside corners of its edges.
+        let midpoints: Vec<_> = polygon.iter()
+            .map(|&edge| {
+                let (a, b) = edge_corners(edge);
+                (corner_position(a) + corner_position(b)) * 0.5
+            })
+            .collect();
+        let normal: na::Vector3<f32> = (0..midpoints.len())
+            .map(|i| midpoints[i].cross(&midpoints[(i + 1) % midpoints.len()]))
+            .sum();
+        let outward: na::Vector3<f32> = polygon.iter()
+            .map(|&edge| {
+                let (a, b) = edge_corners(edge);
+                let (inner, outer) = if inside(a) { (a, b) } else { (b, a) };
+                corner_position(outer) - corner_position(inner)
+            })
+            .sum();
+        if normal.dot(&outward) < 0.0 {
+            polygon.reverse();
+        }
+        for i in 1..polygon.len() - 1 {
+            triangles.extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+        }
+    }
+    triangles
+}
+
+fn edge_corners(edge: usize) -> (usize, usize) {
+    let [x, y, z] = edge_start(edge);
+    let start = (x | y << 1 | z << 2) as usize;
+    (start, start | 1 << (edge / 4))
+}
+
+/// Scalar samples on a regular grid, such as fluid density, which marching cubes turns into a
+/// mesh of the surface where the field crosses a threshold.
+#[derive(Clone, Debug)]
+pub struct DensityGrid {
+    pub origin: na::Point3<f32>,
+    pub spacing: f32,
+    /// Samples along x, y and z.
+    pub size: [u32; 3],
+    /// Samples in x-major order, then y, then z.
+    pub values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(origin: na::Point3<f32>, spacing: f32, size: [u32; 3]) -> Self {
+        Self {
+            origin,
+            spacing,
+            size,
+            values: vec![0.0; size.iter().product::<u32>() as usize],
+        }
+    }
+
+    pub fn from_fn(origin: na::Point3<f32>, spacing: f32, size: [u32; 3], f: impl Fn(na::Point3<f32>) -> f32) -> Self {
+        let mut grid = Self::new(origin, spacing, size);
+        for z in 0..size[2] {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let i = grid.index([x, y, z]);
+                    grid.values[i] = f(grid.position([x, y, z]));
+                }
+            }
+        }
+        grid
+    }
+
+    /// SPH density of the particles, on a grid with room for the kernels all round, so the
+    /// surface of the fluid is closed.
+    pub fn from_particles(particles: &[SphParticle], settings: &SphSettings, spacing: f32) -> Self {
+        let h = settings.smoothing_radius;
+        let positions: Vec<_> = particles.iter().map(|particle| na::Point3::from(particle.position)).collect();
+        let Some(first) = positions.first() else {
+            return Self::new(na::Point3::origin(), spacing, [0; 3]);
+        };
+        let (min, max) = positions.iter().fold((*first, *first), |(min, max), p| (min.inf(p), max.sup(p)));
+        let padding = na::Vector3::repeat(h + spacing);
+        let origin = min - padding;
+        let size = ((max - min + 2.0 * padding) / spacing).map(|extent| extent.ceil() as u32 + 1);
+        let mut grid = Self::new(origin, spacing, size.into());
+
+        let mass = settings.particle_mass();
+        for p in positions {
+            let low = ((p - origin).add_scalar(-h) / spacing).map(|x| x.ceil().max(0.0) as u32);
+            let high = ((p - origin).add_scalar(h) / spacing).zip_map(&size, |x, n| (x.floor() as u32).min(n - 1));
+            for z in low.z..=high.z {
+                for y in low.y..=high.y {
+                    for x in low.x..=high.x {
+                        let r2 = (grid.position([x, y, z]) - p).norm_squared();
+                        if r2 < h * h {
+                            let i = grid.index([x, y, z]);
+                            grid.values[i] += mass * poly6(r2, h);
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn index(&self, [x, y, z]: [u32; 3]) -> usize {
+        ((z * self.size[1] + y) * self.size[0] + x) as usize
+    }
+
+    pub fn position(&self, point: [u32; 3]) -> na::Point3<f32> {
+        self.origin + na::Vector3::from(point.map(|x| x as f32)) * self.spacing
+    }
+
+    pub fn value(&self, point: [u32; 3]) -> f32 {
+        self.values[self.index(point)]
+    }
+
+    /// Central differences, one-sided at the sides of the grid.
+    pub fn gradient(&self, point: [u32; 3]) -> na::Vector3<f32> {
+        na::Vector3::from(std::array::from_fn(|axis| {
+            let (mut low, mut high) = (point, point);
+            low[axis] = low[axis].saturating_sub(1);
+            high[axis] = (high[axis] + 1).min(self.size[axis] - 1);
+            let steps = (high[axis] - low[axis]).max(1) as f32;
+            (self.value(high) - self.value(low)) / (steps * self.spacing)
+        }))
+    }
+
+    /// Marching cubes: a mesh of the surface where the field equals `iso`, facing away from
+    /// where it is higher. Cells share the vertices on their common edges. Normals come from the
+    /// field's gradient, and uvs map x and z over the grid.
+    pub fn extract_surface(&self, iso: f32) -> (Vec<ModelVertex>, Vec<u32>) {
+        let table = triangle_table();
+        let [nx, ny, nz] = self.size;
+        let mut edge_vertices = vec![u32::MAX; 3 * self.values.len()];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for z in 0..nz.saturating_sub(1) {
+            for y in 0..ny.saturating_sub(1) {
+                for x in 0..nx.saturating_sub(1) {
+                    let case = (0..8)
+                        .filter(|&corner| {
+                            let offset = corner_offset(corner);
+                            self.value([x + offset[0], y + offset[1], z + offset[2]]) > iso
+                        })
+                        .fold(0, |case, corner| case | 1 << corner);
+                    for &edge in table[case].iter().take_while(|&&edge| edge != NO_EDGE) {
+                        let offset = edge_start(edge as usize);
+                        let start = [x + offset[0], y + offset[1], z + offset[2]];
+                        let axis = edge as usize / 4;
+                        let key = 3 * self.index(start) + axis;
+                        if edge_vertices[key] == u32::MAX {
+                            edge_vertices[key] = vertices.len() as u32;
+                            vertices.push(self.edge_vertex(start, axis, iso));
+                        }
+                        indices.push(edge_vertices[key]);
+                    }
+                }
+            }
+        }
+        (vertices, indices)
+    }
+
+    fn edge_vertex(&self, start: [u32; 3], axis: usize, iso: f32) -> ModelVertex {
+        let mut end = start;
+        end[axis] += 1;
+        let (a, b) = (self.value(start), self.value(end));
+        let t = (iso - a) / (b - a);
+        let position = self.position(start).coords.lerp(&self.position(end).coords, t);
+        let gradient = self.gradient(start).lerp(&self.gradient(end), t);
+        let normal = (-gradient).try_normalize(f32::EPSILON).unwrap_or_else(na::Vector3::y);
+        let extent = na::Vector3::from(self.size.map(|n| n.saturating_sub(1).max(1) as f32)) * self.spacing;
+        let uv = (position - self.origin.coords).component_div(&extent);
+        ModelVertex {
+            position: position.into(),
+            uv: [uv.x, uv.z],
+            normal: normal.into(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct MarchingCubesUniform {
+    origin: [f32; 3],
+    spacing: f32,
+    size: [u32; 3],
+    iso: f32,
+    // Four bits per edge, sixteen edges per cell case.
+    triangles: [[u32; 4]; 128],
+}
+
+/// Marching cubes in two compute passes, writing a mesh laid out like
+/// `DensityGrid::extract_surface` does, though in no particular order. The first pass puts a
+/// vertex on every edge the surface crosses and the second has each cell append its triangles.
+pub struct GpuMarchingCubes {
+    uniform: MarchingCubesUniform,
+    uniform_buffer: wgpu::Buffer,
+    density_buffer: wgpu::Buffer,
+    pub vertices: wgpu::Buffer,
+    pub indices: wgpu::Buffer,
+    /// Arguments for an indirect indexed draw of the mesh, followed by its vertex count.
+    pub counters: wgpu::Buffer,
+    vertex_pipeline: wgpu::ComputePipeline,
+    vertex_bind_group: wgpu::BindGroup,
+    triangle_pipeline: wgpu::ComputePipeline,
+    triangle_bind_group: wgpu::BindGroup,
+}
+
+impl GpuMarchingCubes {
+    /// Buffers have room for the most any field on a grid of `size` samples can produce.
+    pub fn new(device: &wgpu::Device, size: [u32; 3]) -> Self {
+        let point_count = size.iter().product::<u32>() as u64;
+        let cell_count = size.iter().map(|n| n.saturating_sub(1)).product::<u32>() as u64;
+
+        let mut triangles = [[0; 4]; 128];
+        for (case, edges) in triangle_table().iter().enumerate() {
+            for (i, &edge) in edges.iter().enumerate() {
+                triangles[case / 2][case % 2 * 2 + i / 8] |= (edge as u32) << (i % 8 * 4);
+            }
+        }
+        let uniform = MarchingCubesUniform {
+            origin: [0.0; 3],
+            spacing: 1.0,
+            size,
+            iso: 0.0,
+            triangles,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Marching Cubes Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let density_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marching Cubes Density Buffer"),
+            size: point_count.max(1) * size_of::<f32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // The index of the vertex on each edge leaving each sample along +x, +y and +z.
+        let edge_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marching Cubes Edge Vertex Buffer"),
+            size: 3 * point_count.max(1) * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marching Cubes Vertex Buffer"),
+            size: 3 * point_count.max(1) * size_of::<ModelVertex>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let indices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marching Cubes Index Buffer"),
+            size: MAX_CELL_INDICES as u64 * cell_count.max(1) * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let counters = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marching Cubes Counter Buffer"),
+            size: 6 * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let vertex_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Marching Cubes Vertices",
+            &[include_str!("mc_common.wgsl"), include_str!("mc_vertices.wgsl")],
+        );
+        let vertex_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Marching Cubes Vertex Bind Group"),
+            layout: &vertex_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: density_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: edge_vertex_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: counters.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: vertices.as_entire_binding(),
+                },
+            ],
+        });
+        let triangle_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Marching Cubes Triangles",
+            &[include_str!("mc_common.wgsl"), include_str!("mc_triangles.wgsl")],
+        );
+        let triangle_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Marching Cubes Triangle Bind Group"),
+            layout: &triangle_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: density_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: edge_vertex_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: counters.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: indices.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            uniform,
+            uniform_buffer,
+            density_buffer,
+            vertices,
+            indices,
+            counters,
+            vertex_pipeline,
+            vertex_bind_group,
+            triangle_pipeline,
+            triangle_bind_group,
+        }
+    }
+
+    /// Uploads a field with the size given at creation, to be meshed where it equals `iso` by
+    /// the next dispatch.
+    pub fn write(&mut self, queue: &wgpu::Queue, grid: &DensityGrid, iso: f32) {
+        assert_eq!(grid.size, self.uniform.size, "Density grid must have the size given at creation");
+        self.uniform.origin = grid.origin.into();
+        self.uniform.spacing = grid.spacing;
+        self.uniform.iso = iso;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+        queue.write_buffer(&self.density_buffer, 0, bytemuck::cast_slice(&grid.values));
+        // No indices or vertices yet, and a single instance to draw.
+        queue.write_buffer(&self.counters, 0, bytemuck::cast_slice(&[0u32, 1, 0, 0, 0, 0]));
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let groups = self.uniform.size.map(|n| n.div_ceil(WORKGROUP_SIZE));
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Marching Cubes Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.vertex_pipeline);
+        compute_pass.set_bind_group(0, &self.vertex_bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups[0], groups[1], groups[2]);
+        compute_pass.set_pipeline(&self.triangle_pipeline);
+        compute_pass.set_bind_group(0, &self.triangle_bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups[0], groups[1], groups[2]);
+    }
+}
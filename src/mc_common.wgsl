@@ -0,0 +1,48 @@
+const NO_EDGE: u32 = 15u;
+
+struct Grid {
+    origin: vec3<f32>,
+    spacing: f32,
+    size: vec3<u32>,
+    iso: f32,
+    // Four bits per edge, sixteen edges per cell case, eight cases per vector.
+    triangles: array<vec4<u32>, 128>,
+}
+
+// An indexed indirect draw of the mesh, then its vertex count.
+struct Counters {
+    index_count: atomic<u32>,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+    vertex_count: atomic<u32>,
+}
+
+@group(0) @binding(0)
+var<uniform> grid: Grid;
+@group(0) @binding(1)
+var<storage, read> densities: array<f32>;
+// The vertex on each edge leaving a sample along +x, +y and +z, where the surface crosses it.
+@group(0) @binding(2)
+var<storage, read_write> edge_vertices: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> counters: Counters;
+
+fn point_index(point: vec3<u32>) -> u32 {
+    return (point.z * grid.size.y + point.y) * grid.size.x + point.x;
+}
+
+fn density(point: vec3<u32>) -> f32 {
+    return densities[point_index(point)];
+}
+
+// Corner c of a cell is offset along x, y and z by bits 0, 1 and 2 of c. Edge 4a + k runs
+// along axis a, from the corner whose other two offsets are the bits of k, lowest axis first.
+fn corner_offset(corner: u32) -> vec3<u32> {
+    return vec3(corner, corner >> 1u, corner >> 2u) & vec3(1u);
+}
+
+fn axis_offset(axis: u32) -> vec3<u32> {
+    return vec3<u32>(vec3(axis) == vec3(0u, 1u, 2u));
+}
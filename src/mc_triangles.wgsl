@@ -0,0 +1,45 @@
+@group(0) @binding(5)
+var<storage, read_write> indices: array<u32>;
+
+fn table_edge(case_index: u32, i: u32) -> u32 {
+    let word = grid.triangles[case_index / 2u][case_index % 2u * 2u + i / 8u];
+    return (word >> (i % 8u * 4u)) & 15u;
+}
+
+fn edge_vertex(cell: vec3<u32>, edge: u32) -> u32 {
+    let axis = edge / 4u;
+    let low = edge & 1u;
+    let high = (edge >> 1u) & 1u;
+    var start = vec3(low, high, 0u);
+    if axis == 0u {
+        start = vec3(0u, low, high);
+    } else if axis == 1u {
+        start = vec3(low, 0u, high);
+    }
+    return edge_vertices[3u * point_index(cell + start) + axis];
+}
+
+// One invocation per cell, appending its triangles between the vertices on its edges.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id + 1u >= grid.size) {
+        return;
+    }
+    var case_index = 0u;
+    for (var corner = 0u; corner < 8u; corner++) {
+        if density(id + corner_offset(corner)) > grid.iso {
+            case_index |= 1u << corner;
+        }
+    }
+    var count = 0u;
+    while count < 16u && table_edge(case_index, count) != NO_EDGE {
+        count++;
+    }
+    if count == 0u {
+        return;
+    }
+    let first = atomicAdd(&counters.index_count, count);
+    for (var i = 0u; i < count; i++) {
+        indices[first + i] = edge_vertex(id, table_edge(case_index, i));
+    }
+}
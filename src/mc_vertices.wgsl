@@ -0,0 +1,57 @@
+// Mesh vertices, eight floats each: position, uv and normal.
+@group(0) @binding(4)
+var<storage, read_write> vertices: array<f32>;
+
+fn position(point: vec3<u32>) -> vec3<f32> {
+    return grid.origin + vec3<f32>(point) * grid.spacing;
+}
+
+// Central differences, one-sided at the sides of the grid.
+fn gradient(point: vec3<u32>) -> vec3<f32> {
+    var gradient = vec3(0.0);
+    for (var axis = 0u; axis < 3u; axis++) {
+        let offset = axis_offset(axis);
+        let low = select(point - offset, point, point[axis] == 0u);
+        let high = min(point + offset, grid.size - 1u);
+        let steps = max(f32(high[axis] - low[axis]), 1.0);
+        gradient[axis] = (density(high) - density(low)) / (steps * grid.spacing);
+    }
+    return gradient;
+}
+
+// One invocation per sample, placing a vertex on each edge leaving it that the surface crosses.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id >= grid.size) {
+        return;
+    }
+    let a = density(id);
+    for (var axis = 0u; axis < 3u; axis++) {
+        let end = id + axis_offset(axis);
+        if end[axis] >= grid.size[axis] {
+            continue;
+        }
+        let b = density(end);
+        if (a > grid.iso) == (b > grid.iso) {
+            continue;
+        }
+        let t = (grid.iso - a) / (b - a);
+        let p = mix(position(id), position(end), t);
+        let g = -mix(gradient(id), gradient(end), t);
+        let normal = select(vec3(0.0, 1.0, 0.0), normalize(g), dot(g, g) > 0.0);
+        let extent = vec3<f32>(max(grid.size, vec3(2u)) - 1u) * grid.spacing;
+        let uv = (p - grid.origin) / extent;
+
+        let index = atomicAdd(&counters.vertex_count, 1u);
+        let base = 8u * index;
+        vertices[base] = p.x;
+        vertices[base + 1u] = p.y;
+        vertices[base + 2u] = p.z;
+        vertices[base + 3u] = uv.x;
+        vertices[base + 4u] = uv.z;
+        vertices[base + 5u] = normal.x;
+        vertices[base + 6u] = normal.y;
+        vertices[base + 7u] = normal.z;
+        edge_vertices[3u * point_index(id) + axis] = index;
+    }
+}
@@ -0,0 +1,480 @@
+#![allow(dead_code)]
+
+use crate::{compute::create_compute_pipeline, spectrum::SpectrumSettings, texture::Texture};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt as _;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OceanSettings {
+    pub resolution: u32,
+    pub patch_size: f32,
+    pub choppiness: f32,
+    pub spectrum: SpectrumSettings,
+    pub foam: FoamSettings,
+}
+
+impl Default for OceanSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            patch_size: 64.0,
+            choppiness: 1.0,
+            spectrum: SpectrumSettings::default(),
+            foam: FoamSettings::default(),
+        }
+    }
+}
+
+/// Whitecaps form where the Jacobian of the horizontal displacement drops below `threshold`,
+/// meaning the surface is compressed towards folding over, and then fade out.
+#[derive(Clone, Copy, Debug)]
+pub struct FoamSettings {
+    /// Foam added per unit of Jacobian below the threshold, saturating at full coverage.
+    pub coverage: f32,
+    /// Fraction of the foam that fades per second.
+    pub decay: f32,
+    pub threshold: f32,
+}
+
+impl Default for FoamSettings {
+    fn default() -> Self {
+        Self {
+            coverage: 2.0,
+            decay: 0.6,
+            threshold: 0.7,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct OceanUniform {
+    resolution: u32,
+    patch_size: f32,
+    time: f32,
+    choppiness: f32,
+    time_step: f32,
+    foam_coverage: f32,
+    foam_decay: f32,
+    foam_threshold: f32,
+}
+
+impl OceanUniform {
+    fn new(settings: &OceanSettings, time: f32, time_step: f32) -> Self {
+        Self {
+            resolution: settings.resolution,
+            patch_size: settings.patch_size,
+            time,
+            choppiness: settings.choppiness,
+            time_step,
+            foam_coverage: settings.foam.coverage,
+            foam_decay: settings.foam.decay,
+            foam_threshold: settings.foam.threshold,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FftStage {
+    stage: u32,
+    horizontal: u32,
+    _padding: [u32; 2],
+}
+
+pub struct Ocean {
+    settings: OceanSettings,
+    uniform_buffer: wgpu::Buffer,
+    spectrum: Texture,
+    fields: [wgpu::Buffer; 2],
+    pub displacement: Texture,
+    pub normal: Texture,
+    /// Foam coverage in x and the Jacobian in y, carried over from frame to frame through `foam_history`.
+    pub foam: Texture,
+    foam_history: Texture,
+    evolve_pipeline: wgpu::ComputePipeline,
+    fft_pipeline: wgpu::ComputePipeline,
+    resolve_pipeline: wgpu::ComputePipeline,
+    foam_pipeline: wgpu::ComputePipeline,
+    evolve_bind_group: wgpu::BindGroup,
+    fft_bind_groups: Vec<wgpu::BindGroup>,
+    resolve_bind_group: wgpu::BindGroup,
+    foam_bind_group: wgpu::BindGroup,
+    time: f32,
+}
+
+impl Ocean {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, settings: OceanSettings) -> Self {
+        let n = settings.resolution;
+        assert!(n.is_power_of_two() && n >= 2 * WORKGROUP_SIZE, "Ocean resolution must be a power of two of at least {}", 2 * WORKGROUP_SIZE);
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ocean Uniform Buffer"),
+            contents: bytemuck::bytes_of(&OceanUniform::new(&settings, 0.0, 0.0)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let spectrum = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba32Float, Some("Ocean Spectrum"));
+        let displacement = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Ocean Displacement"));
+        let normal = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Ocean Normal"));
+        let foam = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Ocean Foam"));
+        let foam_history = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Ocean Foam History"));
+        // Two complex pairs per texel in each half: (Dx, Dz), (h, dh/dx) and (dh/dz, unused).
+        let fields = [0, 1].map(|i| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("Ocean Field Buffer {i}")),
+            size: 2 * (n * n) as u64 * size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        }));
+
+        // Rgba32Float is not filterable, so the evolve layout cannot be derived from the shader.
+        let evolve_bind_group_layout = device.create_bind_group_layout(&EVOLVE_BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let evolve_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Ocean Evolve Pipeline Layout"),
+            bind_group_layouts: &[&evolve_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let evolve_pipeline = create_compute_pipeline(
+            device,
+            Some(&evolve_pipeline_layout),
+            "Ocean Evolve",
+            &[include_str!("ocean_common.wgsl"), include_str!("ocean_evolve.wgsl")],
+        );
+        let fft_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ocean FFT",
+            &[include_str!("ocean_common.wgsl"), include_str!("ocean_fft.wgsl")],
+        );
+        let resolve_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ocean Resolve",
+            &[include_str!("ocean_common.wgsl"), include_str!("ocean_resolve.wgsl")],
+        );
+        let foam_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ocean Foam",
+            &[include_str!("ocean_common.wgsl"), include_str!("ocean_foam.wgsl")],
+        );
+
+        let evolve_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ocean Evolve Bind Group"),
+            layout: &evolve_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&spectrum.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: fields[0].as_entire_binding(),
+                },
+            ],
+        });
+
+        let stages = n.trailing_zeros();
+        let fft_bind_groups = (0..2 * stages).map(|i| {
+            let stage = FftStage {
+                stage: i % stages,
+                horizontal: (i < stages) as u32,
+                _padding: [0; 2],
+            };
+            let stage_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Ocean FFT Stage Buffer"),
+                contents: bytemuck::bytes_of(&stage),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let (input, output) = (&fields[i as usize % 2], &fields[(i as usize + 1) % 2]);
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Ocean FFT Bind Group"),
+                layout: &fft_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: stage_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: input.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: output.as_entire_binding(),
+                    },
+                ],
+            })
+        }).collect::<Vec<_>>();
+
+        // An even number of stages always leaves the result back in the first buffer.
+        let resolve_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ocean Resolve Bind Group"),
+            layout: &resolve_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: fields[0].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&displacement.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+            ],
+        });
+
+        let foam_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ocean Foam Bind Group"),
+            layout: &foam_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&displacement.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&foam_history.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&foam.view),
+                },
+            ],
+        });
+
+        let ocean = Self {
+            settings,
+            uniform_buffer,
+            spectrum,
+            fields,
+            displacement,
+            normal,
+            foam,
+            foam_history,
+            evolve_pipeline,
+            fft_pipeline,
+            resolve_pipeline,
+            foam_pipeline,
+            evolve_bind_group,
+            fft_bind_groups,
+            resolve_bind_group,
+            foam_bind_group,
+            time: 0.0,
+        };
+        ocean.upload_spectrum(queue);
+        ocean
+    }
+
+    pub fn settings(&self) -> &OceanSettings {
+        &self.settings
+    }
+
+    pub fn set_spectrum(&mut self, queue: &wgpu::Queue, spectrum: SpectrumSettings) {
+        self.settings.spectrum = spectrum;
+        self.upload_spectrum(queue);
+    }
+
+    pub fn set_foam(&mut self, queue: &wgpu::Queue, foam: FoamSettings) {
+        self.settings.foam = foam;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&OceanUniform::new(&self.settings, self.time, 0.0)));
+    }
+
+    fn upload_spectrum(&self, queue: &wgpu::Queue) {
+        let n = self.settings.resolution;
+        let h0 = self.settings.spectrum.generate(n, self.settings.patch_size);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.spectrum.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&h0),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(n * size_of::<[f32; 4]>() as u32),
+                rows_per_image: Some(n),
+            },
+            self.spectrum.texture.size(),
+        );
+    }
+
+    /// Foam fades by the time elapsed since the previous update.
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32) {
+        let time_step = (time - self.time).max(0.0);
+        self.time = time;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&OceanUniform::new(&self.settings, time, time_step)));
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let groups = self.settings.resolution / WORKGROUP_SIZE;
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Ocean Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.evolve_pipeline);
+        compute_pass.set_bind_group(0, &self.evolve_bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+
+        compute_pass.set_pipeline(&self.fft_pipeline);
+        for bind_group in &self.fft_bind_groups {
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(groups / 2, groups, 2);
+        }
+
+        compute_pass.set_pipeline(&self.resolve_pipeline);
+        compute_pass.set_bind_group(0, &self.resolve_bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+
+        compute_pass.set_pipeline(&self.foam_pipeline);
+        compute_pass.set_bind_group(0, &self.foam_bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+        drop(compute_pass);
+
+        encoder.copy_texture_to_texture(
+            self.foam.texture.as_image_copy(),
+            self.foam_history.texture.as_image_copy(),
+            self.foam.texture.size(),
+        );
+    }
+
+    pub fn create_bind_group(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ocean Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.displacement.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&self.displacement.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&self.foam.view),
+                },
+            ],
+        })
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Ocean Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+        ],
+    };
+}
+
+const EVOLVE_BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("Ocean Evolve Bind Group Layout"),
+    entries: &[
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+    ],
+};
@@ -0,0 +1,34 @@
+const TAU: f32 = 6.28318530718;
+const GRAVITY: f32 = 9.81;
+
+struct Ocean {
+    resolution: u32,
+    patch_size: f32,
+    time: f32,
+    choppiness: f32,
+    time_step: f32,
+    foam_coverage: f32,
+    foam_decay: f32,
+    foam_threshold: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> ocean: Ocean;
+
+fn wave_vector(id: vec2<u32>) -> vec2<f32> {
+    let n = vec2<i32>(id) - i32(ocean.resolution / 2u);
+    return TAU * vec2<f32>(n) / ocean.patch_size;
+}
+
+fn cmul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn conj(a: vec2<f32>) -> vec2<f32> {
+    return vec2(a.x, -a.y);
+}
+
+// Packs two real-valued signals' spectra into one complex spectrum, a + i * b.
+fn pack(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2(a.x - b.y, a.y + b.x);
+}
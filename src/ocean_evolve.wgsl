@@ -0,0 +1,29 @@
+@group(0) @binding(1)
+var spectrum: texture_2d<f32>;
+@group(0) @binding(2)
+var<storage, read_write> fields: array<vec4<f32>>;
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ocean.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let k = wave_vector(id.xy);
+    let k_length = max(length(k), 0.0001);
+    let omega = sqrt(GRAVITY * k_length);
+    let phase = vec2(cos(omega * ocean.time), sin(omega * ocean.time));
+
+    let h0 = textureLoad(spectrum, id.xy, 0);
+    let h = cmul(h0.xy, phase) + cmul(h0.zw, conj(phase));
+
+    // Horizontal displacement i * k / |k| * h and slopes i * k * h.
+    let dx = cmul(vec2(0.0, k.x / k_length), h);
+    let dz = cmul(vec2(0.0, k.y / k_length), h);
+    let sx = cmul(vec2(0.0, k.x), h);
+    let sz = cmul(vec2(0.0, k.y), h);
+
+    let index = id.y * n + id.x;
+    fields[index] = vec4(pack(dx, dz), pack(h, sx));
+    fields[index + n * n] = vec4(pack(sz, vec2(0.0)), vec2(0.0));
+}
@@ -0,0 +1,44 @@
+struct FftStage {
+    stage: u32,
+    horizontal: u32,
+}
+
+@group(0) @binding(1)
+var<uniform> fft: FftStage;
+@group(0) @binding(2)
+var<storage, read> input: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read_write> output: array<vec4<f32>>;
+
+fn element(i: u32, line: u32) -> u32 {
+    if fft.horizontal != 0u {
+        return line * ocean.resolution + i;
+    }
+    return i * ocean.resolution + line;
+}
+
+// One radix-2 Stockham stage of an inverse FFT along rows or columns.
+// Each invocation computes one butterfly on both complex pairs of a texel.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ocean.resolution;
+    let half = n / 2u;
+    let i = id.x;
+    let line = id.y;
+    if i >= half || line >= n {
+        return;
+    }
+    let base = id.z * n * n;
+    let span = 1u << fft.stage;
+    let k = i & (span - 1u);
+    let j = ((i >> fft.stage) << (fft.stage + 1u)) + k;
+
+    let angle = TAU * f32(k) / f32(2u * span);
+    let w = vec2(cos(angle), sin(angle));
+    let a = input[base + element(i, line)];
+    let b = input[base + element(i + half, line)];
+    let wb = vec4(cmul(w, b.xy), cmul(w, b.zw));
+
+    output[base + element(j, line)] = a + wb;
+    output[base + element(j + span, line)] = a - wb;
+}
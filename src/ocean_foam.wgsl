@@ -0,0 +1,29 @@
+@group(0) @binding(1)
+var displacement: texture_2d<f32>;
+@group(0) @binding(2)
+var history: texture_2d<f32>;
+@group(0) @binding(3)
+var foam: texture_storage_2d<rgba16float, write>;
+
+fn offset_at(id: vec2<u32>, dx: i32, dz: i32) -> vec3<f32> {
+    let n = i32(ocean.resolution);
+    let texel = (vec2<i32>(id) + vec2(dx, dz) + n) % n;
+    return textureLoad(displacement, texel, 0).xyz;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ocean.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    // Central differences of the horizontal displacement, which tiles with the patch.
+    let spacing = 2.0 * ocean.patch_size / f32(n);
+    let ddx = (offset_at(id.xy, 1, 0) - offset_at(id.xy, -1, 0)).xz / spacing;
+    let ddz = (offset_at(id.xy, 0, 1) - offset_at(id.xy, 0, -1)).xz / spacing;
+    let jacobian = (1.0 + ddx.x) * (1.0 + ddz.y) - ddx.y * ddz.x;
+
+    let whitecap = clamp(ocean.foam_coverage * (ocean.foam_threshold - jacobian), 0.0, 1.0);
+    let previous = textureLoad(history, id.xy, 0).x * exp(-ocean.foam_decay * ocean.time_step);
+    textureStore(foam, id.xy, vec4(max(previous, whitecap), jacobian, 0.0, 0.0));
+}
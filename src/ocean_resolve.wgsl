@@ -0,0 +1,24 @@
+@group(0) @binding(1)
+var<storage, read> fields: array<vec4<f32>>;
+@group(0) @binding(2)
+var displacement: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(3)
+var normal: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ocean.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    // The spectrum is stored with k = 0 in the centre, which shifts every
+    // spatial sample by a phase of (-1)^(x + y).
+    let sign = select(-1.0, 1.0, ((id.x + id.y) & 1u) == 0u);
+    let index = id.y * n + id.x;
+    let a = fields[index] * sign;
+    let b = fields[index + n * n] * sign;
+
+    let offset = vec3(ocean.choppiness * a.x, a.z, ocean.choppiness * a.y);
+    textureStore(displacement, id.xy, vec4(offset, 0.0));
+    textureStore(normal, id.xy, vec4(normalize(vec3(-a.w, 1.0, -b.x)), 0.0));
+}
@@ -0,0 +1,67 @@
+// Final pass from the scene target to the screen, which fogs whatever is seen from under the water.
+
+// Extinction per metre of the water between the camera and what it sees, matching the water shader.
+const ABSORPTION: vec3<f32> = vec3(0.45, 0.09, 0.06);
+// Colour of the light scattered towards the camera just below the surface.
+const FOG_COLOR: vec3<f32> = vec3(0.0, 0.2, 0.25);
+// Half the width in metres, on the near plane, of the band where the surface meets the lens.
+const WATERLINE_WIDTH: f32 = 0.002;
+const WATERLINE_COLOR: vec3<f32> = vec3(0.02, 0.1, 0.12);
+
+struct Underwater {
+    waterline: vec4<f32>,
+    depth: f32,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var scene_sampler: sampler;
+@group(0) @binding(2)
+var scene_depth: texture_2d<f32>;
+@group(0) @binding(3)
+var<uniform> camera: Camera;
+@group(0) @binding(4)
+var<uniform> underwater: Underwater;
+
+// A single triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> Fragment {
+    let uv = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    var fragment: Fragment;
+    fragment.pos = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragment.uv = uv;
+    return fragment;
+}
+
+fn unproject(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let position = camera.inverse_view_proj * vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    // The sky is at w = 0 when the projection has no far plane, which this keeps finite.
+    return position.xyz / max(position.w, 1e-6);
+}
+
+@fragment
+fn fs_main(fragment: Fragment) -> @location(0) vec4<f32> {
+    let sample = textureSample(scene, scene_sampler, fragment.uv);
+    let color = sample.rgb;
+    // Signed height above the water of the point on the lens this pixel looks through.
+    let lens = dot(underwater.waterline.xyz, unproject(fragment.uv, camera.near_depth)) + underwater.waterline.w;
+    let waterline = 1.0 - smoothstep(0.0, WATERLINE_WIDTH, abs(lens));
+    // The plane misses waves rising over the lens, but the water shader marks their undersides.
+    if lens >= 0.0 && sample.a > 0.5 {
+        return vec4(mix(color, WATERLINE_COLOR, waterline), 1.0);
+    }
+
+    let depth = textureLoad(scene_depth, vec2<u32>(fragment.pos.xy), 0).r;
+    let distance = length(unproject(fragment.uv, depth) - camera.eye);
+    let transmittance = exp(-ABSORPTION * distance);
+    // Less light reaches the water around the camera the deeper it is.
+    let ambient = FOG_COLOR * exp(-ABSORPTION * max(underwater.depth, 0.0));
+    let fogged = color * transmittance + ambient * (1.0 - transmittance);
+    return vec4(mix(fogged, WATERLINE_COLOR, waterline), 1.0);
+}
@@ -0,0 +1,188 @@
+#![allow(dead_code)]
+
+use crate::{
+    camera::{Camera, Projection},
+    vertex::TextureVertex,
+};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+// Fraction of the screen the grid reaches past its sides and near edge, so waves pushed sideways
+// into view still have vertices under them.
+const SCREEN_MARGIN: f32 = 0.1;
+// Least height of the projector above or below the displaced water, so its rays never graze it.
+const PROJECTOR_CLEARANCE: f32 = 1.0;
+// Where the grid stops when the projection has no far plane.
+const MAX_DISTANCE: f32 = 10000.0;
+
+/// How the water surface is meshed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WaterGrid {
+    /// A fixed square grid around the origin.
+    #[default]
+    Fixed,
+    /// A grid laid out on screen and projected onto the water plane, out to the far plane.
+    Projected,
+    /// Patches around the camera that grow with distance. See `QuadtreeSettings`.
+    Quadtree,
+}
+
+/// Where the corners of a screen-space grid meet the water plane, as homogeneous points.
+/// Interpolating them bilinearly and dividing by w spaces the grid evenly on screen.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ProjectedGridUniform {
+    /// At grid coordinates (0, 0), (1, 0), (0, 1) and (1, 1).
+    pub corners: [[f32; 4]; 4],
+}
+
+impl ProjectedGridUniform {
+    /// Covers the part of the screen where the water, displaced up to `max_height` either side of
+    /// `level`, can appear. None when the camera sees none of it.
+    pub fn new(camera: &Camera, projection: &Projection, level: f32, max_height: f32) -> Option<Self> {
+        let inverse = (projection.to_matrix() * camera.0.to_homogeneous())
+            .try_inverse()
+            .expect("Failed to invert view projection matrix");
+        let depths = [projection.near_depth(), projection.depth(projection.z_far().min(MAX_DISTANCE))];
+        let frustum: Vec<_> = (0..8)
+            .map(|i| {
+                let ndc = na::Point3::new(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -1.0 } else { 1.0 },
+                    depths[i >> 2],
+                );
+                inverse.transform_point(&ndc)
+            })
+            .collect();
+
+        // The frustum corners inside the slab the water moves in, and where its edges cross the slab.
+        let mut points = Vec::new();
+        for (i, &a) in frustum.iter().enumerate() {
+            if (a.y - level).abs() <= max_height {
+                points.push(a);
+            }
+            for bit in [1, 2, 4] {
+                if i & bit != 0 {
+                    continue;
+                }
+                let b = frustum[i | bit];
+                for height in [level - max_height, level + max_height] {
+                    let (da, db) = (a.y - height, b.y - height);
+                    if da * db < 0.0 {
+                        points.push(a + (b - a) * (da / (da - db)));
+                    }
+                }
+            }
+        }
+
+        // Their bounds seen from the projector, once flattened onto the water plane.
+        let (projector, above) = Self::projector(camera, projection, level, max_height);
+        let mut min = na::Vector2::repeat(f32::MAX);
+        let mut max = na::Vector2::repeat(f32::MIN);
+        for point in points {
+            let clip = projector * na::Vector4::new(point.x, level, point.z, 1.0);
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let ndc = clip.xy() / clip.w;
+            min = min.inf(&ndc);
+            max = max.sup(&ndc);
+        }
+        if min.x > max.x {
+            return None;
+        }
+        // Widening towards the horizon would wrap the grid past it, so only the near edge moves.
+        let margin = 2.0 * SCREEN_MARGIN;
+        min.x -= margin;
+        max.x += margin;
+        if above {
+            min.y -= margin;
+        } else {
+            max.y += margin;
+        }
+
+        let inverse = projector.try_inverse().expect("Failed to invert projector matrix");
+        let corner = |x: f32, y: f32| -> [f32; 4] {
+            let near = inverse * na::Vector4::new(x, y, 0.0, 1.0);
+            let far = inverse * na::Vector4::new(x, y, 1.0, 1.0);
+            // Heights above the plane, scaled by w, which vary linearly along the homogeneous line.
+            let (dn, df) = (near.y - level * near.w, far.y - level * far.w);
+            (near + (far - near) * (dn / (dn - df))).into()
+        };
+        Some(Self {
+            corners: [corner(min.x, min.y), corner(max.x, min.y), corner(min.x, max.y), corner(max.x, max.y)],
+        })
+    }
+
+    /// The camera, moved out of the water's slab if need be, and whether it looks from above.
+    fn projector(camera: &Camera, projection: &Projection, level: f32, max_height: f32) -> (na::Matrix4<f32>, bool) {
+        let mut eye = camera.eye();
+        let above = eye.y >= level;
+        let clearance = max_height + PROJECTOR_CLEARANCE;
+        if (eye.y - level).abs() < clearance {
+            eye.y = if above { level + clearance } else { level - clearance };
+        }
+        let rotation = camera.0.rotation;
+        let view = na::Isometry3::from_parts(na::Translation3::from(-(rotation * eye.coords)), rotation);
+        (projection.to_matrix() * view.to_homogeneous(), above)
+    }
+
+    /// Rest position on the water plane of grid point `uv`. Mirrors `vs_projected` in `water.wgsl`.
+    pub fn position(&self, uv: na::Point2<f32>) -> na::Point3<f32> {
+        let [c00, c10, c01, c11] = self.corners.map(na::Vector4::from);
+        let point = (c00.lerp(&c10, uv.x)).lerp(&c01.lerp(&c11, uv.x), uv.y);
+        na::Point3::from(point.xyz() / point.w)
+    }
+}
+
+/// The screen-space grid mesh for `WaterGrid::Projected` and the corners it is projected with.
+pub struct ProjectedGrid {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    index_count: u32,
+    pub uniform_buffer: wgpu::Buffer,
+    visible: bool,
+}
+
+impl ProjectedGrid {
+    pub fn new(device: &wgpu::Device, subdivisions: u32) -> Self {
+        let (vertices, indices) = TextureVertex::grid(1.0, subdivisions);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Projected Grid Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Projected Grid Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Projected Grid Buffer"),
+            contents: bytemuck::bytes_of(&ProjectedGridUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            uniform_buffer,
+            visible: false,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, projection: &Projection, level: f32, max_height: f32) {
+        let uniform = ProjectedGridUniform::new(camera, projection, level, max_height);
+        self.visible = uniform.is_some();
+        if let Some(uniform) = uniform {
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+    }
+
+    /// Indices to draw, none while the water is out of view.
+    pub fn index_count(&self) -> u32 {
+        if self.visible { self.index_count } else { 0 }
+    }
+}
@@ -0,0 +1,284 @@
+#![allow(dead_code)]
+
+use crate::{
+    camera::{Camera, Projection},
+    vertex::{TextureVertex, Vertex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+/// Quads along each side of a patch. Matches PATCH_RESOLUTION in `water.wgsl`. Even, so an edge
+/// stitched to a coarser patch can drop every other vertex.
+pub const PATCH_RESOLUTION: u32 = 32;
+const MAX_PATCHES: usize = 1024;
+
+/// Which edges of a patch border a patch twice its size, as bits of `WaterPatch::stitch`.
+pub const STITCH_NEG_X: u32 = 1;
+pub const STITCH_POS_X: u32 = 2;
+pub const STITCH_NEG_Z: u32 = 4;
+pub const STITCH_POS_Z: u32 = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct QuadtreeSettings {
+    /// Side of the square around the camera the patches cover.
+    pub size: f32,
+    /// Side of the smallest patches, which the camera is surrounded by.
+    pub min_patch_size: f32,
+    /// A patch is split while the camera is closer to it than this many of its sides.
+    pub split_distance: f32,
+}
+
+impl Default for QuadtreeSettings {
+    fn default() -> Self {
+        Self {
+            size: 1024.0,
+            min_patch_size: 4.0,
+            split_distance: 1.5,
+        }
+    }
+}
+
+/// A square of water drawn with the shared patch mesh, one instance each.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct WaterPatch {
+    /// Corner with the lowest x and z.
+    pub origin: [f32; 2],
+    pub size: f32,
+    /// `STITCH_*` bits of the edges to stitch to a coarser neighbour.
+    pub stitch: u32,
+}
+
+impl Vertex for WaterPatch {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: size_of::<[f32; 2]>() as u64,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: size_of::<[f32; 3]>() as u64,
+                shader_location: 4,
+            },
+        ],
+    };
+}
+
+struct Node {
+    origin: na::Point2<f32>,
+    size: f32,
+    // Index of the first of four children, in z-major order.
+    children: Option<usize>,
+}
+
+impl QuadtreeSettings {
+    /// Patches covering the water around the camera, smaller the closer they are, balanced so that
+    /// neighbours differ in size by at most a factor of two. Patches outside the view frustum, with
+    /// the water displaced up to `max_height` either side of `level`, are left out.
+    pub fn select_patches(&self, camera: &Camera, projection: &Projection, level: f32, max_height: f32) -> Vec<WaterPatch> {
+        let eye = camera.eye();
+        // Snapped to the root's vertex spacing, so every patch's vertices stay put as the camera moves.
+        let spacing = self.size / PATCH_RESOLUTION as f32;
+        let center = (eye.xz().coords / spacing).map(f32::round) * spacing;
+        let mut nodes = vec![Node {
+            origin: na::Point2::from(center - na::Vector2::repeat(self.size * 0.5)),
+            size: self.size,
+            children: None,
+        }];
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &nodes[index];
+            let (low, high) = self.bounds(node, level, max_height);
+            let closest = eye.coords.sup(&low.coords).inf(&high.coords);
+            if node.size * 0.5 >= self.min_patch_size && (eye.coords - closest).norm() < self.split_distance * node.size {
+                stack.extend(split(&mut nodes, index));
+            }
+        }
+
+        // Splits leaves next to much smaller ones until neighbours are within a factor of two.
+        loop {
+            let leaves: Vec<_> = (0..nodes.len()).filter(|&i| nodes[i].children.is_none()).collect();
+            let mut balanced = true;
+            for index in leaves {
+                let (origin, size) = (nodes[index].origin, nodes[index].size);
+                let too_fine = edge_points(origin, size, 4)
+                    .any(|point| find_leaf(&nodes, point).is_some_and(|leaf| nodes[leaf].size < size * 0.5));
+                if too_fine {
+                    split(&mut nodes, index);
+                    balanced = false;
+                }
+            }
+            if balanced {
+                break;
+            }
+        }
+
+        let planes = frustum_planes(&(projection.to_matrix() * camera.0.to_homogeneous()));
+        let mut patches: Vec<_> = (0..nodes.len())
+            .filter(|&i| nodes[i].children.is_none())
+            .filter(|&i| {
+                let (low, high) = self.bounds(&nodes[i], level, max_height);
+                planes.iter().all(|plane| {
+                    let farthest = na::Vector3::from_fn(|axis, _| if plane[axis] >= 0.0 { high[axis] } else { low[axis] });
+                    plane.xyz().dot(&farthest) + plane.w >= 0.0
+                })
+            })
+            .map(|i| {
+                let node = &nodes[i];
+                let mut stitch = 0;
+                for (bit, point) in [STITCH_NEG_X, STITCH_POS_X, STITCH_NEG_Z, STITCH_POS_Z].into_iter().zip(edge_points(node.origin, node.size, 1)) {
+                    if find_leaf(&nodes, point).is_some_and(|leaf| nodes[leaf].size > node.size) {
+                        stitch |= bit;
+                    }
+                }
+                WaterPatch {
+                    origin: node.origin.into(),
+                    size: node.size,
+                    stitch,
+                }
+            })
+            .collect();
+        if patches.len() > MAX_PATCHES {
+            log::warn!("Only {MAX_PATCHES} of {} water patches are drawn", patches.len());
+            patches.truncate(MAX_PATCHES);
+        }
+        patches
+    }
+
+    fn bounds(&self, node: &Node, level: f32, max_height: f32) -> (na::Point3<f32>, na::Point3<f32>) {
+        (
+            na::Point3::new(node.origin.x, level - max_height, node.origin.y),
+            na::Point3::new(node.origin.x + node.size, level + max_height, node.origin.y + node.size),
+        )
+    }
+}
+
+fn split(nodes: &mut Vec<Node>, index: usize) -> std::ops::Range<usize> {
+    let first = nodes.len();
+    let (origin, half) = (nodes[index].origin, nodes[index].size * 0.5);
+    for (x, z) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+        nodes.push(Node {
+            origin: origin + na::Vector2::new(x, z) * half,
+            size: half,
+            children: None,
+        });
+    }
+    nodes[index].children = Some(first);
+    first..first + 4
+}
+
+fn find_leaf(nodes: &[Node], point: na::Point2<f32>) -> Option<usize> {
+    let root = &nodes[0];
+    let offset = point - root.origin;
+    if offset.min() < 0.0 || offset.max() >= root.size {
+        return None;
+    }
+    let mut index = 0;
+    while let Some(first) = nodes[index].children {
+        let node = &nodes[index];
+        let half = node.size * 0.5;
+        let x = (point.x >= node.origin.x + half) as usize;
+        let z = (point.y >= node.origin.y + half) as usize;
+        index = first + x + 2 * z;
+    }
+    Some(index)
+}
+
+// Points just outside each edge of a square, `count` per edge at the middles of equal segments,
+// in the order -x, +x, -z, +z.
+fn edge_points(origin: na::Point2<f32>, size: f32, count: usize) -> impl Iterator<Item = na::Point2<f32>> {
+    let outside = size * 1e-3;
+    [
+        (na::Vector2::new(-outside, 0.0), na::Vector2::y()),
+        (na::Vector2::new(size + outside, 0.0), na::Vector2::y()),
+        (na::Vector2::new(0.0, -outside), na::Vector2::x()),
+        (na::Vector2::new(0.0, size + outside), na::Vector2::x()),
+    ]
+    .into_iter()
+    .flat_map(move |(start, along)| {
+        (0..count).map(move |i| origin + start + along * ((i as f32 + 0.5) / count as f32 * size))
+    })
+}
+
+// Planes of the view frustum as (normal, distance), positive inside, with the 0 to 1 depth range.
+fn frustum_planes(view_proj: &na::Matrix4<f32>) -> [na::Vector4<f32>; 6] {
+    let row = |i: usize| view_proj.row(i).transpose();
+    [
+        row(3) + row(0),
+        row(3) - row(0),
+        row(3) + row(1),
+        row(3) - row(1),
+        row(2),
+        row(3) - row(2),
+    ]
+}
+
+/// The shared patch mesh and the instances selected for the current view, for `WaterGrid::Quadtree`.
+pub struct WaterQuadtree {
+    settings: QuadtreeSettings,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub instance_buffer: wgpu::Buffer,
+    patch_count: u32,
+}
+
+impl WaterQuadtree {
+    pub fn new(device: &wgpu::Device, settings: QuadtreeSettings) -> Self {
+        let (vertices, indices) = TextureVertex::grid(1.0, PATCH_RESOLUTION);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Water Patch Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Water Patch Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Water Patch Instance Buffer"),
+            size: (MAX_PATCHES * size_of::<WaterPatch>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            settings,
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            instance_buffer,
+            patch_count: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &QuadtreeSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: QuadtreeSettings) {
+        self.settings = settings;
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, projection: &Projection, level: f32, max_height: f32) {
+        let patches = self.settings.select_patches(camera, projection, level, max_height);
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&patches));
+        self.patch_count = patches.len() as u32;
+    }
+
+    pub fn patch_count(&self) -> u32 {
+        self.patch_count
+    }
+}
@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+
+use crate::rng::Rng;
+
+use nalgebra as na;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RainSettings {
+    /// Drops per second on each square metre at full intensity.
+    pub rate: f32,
+    /// Side length in metres of the square, centred on the origin, the rain falls on.
+    pub size: f32,
+    /// Radius of the dent each drop leaves in the water.
+    pub drop_radius: f32,
+    /// Depth in metres of the dent left by the heaviest drops.
+    pub drop_strength: f32,
+    pub seed: u64,
+}
+
+impl Default for RainSettings {
+    fn default() -> Self {
+        Self {
+            rate: 0.4,
+            size: 32.0,
+            drop_radius: 0.15,
+            drop_strength: 0.02,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RainDrop {
+    /// World xz position of the impact.
+    pub position: na::Point2<f32>,
+    /// Fraction of the heaviest drop, from 0.5 to 1.
+    pub strength: f32,
+}
+
+/// Drops landing at random as a Poisson process whose rate follows the intensity. Each drop
+/// waits for an exponentially distributed amount of rain to fall, so the drops are the same
+/// however the time is split into updates.
+pub struct Rain {
+    settings: RainSettings,
+    intensity: f32,
+    rng: Rng,
+    // Expected number of drops still to fall before the next one lands.
+    until_next: f32,
+}
+
+impl Rain {
+    pub fn new(settings: RainSettings) -> Self {
+        let mut rng = Rng::new(settings.seed);
+        let until_next = exponential(&mut rng);
+        Self {
+            settings,
+            intensity: 0.0,
+            rng,
+            until_next,
+        }
+    }
+
+    pub fn settings(&self) -> &RainSettings {
+        &self.settings
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// From 0 for no rain to 1 for the full rate.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0.0, 1.0);
+    }
+
+    /// Drops landing in the next `dt` seconds.
+    pub fn update(&mut self, dt: f32) -> Vec<RainDrop> {
+        let area = self.settings.size * self.settings.size;
+        let mut expected = self.settings.rate * area * self.intensity * dt;
+        let mut drops = Vec::new();
+        while self.until_next <= expected {
+            expected -= self.until_next;
+            let position = na::Point2::new(self.rng.uniform() as f32 - 0.5, self.rng.uniform() as f32 - 0.5)
+                * self.settings.size;
+            drops.push(RainDrop {
+                position,
+                strength: 0.5 + 0.5 * self.rng.uniform() as f32,
+            });
+            self.until_next = exponential(&mut self.rng);
+        }
+        self.until_next -= expected;
+        drops
+    }
+}
+
+fn exponential(rng: &mut Rng) -> f32 {
+    -rng.uniform().ln() as f32
+}
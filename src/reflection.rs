@@ -0,0 +1,71 @@
+#![allow(dead_code)]
+
+use crate::{
+    camera::{Camera, CameraUniform, Projection},
+    texture::Texture,
+};
+
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+// Moves the clip plane slightly past the water level, so reflections still meet wave troughs.
+const CLIP_PLANE_OFFSET: f32 = 0.1;
+
+/// Offscreen target holding the scene mirrored about a horizontal water plane, rendered from a
+/// mirrored camera whose near plane is the water plane.
+pub struct Reflection {
+    pub height: f32,
+    pub color: Texture,
+    pub depth: Texture,
+    camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+}
+
+impl Reflection {
+    /// `camera_layout` is the layout of the `CameraUniform` the scene shaders use.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        camera_layout: &wgpu::BindGroupLayout,
+        height: f32,
+    ) -> Self {
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Reflection Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(na::Matrix4::identity(), na::Matrix4::identity(), 0.0)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Reflection Camera Bind Group"),
+            layout: camera_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            height,
+            color: Texture::create_render_target(device, config, Some("Reflection Texture")),
+            depth: Texture::create_depth_texture(device, config, Some("Reflection Depth Texture")),
+            camera_buffer,
+            camera_bind_group,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.color = Texture::create_render_target(device, config, Some("Reflection Texture"));
+        self.depth = Texture::create_depth_texture(device, config, Some("Reflection Depth Texture"));
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera, projection: Projection) {
+        let view = camera.reflected_view(self.height);
+        // Keep the side of the water the camera is on, which the mirrored camera sees from behind.
+        let side = (camera.eye().y - self.height).signum();
+        let plane = na::Vector4::new(0.0, side, 0.0, -side * self.height + CLIP_PLANE_OFFSET);
+        let view_plane = view.try_inverse().expect("Failed to invert reflected view matrix").transpose() * plane;
+        let uniform = CameraUniform::new(view, projection.to_oblique_matrix(view_plane), projection.near_depth());
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+}
@@ -0,0 +1,1564 @@
+#![allow(dead_code)]
+
+use crate::{
+    buoyancy::{Buoyancy, BuoyancySettings, FloatingBody},
+    camera::{Camera, CameraUniform, LightCamera, Projection},
+    carving::{SubmergedVolume, HULL_MASK_STENCIL, OUTSIDE_HULLS_STENCIL},
+    caustics::Caustics,
+    fluid_surface::FluidSurface,
+    flow::{FlowMap, FlowSettings},
+    ocean::{FoamSettings, Ocean, OceanSettings},
+    projected_grid::{ProjectedGrid, WaterGrid},
+    quadtree::{QuadtreeSettings, WaterPatch, WaterQuadtree},
+    rain::{Rain, RainSettings},
+    reflection::Reflection,
+    ripple::{RippleSettings, Ripples},
+    shallow_water::{GpuShallowWater, ShallowWater},
+    spectrum::SpectrumSettings,
+    splash::{SplashParticle, Splashes, MAX_SPLASH_PARTICLES},
+    sph::{GpuSph, Sph, SphParticle},
+    surface::WaterSurface,
+    surface_detail::SurfaceDetail,
+    texture::Texture,
+    underwater::UnderwaterUniform,
+    vertex::{ModelInstance, ModelVertex, TextureVertex, Vertex as _},
+    wake::{WakeEmitter, WakeSettings, Wakes},
+    wave::{GerstnerWave, WaveUniform},
+};
+
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+const WATER_SIZE: f32 = 32.0;
+const WATER_SUBDIVISIONS: u32 = 256;
+const SEABED_SUBDIVISIONS: u32 = 64;
+const WATER_LEVEL: f32 = 0.0;
+const PROJECTED_GRID_SUBDIVISIONS: u32 = 256;
+const RIPPLE_RADIUS: f32 = 0.4;
+// Furthest along a click's ray the water is looked for, as an infinite projection sets no limit.
+const MAX_PICK_DISTANCE: f32 = 1000.0;
+const CAUSTIC_RESOLUTION: u32 = 512;
+// Matches SUN_DIRECTION in the shaders.
+const SUN_DIRECTION: na::Vector3<f32> = na::Vector3::new(0.4082483, 0.8164966, 0.4082483);
+const SKY_COLOR: wgpu::Color = wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 };
+
+/// Everything drawn each frame and the simulations behind it, independent of the window.
+pub struct Renderer {
+    pipelines: ScenePipelines,
+    pipeline_layouts: ScenePipelineLayouts,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+    water_grid: WaterGrid,
+    projected_grid: ProjectedGrid,
+    quadtree: WaterQuadtree,
+    projection: Projection,
+    camera: Camera,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    depth_texture: Texture,
+    // The opaque scene is drawn here first and copied, with its depth, for the water to refract.
+    scene: Texture,
+    underwater_buffer: wgpu::Buffer,
+    post_bind_group_layout: wgpu::BindGroupLayout,
+    post_bind_group: wgpu::BindGroup,
+    refraction: Texture,
+    refraction_depth: Texture,
+    post_pipeline: wgpu::RenderPipeline,
+    reflection: Reflection,
+    view_bind_group_layout: wgpu::BindGroupLayout,
+    view_bind_group: wgpu::BindGroup,
+    water: WaterSurface,
+    wave_buffer: wgpu::Buffer,
+    wave_bind_group: wgpu::BindGroup,
+    ocean: Ocean,
+    ocean_bind_group: wgpu::BindGroup,
+    ripples: Ripples,
+    surface_detail: SurfaceDetail,
+    surface_detail_bind_group: wgpu::BindGroup,
+    wakes: Wakes,
+    rain: Rain,
+    splashes: Splashes,
+    fluid: Option<GpuSph>,
+    fluid_surface: FluidSurface,
+    basin: Option<Basin>,
+    basin_bind_group_layout: wgpu::BindGroupLayout,
+    flow_bind_group_layout: wgpu::BindGroupLayout,
+    flow: FlowSettings,
+    seabed_vertex_buffer: wgpu::Buffer,
+    seabed_index_buffer: wgpu::Buffer,
+    seabed_index_count: u32,
+    caustics: Caustics,
+    caustic_bind_group: wgpu::BindGroup,
+    caustic_pipeline: wgpu::RenderPipeline,
+    buoyancy: Buoyancy,
+    body_vertex_buffer: wgpu::Buffer,
+    body_index_buffer: wgpu::Buffer,
+    body_index_count: u32,
+    body_instance_buffer: wgpu::Buffer,
+    submerged: SubmergedVolume,
+    time: f32,
+}
+
+impl Renderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        mut projection: Projection,
+    ) -> Self {
+        let depth_texture = Texture::create_depth_texture(device, config, Some("Depth Texture"));
+
+        let camera_bind_group_layout = device.create_bind_group_layout(&CameraUniform::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let camera = Camera(na::Isometry3::look_at_rh(
+            &na::Point3::new(0.0, 4.0, 12.0),
+            &na::Point3::new(0.0, 0.0, 0.0),
+            &na::Vector3::y(),
+        ));
+        projection.set_aspect(config.width as f32 / config.height as f32);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::from_camera(&camera, &projection)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let scene = Texture::create_render_target(device, config, Some("Scene Texture"));
+        let refraction = Texture::create_render_target(device, config, Some("Refraction Texture"));
+        let refraction_depth = Texture::create_depth_texture(device, config, Some("Refraction Depth Texture"));
+        let underwater_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Underwater Buffer"),
+            contents: bytemuck::bytes_of(&UnderwaterUniform::DRY),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let post_bind_group_layout = device.create_bind_group_layout(&POST_BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let post_bind_group = create_post_bind_group(
+            device,
+            &post_bind_group_layout,
+            &scene,
+            &depth_texture,
+            &camera_buffer,
+            &underwater_buffer,
+        );
+
+        let reflection = Reflection::new(device, config, &camera_bind_group_layout, WATER_LEVEL);
+        reflection.update(queue, &camera, projection);
+        let projected_grid = ProjectedGrid::new(device, PROJECTED_GRID_SUBDIVISIONS);
+        let view_bind_group_layout = device.create_bind_group_layout(&VIEW_BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let view_bind_group = create_view_bind_group(
+            device,
+            &view_bind_group_layout,
+            &camera_buffer,
+            &projected_grid.uniform_buffer,
+            &reflection,
+            &refraction,
+            &refraction_depth,
+        );
+
+        let wave_bind_group_layout = device.create_bind_group_layout(&WaveUniform::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let ocean_settings = OceanSettings::default();
+        let water = WaterSurface::new(WaveUniform::default().waves(), ocean_settings);
+        let wave_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wave Buffer"),
+            contents: bytemuck::bytes_of(water.waves()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let wave_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Wave Bind Group"),
+            layout: &wave_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wave_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let ocean_bind_group_layout = device.create_bind_group_layout(&Ocean::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let ocean = Ocean::new(device, queue, ocean_settings);
+        let ocean_bind_group = ocean.create_bind_group(device, &ocean_bind_group_layout);
+
+        let mut ripples = Ripples::new(device, RippleSettings {
+            size: WATER_SIZE,
+            ..Default::default()
+        });
+        let submerged = SubmergedVolume::new(device, ripples.settings(), WATER_LEVEL);
+        ripples.set_displacement(device, &submerged);
+        let wakes = Wakes::new(device, WakeSettings {
+            size: WATER_SIZE,
+            ..Default::default()
+        });
+        let surface_detail_bind_group_layout = device.create_bind_group_layout(&SurfaceDetail::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let surface_detail_bind_group =
+            SurfaceDetail::create_bind_group(device, &surface_detail_bind_group_layout, &ripples, &wakes);
+        let surface_detail = SurfaceDetail::new(device, &ripples, &wakes);
+
+        let rain = Rain::new(RainSettings {
+            size: WATER_SIZE,
+            ..Default::default()
+        });
+        let splashes = Splashes::new(device, 1);
+
+        let fluid_surface = FluidSurface::new(
+            device,
+            config,
+            &camera_bind_group_layout,
+            &view_bind_group_layout,
+            &projection,
+        );
+
+        let (vertices, indices) = TextureVertex::grid(WATER_SIZE, WATER_SUBDIVISIONS);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let index_count = indices.len() as u32;
+
+        let water_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &view_bind_group_layout,
+                &wave_bind_group_layout,
+                &ocean_bind_group_layout,
+                &surface_detail_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let quadtree = WaterQuadtree::new(device, QuadtreeSettings::default());
+
+        let (seabed_vertices, seabed_indices) = TextureVertex::grid(WATER_SIZE, SEABED_SUBDIVISIONS);
+        let seabed_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Seabed Vertex Buffer"),
+            contents: bytemuck::cast_slice(&seabed_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let seabed_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Seabed Index Buffer"),
+            contents: bytemuck::cast_slice(&seabed_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let seabed_index_count = seabed_indices.len() as u32;
+        let caustics = Caustics::new(device, &camera_bind_group_layout, LightCamera {
+            direction: na::Unit::new_normalize(SUN_DIRECTION),
+            center: na::Point3::new(0.0, -1.0, 0.0),
+            // Covers the whole water grid, which the sun sees at a slant.
+            half_size: WATER_SIZE * 0.75,
+            depth: 4.0 * WATER_SIZE,
+        }, CAUSTIC_RESOLUTION);
+        let caustic_bind_group_layout = device.create_bind_group_layout(&Caustics::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let caustic_bind_group = caustics.create_bind_group(device, &caustic_bind_group_layout);
+        let caustic_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Caustic Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                [
+                    include_str!("camera.wgsl"),
+                    include_str!("water_shading.wgsl"),
+                    include_str!("water.wgsl"),
+                    include_str!("caustics.wgsl"),
+                ].concat().into(),
+            ),
+        });
+        let caustic_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bind_group_layout,
+                &wave_bind_group_layout,
+                &ocean_bind_group_layout,
+                &surface_detail_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        // Overlapping triangles focus their light on the same texels, so photons add up.
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let caustic_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Caustic Pipeline"),
+            layout: Some(&caustic_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &caustic_shader_module,
+                entry_point: Some("vs_caustics"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[TextureVertex::LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &caustic_shader_module,
+                entry_point: Some("fs_caustics"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Caustics::FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: additive,
+                        alpha: additive,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Refraction can fold triangles over, so both windings have to be drawn.
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let seabed_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bind_group_layout, &caustic_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let buoyancy = Buoyancy::new(BuoyancySettings::default());
+        let (body_vertices, body_indices) = ModelVertex::cube();
+        let body_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Body Vertex Buffer"),
+            contents: bytemuck::cast_slice(&body_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let body_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Body Index Buffer"),
+            contents: bytemuck::cast_slice(&body_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let body_index_count = body_indices.len() as u32;
+        let body_instance_buffer = create_body_instance_buffer(device, &buoyancy);
+
+        let post_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Shader"),
+            source: wgpu::ShaderSource::Wgsl([include_str!("camera.wgsl"), include_str!("post.wgsl")].concat().into()),
+        });
+        let post_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&post_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let post_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Post Pipeline"),
+            layout: Some(&post_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &post_shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &post_shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let basin_bind_group_layout = device.create_bind_group_layout(&GpuShallowWater::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let flow_bind_group_layout = device.create_bind_group_layout(&FlowMap::BIND_GROUP_LAYOUT_DESCRIPTOR);
+        let terrain_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bind_group_layout, &basin_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let basin_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bind_group_layout, &basin_bind_group_layout, &flow_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let camera_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline_layouts = ScenePipelineLayouts {
+            water: water_pipeline_layout,
+            camera: camera_pipeline_layout,
+            seabed: seabed_pipeline_layout,
+            terrain: terrain_pipeline_layout,
+            basin: basin_pipeline_layout,
+        };
+        let pipelines = ScenePipelines::new(device, config.format, &pipeline_layouts, &projection);
+
+        Self {
+            pipelines,
+            pipeline_layouts,
+            vertex_buffer,
+            index_buffer,
+            index_count,
+            water_grid: WaterGrid::default(),
+            projected_grid,
+            quadtree,
+            projection,
+            camera,
+            camera_buffer,
+            camera_bind_group,
+            depth_texture,
+            scene,
+            underwater_buffer,
+            post_bind_group_layout,
+            post_bind_group,
+            refraction,
+            refraction_depth,
+            post_pipeline,
+            reflection,
+            view_bind_group_layout,
+            view_bind_group,
+            water,
+            wave_buffer,
+            wave_bind_group,
+            ocean,
+            ocean_bind_group,
+            ripples,
+            surface_detail,
+            surface_detail_bind_group,
+            wakes,
+            rain,
+            splashes,
+            fluid: None,
+            fluid_surface,
+            basin: None,
+            basin_bind_group_layout,
+            flow_bind_group_layout,
+            flow: FlowSettings::default(),
+            seabed_vertex_buffer,
+            seabed_index_buffer,
+            seabed_index_count,
+            caustics,
+            caustic_bind_group,
+            caustic_pipeline,
+            buoyancy,
+            body_vertex_buffer,
+            body_index_buffer,
+            body_index_count,
+            body_instance_buffer,
+            submerged,
+            time: 0.0,
+        }
+    }
+
+    pub fn water_surface(&self) -> &WaterSurface {
+        &self.water
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn bodies(&self) -> &[FloatingBody] {
+        self.buoyancy.bodies()
+    }
+
+    /// Bodies can be steered by setting their thrust.
+    pub fn bodies_mut(&mut self) -> &mut [FloatingBody] {
+        self.buoyancy.bodies_mut()
+    }
+
+    /// Floats `body` on the water, returning its index in `bodies`.
+    pub fn add_body(&mut self, device: &wgpu::Device, body: FloatingBody) -> usize {
+        let index = self.buoyancy.add_body(body);
+        self.body_instance_buffer = create_body_instance_buffer(device, &self.buoyancy);
+        index
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    /// Keeps the window's aspect ratio. Turning depth around rebuilds the pipelines that test it,
+    /// and leaves the simulations running.
+    pub fn set_projection(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, mut projection: Projection) {
+        projection.set_aspect(self.projection.aspect());
+        if projection.reversed_depth() != self.projection.reversed_depth() {
+            self.pipelines = ScenePipelines::new(device, config.format, &self.pipeline_layouts, &projection);
+            self.fluid_surface.set_projection(device, &projection);
+        }
+        self.projection = projection;
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.projection.set_aspect(config.width as f32 / config.height as f32);
+        self.depth_texture = Texture::create_depth_texture(device, config, Some("Depth Texture"));
+        self.scene = Texture::create_render_target(device, config, Some("Scene Texture"));
+        self.refraction = Texture::create_render_target(device, config, Some("Refraction Texture"));
+        self.refraction_depth = Texture::create_depth_texture(device, config, Some("Refraction Depth Texture"));
+        self.reflection.resize(device, config);
+        self.fluid_surface.resize(device, config);
+        self.view_bind_group = create_view_bind_group(
+            device,
+            &self.view_bind_group_layout,
+            &self.camera_buffer,
+            &self.projected_grid.uniform_buffer,
+            &self.reflection,
+            &self.refraction,
+            &self.refraction_depth,
+        );
+        self.post_bind_group = create_post_bind_group(
+            device,
+            &self.post_bind_group_layout,
+            &self.scene,
+            &self.depth_texture,
+            &self.camera_buffer,
+            &self.underwater_buffer,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: f32) {
+        self.time += dt;
+
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&CameraUniform::from_camera(&self.camera, &self.projection)));
+        self.reflection.update(queue, &self.camera, self.projection);
+        self.fluid_surface.update(queue, &self.projection);
+
+        self.water.update(self.time);
+        self.surface_detail.read_heights();
+        let max_height = self.water.max_height() + self.surface_detail.max_height();
+        match self.water_grid {
+            WaterGrid::Fixed => (),
+            WaterGrid::Projected => {
+                self.projected_grid.update(queue, &self.camera, &self.projection, WATER_LEVEL, max_height)
+            }
+            WaterGrid::Quadtree => self.quadtree.update(queue, &self.camera, &self.projection, WATER_LEVEL, max_height),
+        }
+        let underwater = UnderwaterUniform::new(&self.camera, &self.water, WATER_SIZE * 0.5);
+        queue.write_buffer(&self.underwater_buffer, 0, bytemuck::bytes_of(&underwater));
+        queue.write_buffer(&self.wave_buffer, 0, bytemuck::bytes_of(self.water.waves()));
+        self.ocean.update(queue, self.time);
+        self.buoyancy.update(&self.water, dt);
+        queue.write_buffer(&self.body_instance_buffer, 0, bytemuck::cast_slice(&body_instances(&self.buoyancy)));
+        self.wakes.update(queue, self.time, &wake_emitters(&self.buoyancy));
+        for drop in self.rain.update(dt) {
+            let settings = self.rain.settings();
+            self.ripples.add_impulse(drop.position, settings.drop_radius, settings.drop_strength * drop.strength);
+            let height = self.water.height_at(drop.position.x, drop.position.y);
+            self.splashes.emit(na::Point3::new(drop.position.x, height, drop.position.y), drop.strength);
+        }
+        self.splashes.update(queue, dt);
+        if let Some(fluid) = &mut self.fluid {
+            fluid.update(dt);
+        }
+        self.ripples.update(queue, dt);
+        if let Some(basin) = &mut self.basin {
+            basin.water.update(queue, dt);
+            basin.flow.update(queue, self.time);
+        }
+    }
+
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.ocean.dispatch(&mut encoder);
+        self.submerged.render(
+            &mut encoder,
+            self.body_vertex_buffer.slice(..),
+            self.body_index_buffer.slice(..),
+            self.body_index_count,
+            self.body_instance_buffer.slice(..),
+            self.buoyancy.bodies().len() as u32,
+        );
+        self.ripples.dispatch(&mut encoder);
+        self.wakes.dispatch(&mut encoder);
+        self.surface_detail.reduce_heights(&mut encoder);
+        self.splashes.dispatch(&mut encoder);
+        if let Some(fluid) = &mut self.fluid {
+            fluid.dispatch(&mut encoder);
+        }
+        if let Some(basin) = &mut self.basin {
+            basin.water.dispatch(&mut encoder);
+            basin.flow.dispatch(&mut encoder);
+        }
+
+        let mut caustic_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Caustic Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.caustics.texture.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        caustic_pass.set_pipeline(&self.caustic_pipeline);
+        caustic_pass.set_bind_group(0, &self.caustics.light_bind_group, &[]);
+        caustic_pass.set_bind_group(1, &self.wave_bind_group, &[]);
+        caustic_pass.set_bind_group(2, &self.ocean_bind_group, &[]);
+        caustic_pass.set_bind_group(3, &self.surface_detail_bind_group, &[]);
+        caustic_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        caustic_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        caustic_pass.draw_indexed(0..self.index_count, 0, 0..1);
+        drop(caustic_pass);
+
+        let mut reflection_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Reflection Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.reflection.color.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(SKY_COLOR),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.reflection.depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.projection.far_depth()),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        reflection_pass.set_bind_group(0, &self.reflection.camera_bind_group, &[]);
+        reflection_pass.set_bind_group(1, &self.caustic_bind_group, &[]);
+        reflection_pass.set_pipeline(&self.pipelines.reflected_seabed);
+        reflection_pass.set_vertex_buffer(0, self.seabed_vertex_buffer.slice(..));
+        reflection_pass.set_index_buffer(self.seabed_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        reflection_pass.draw_indexed(0..self.seabed_index_count, 0, 0..1);
+        if let Some(basin) = &self.basin {
+            basin.draw(&mut reflection_pass, &self.pipelines.reflected_terrain, &self.pipelines.reflected_basin);
+        }
+        reflection_pass.set_pipeline(&self.pipelines.reflected_body);
+        reflection_pass.set_vertex_buffer(0, self.body_vertex_buffer.slice(..));
+        reflection_pass.set_vertex_buffer(1, self.body_instance_buffer.slice(..));
+        reflection_pass.set_index_buffer(self.body_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        reflection_pass.draw_indexed(0..self.body_index_count, 0, 0..self.buoyancy.bodies().len() as u32);
+        drop(reflection_pass);
+
+        let mut opaque_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Opaque Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.scene.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(SKY_COLOR),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.projection.far_depth()),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        opaque_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        opaque_pass.set_bind_group(1, &self.caustic_bind_group, &[]);
+        opaque_pass.set_pipeline(&self.pipelines.seabed);
+        opaque_pass.set_vertex_buffer(0, self.seabed_vertex_buffer.slice(..));
+        opaque_pass.set_index_buffer(self.seabed_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        opaque_pass.draw_indexed(0..self.seabed_index_count, 0, 0..1);
+
+        if let Some(basin) = &self.basin {
+            basin.draw(&mut opaque_pass, &self.pipelines.terrain, &self.pipelines.basin);
+        }
+
+        opaque_pass.set_pipeline(&self.pipelines.body);
+        opaque_pass.set_vertex_buffer(0, self.body_vertex_buffer.slice(..));
+        opaque_pass.set_vertex_buffer(1, self.body_instance_buffer.slice(..));
+        opaque_pass.set_index_buffer(self.body_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        opaque_pass.draw_indexed(0..self.body_index_count, 0, 0..self.buoyancy.bodies().len() as u32);
+        drop(opaque_pass);
+
+        // The water samples the opaque scene while drawing over it, so it reads from copies.
+        self.copy_refraction(&mut encoder);
+
+        let mut water_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Water Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.scene.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        // The quadtree draws its patches as instances of one mesh, the other grids a single mesh.
+        let (water_depth_pipeline, water_pipeline, vertex_buffer, index_buffer, index_count, instances) = match self.water_grid {
+            WaterGrid::Fixed => (
+                &self.pipelines.water_depth,
+                &self.pipelines.water,
+                &self.vertex_buffer,
+                &self.index_buffer,
+                self.index_count,
+                None,
+            ),
+            WaterGrid::Projected => (
+                &self.pipelines.projected_water_depth,
+                &self.pipelines.projected_water,
+                &self.projected_grid.vertex_buffer,
+                &self.projected_grid.index_buffer,
+                self.projected_grid.index_count(),
+                None,
+            ),
+            WaterGrid::Quadtree => (
+                &self.pipelines.quadtree_water_depth,
+                &self.pipelines.quadtree_water,
+                &self.quadtree.vertex_buffer,
+                &self.quadtree.index_buffer,
+                self.quadtree.index_count,
+                Some(&self.quadtree.instance_buffer),
+            ),
+        };
+        let instance_count = if instances.is_some() { self.quadtree.patch_count() } else { 1 };
+        // The water's depth first, then the hulls counted in front of it mark the water inside them,
+        // and the water is shaded everywhere else.
+        water_pass.set_pipeline(water_depth_pipeline);
+        water_pass.set_bind_group(0, &self.view_bind_group, &[]);
+        water_pass.set_bind_group(1, &self.wave_bind_group, &[]);
+        water_pass.set_bind_group(2, &self.ocean_bind_group, &[]);
+        water_pass.set_bind_group(3, &self.surface_detail_bind_group, &[]);
+        water_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        if let Some(instances) = instances {
+            water_pass.set_vertex_buffer(1, instances.slice(..));
+        }
+        water_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        water_pass.draw_indexed(0..index_count, 0, 0..instance_count);
+
+        water_pass.set_pipeline(&self.pipelines.hull_mask);
+        water_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        water_pass.set_vertex_buffer(0, self.body_vertex_buffer.slice(..));
+        water_pass.set_vertex_buffer(1, self.body_instance_buffer.slice(..));
+        water_pass.set_index_buffer(self.body_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        water_pass.draw_indexed(0..self.body_index_count, 0, 0..self.buoyancy.bodies().len() as u32);
+
+        water_pass.set_pipeline(water_pipeline);
+        water_pass.set_bind_group(0, &self.view_bind_group, &[]);
+        water_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        if let Some(instances) = instances {
+            water_pass.set_vertex_buffer(1, instances.slice(..));
+        }
+        water_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        water_pass.draw_indexed(0..index_count, 0, 0..instance_count);
+
+        water_pass.set_pipeline(&self.pipelines.splash);
+        water_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        water_pass.set_vertex_buffer(0, self.splashes.particles.slice(..));
+        water_pass.draw(0..4, 0..MAX_SPLASH_PARTICLES);
+        drop(water_pass);
+
+        if let Some(fluid) = &self.fluid {
+            // The fluid refracts the water as well, so the copies are taken again.
+            self.copy_refraction(&mut encoder);
+            self.fluid_surface.render(
+                &mut encoder,
+                fluid.particles.slice(..),
+                fluid.particle_count(),
+                &self.camera_bind_group,
+                &self.view_bind_group,
+                &self.scene,
+                &self.depth_texture,
+            );
+        }
+
+        // Fogs whatever the camera sees from below the water on the way to the screen.
+        let mut post_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(SKY_COLOR),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        post_pass.set_pipeline(&self.post_pipeline);
+        post_pass.set_bind_group(0, &self.post_bind_group, &[]);
+        post_pass.draw(0..3, 0..1);
+        drop(post_pass);
+
+        queue.submit(std::iter::once(encoder.finish()));
+        self.surface_detail.map_heights();
+    }
+
+    fn copy_refraction(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_texture(
+            self.scene.texture.as_image_copy(),
+            self.refraction.texture.as_image_copy(),
+            self.scene.texture.size(),
+        );
+        encoder.copy_texture_to_texture(
+            self.depth_texture.texture.as_image_copy(),
+            self.refraction_depth.texture.as_image_copy(),
+            self.depth_texture.texture.size(),
+        );
+    }
+
+    /// Disturbs the water where the ray through `ndc` on screen first hits it.
+    pub fn disturb_water(&mut self, ndc: na::Point2<f32>, strength: f32) {
+        let (origin, direction) = self.camera.ray(&self.projection, ndc);
+        if let Some(hit) = self.water.intersect_ray(origin, direction, self.projection.z_far().min(MAX_PICK_DISTANCE)) {
+            self.ripples.add_impulse(na::Point2::new(hit.x, hit.z), RIPPLE_RADIUS, strength);
+        }
+    }
+
+    pub fn set_waves(&mut self, queue: &wgpu::Queue, waves: &[GerstnerWave]) {
+        self.water.set_waves(waves);
+        queue.write_buffer(&self.wave_buffer, 0, bytemuck::bytes_of(self.water.waves()));
+    }
+
+    pub fn set_spectrum(&mut self, queue: &wgpu::Queue, spectrum: SpectrumSettings) {
+        self.water.set_spectrum(spectrum);
+        self.ocean.set_spectrum(queue, spectrum);
+    }
+
+    pub fn set_foam(&mut self, queue: &wgpu::Queue, foam: FoamSettings) {
+        self.ocean.set_foam(queue, foam);
+    }
+
+    pub fn set_water_grid(&mut self, grid: WaterGrid) {
+        self.water_grid = grid;
+    }
+
+    /// From 0 for no rain to 1 for a downpour.
+    pub fn set_rain_intensity(&mut self, intensity: f32) {
+        self.rain.set_intensity(intensity);
+    }
+
+    /// Changes the speed and tiling of the river's flow. The map stays over the basin.
+    pub fn set_flow(&mut self, queue: &wgpu::Queue, flow: FlowSettings) {
+        self.flow = flow;
+        if let Some(basin) = &mut self.basin {
+            let settings = basin.flow.settings();
+            basin.flow.set_settings(queue, FlowSettings {
+                center: settings.center,
+                size: settings.size,
+                ..flow
+            });
+        }
+    }
+
+    /// Simulates `water` beside the ocean, drawn over its bed with the flow of its river, or
+    /// takes the basin away with `None`.
+    pub fn set_basin(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, water: Option<&ShallowWater>) {
+        self.basin = water.map(|water| Basin::new(
+            device,
+            queue,
+            water,
+            self.flow,
+            &self.basin_bind_group_layout,
+            &self.flow_bind_group_layout,
+        ));
+    }
+
+    /// Simulates the particles of `fluid` and draws them as a surface, or takes the fluid away
+    /// with `None`.
+    pub fn set_fluid(&mut self, device: &wgpu::Device, fluid: Option<&Sph>) {
+        self.fluid = fluid.map(|fluid| GpuSph::new(device, fluid));
+    }
+
+    /// Puts the fluid's particles back to `particles`, which must be as many as it has.
+    pub fn reset_fluid(&mut self, queue: &wgpu::Queue, particles: &[SphParticle]) {
+        if let Some(fluid) = &mut self.fluid {
+            fluid.reset(queue, particles);
+        }
+    }
+}
+
+// A shallow-water simulation with the flow map of its river, and the grid its bed and water are
+// drawn with.
+struct Basin {
+    water: GpuShallowWater,
+    bind_group: wgpu::BindGroup,
+    flow: FlowMap,
+    flow_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl Basin {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        water: &ShallowWater,
+        flow: FlowSettings,
+        layout: &wgpu::BindGroupLayout,
+        flow_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let settings = *water.settings();
+        let water = GpuShallowWater::new(device, water);
+        let bind_group = water.create_bind_group(device, layout);
+        let flow = FlowMap::from_gpu_shallow_water(device, queue, &water, flow);
+        let flow_bind_group = flow.create_bind_group(device, flow_layout);
+        // One vertex per cell centre.
+        let (vertices, indices) = TextureVertex::grid(settings.size - settings.cell_size(), settings.resolution - 1);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Basin Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Basin Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            water,
+            bind_group,
+            flow,
+            flow_bind_group,
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    // The bed first, then the water over it.
+    fn draw(&self, pass: &mut wgpu::RenderPass, terrain_pipeline: &wgpu::RenderPipeline, water_pipeline: &wgpu::RenderPipeline) {
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.set_bind_group(2, &self.flow_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        pass.set_pipeline(terrain_pipeline);
+        pass.draw_indexed(0..self.index_count, 0, 0..1);
+        pass.set_pipeline(water_pipeline);
+        pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
+
+fn wake_emitters(buoyancy: &Buoyancy) -> Vec<WakeEmitter> {
+    buoyancy.bodies()
+        .iter()
+        .map(|floating| WakeEmitter {
+            position: floating.body.position.xz(),
+            velocity: floating.body.linear_velocity.xz(),
+            half_width: floating.half_extents.z,
+        })
+        .collect()
+}
+
+// Written every update, with room for one instance when there are no bodies, as a buffer bound
+// for drawing cannot be empty.
+fn create_body_instance_buffer(device: &wgpu::Device, buoyancy: &Buoyancy) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Body Instance Buffer"),
+        size: (buoyancy.bodies().len().max(1) * size_of::<ModelInstance>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn body_instances(buoyancy: &Buoyancy) -> Vec<ModelInstance> {
+    buoyancy.model_matrices()
+        .into_iter()
+        .map(|model| ModelInstance { model: model.into() })
+        .collect()
+}
+
+// Per-view resources of the water shader: the view projection plus targets of earlier passes.
+const VIEW_BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("View Bind Group Layout"),
+    entries: &[
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 5,
+            visibility: wgpu::ShaderStages::VERTEX,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+    ],
+};
+
+fn create_view_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    camera_buffer: &wgpu::Buffer,
+    projected_grid_buffer: &wgpu::Buffer,
+    reflection: &Reflection,
+    refraction: &Texture,
+    depth_texture: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("View Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&reflection.color.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&reflection.color.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&refraction.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&depth_texture.create_depth_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: projected_grid_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+// Resources of the post-processing pass: the finished scene, its depth, and where the water is.
+const POST_BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("Post Bind Group Layout"),
+    entries: &[
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            count: None,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        },
+    ],
+};
+
+fn create_post_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    scene: &Texture,
+    depth_texture: &Texture,
+    camera_buffer: &wgpu::Buffer,
+    underwater_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Post Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&scene.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&scene.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&depth_texture.create_depth_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: underwater_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+// The layouts the depth-tested pipelines are built with, kept to rebuild them.
+struct ScenePipelineLayouts {
+    water: wgpu::PipelineLayout,
+    camera: wgpu::PipelineLayout,
+    seabed: wgpu::PipelineLayout,
+    terrain: wgpu::PipelineLayout,
+    basin: wgpu::PipelineLayout,
+}
+
+// Everything drawn against the depth buffer, which has to be rebuilt when the projection turns
+// depth around.
+struct ScenePipelines {
+    water_depth: wgpu::RenderPipeline,
+    water: wgpu::RenderPipeline,
+    projected_water_depth: wgpu::RenderPipeline,
+    projected_water: wgpu::RenderPipeline,
+    quadtree_water_depth: wgpu::RenderPipeline,
+    quadtree_water: wgpu::RenderPipeline,
+    splash: wgpu::RenderPipeline,
+    seabed: wgpu::RenderPipeline,
+    reflected_seabed: wgpu::RenderPipeline,
+    body: wgpu::RenderPipeline,
+    reflected_body: wgpu::RenderPipeline,
+    hull_mask: wgpu::RenderPipeline,
+    terrain: wgpu::RenderPipeline,
+    basin: wgpu::RenderPipeline,
+    reflected_terrain: wgpu::RenderPipeline,
+    reflected_basin: wgpu::RenderPipeline,
+}
+
+impl ScenePipelines {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        layouts: &ScenePipelineLayouts,
+        projection: &Projection,
+    ) -> Self {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                [include_str!("camera.wgsl"), include_str!("water_shading.wgsl"), include_str!("water.wgsl")].concat().into(),
+            ),
+        });
+        // The water's depth alone, and the shading wherever the surface is outside the hulls, for
+        // each way of meshing it.
+        let create_water_pipelines = |vs_entry: &str, buffers: &[wgpu::VertexBufferLayout]| {
+            let depth_pipeline = create_masked_render_pipeline(
+                device,
+                &layouts.water,
+                &shader_module,
+                vs_entry,
+                "fs_depth",
+                buffers,
+                format,
+                None,
+                wgpu::ColorWrites::empty(),
+                wgpu::DepthStencilState {
+                    format: Texture::depth_format(device),
+                    depth_write_enabled: true,
+                    depth_compare: projection.depth_compare(),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                },
+            );
+            let pipeline = create_masked_render_pipeline(
+                device,
+                &layouts.water,
+                &shader_module,
+                vs_entry,
+                "fs_main",
+                buffers,
+                format,
+                // The surface is seen from below too.
+                None,
+                wgpu::ColorWrites::ALL,
+                wgpu::DepthStencilState {
+                    format: Texture::depth_format(device),
+                    depth_write_enabled: true,
+                    depth_compare: projection.depth_compare_equal(),
+                    stencil: OUTSIDE_HULLS_STENCIL,
+                    bias: wgpu::DepthBiasState::default(),
+                },
+            );
+            (depth_pipeline, pipeline)
+        };
+        let (water_depth, water) = create_water_pipelines("vs_main", &[TextureVertex::LAYOUT]);
+        let (projected_water_depth, projected_water) = create_water_pipelines("vs_projected", &[TextureVertex::LAYOUT]);
+        let (quadtree_water_depth, quadtree_water) =
+            create_water_pipelines("vs_patch", &[TextureVertex::LAYOUT, WaterPatch::LAYOUT]);
+
+        let splash_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Splash Shader"),
+            source: wgpu::ShaderSource::Wgsl([include_str!("camera.wgsl"), include_str!("splash.wgsl")].concat().into()),
+        });
+        let splash = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Splash Pipeline"),
+            layout: Some(&layouts.camera),
+            vertex: wgpu::VertexState {
+                module: &splash_shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[SplashParticle::LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &splash_shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // Alpha is left alone, since the post pass reads it as the water's underside mark.
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            // Tested against the water and the scene, but translucent, so it writes no depth.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::depth_format(device),
+                depth_write_enabled: false,
+                depth_compare: projection.depth_compare(),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let seabed_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Seabed Shader"),
+            source: wgpu::ShaderSource::Wgsl([include_str!("camera.wgsl"), include_str!("seabed.wgsl")].concat().into()),
+        });
+        let seabed = create_render_pipeline(
+            device,
+            &layouts.seabed,
+            &seabed_shader_module,
+            "vs_main",
+            "fs_main",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Back),
+            projection.depth_compare(),
+        );
+        let reflected_seabed = create_render_pipeline(
+            device,
+            &layouts.seabed,
+            &seabed_shader_module,
+            "vs_main",
+            "fs_main",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Front),
+            projection.depth_compare(),
+        );
+
+        let body_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Floating Body Shader"),
+            source: wgpu::ShaderSource::Wgsl([include_str!("camera.wgsl"), include_str!("floating.wgsl")].concat().into()),
+        });
+        let body = create_render_pipeline(
+            device,
+            &layouts.camera,
+            &body_shader_module,
+            "vs_main",
+            "fs_main",
+            &[ModelVertex::LAYOUT, ModelInstance::LAYOUT],
+            format,
+            Some(wgpu::Face::Back),
+            projection.depth_compare(),
+        );
+        let reflected_body = create_render_pipeline(
+            device,
+            &layouts.camera,
+            &body_shader_module,
+            "vs_main",
+            "fs_main",
+            &[ModelVertex::LAYOUT, ModelInstance::LAYOUT],
+            format,
+            Some(wgpu::Face::Front),
+            projection.depth_compare(),
+        );
+        let hull_mask = create_masked_render_pipeline(
+            device,
+            &layouts.camera,
+            &body_shader_module,
+            "vs_main",
+            "fs_main",
+            &[ModelVertex::LAYOUT, ModelInstance::LAYOUT],
+            format,
+            None,
+            wgpu::ColorWrites::empty(),
+            wgpu::DepthStencilState {
+                format: Texture::depth_format(device),
+                depth_write_enabled: false,
+                depth_compare: projection.depth_compare(),
+                stencil: HULL_MASK_STENCIL,
+                bias: wgpu::DepthBiasState::default(),
+            },
+        );
+
+        let basin_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shallow Water Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                [include_str!("camera.wgsl"), include_str!("shallow_water.wgsl"), include_str!("flow.wgsl")].concat().into(),
+            ),
+        });
+        let terrain = create_render_pipeline(
+            device,
+            &layouts.terrain,
+            &basin_shader_module,
+            "vs_terrain",
+            "fs_terrain",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Back),
+            projection.depth_compare(),
+        );
+        let basin = create_render_pipeline(
+            device,
+            &layouts.basin,
+            &basin_shader_module,
+            "vs_water",
+            "fs_water",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Back),
+            projection.depth_compare(),
+        );
+        // Mirroring flips the winding of every triangle, so the reflected pipelines cull front faces.
+        let reflected_terrain = create_render_pipeline(
+            device,
+            &layouts.terrain,
+            &basin_shader_module,
+            "vs_terrain",
+            "fs_terrain",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Front),
+            projection.depth_compare(),
+        );
+        let reflected_basin = create_render_pipeline(
+            device,
+            &layouts.basin,
+            &basin_shader_module,
+            "vs_water",
+            "fs_water",
+            &[TextureVertex::LAYOUT],
+            format,
+            Some(wgpu::Face::Front),
+            projection.depth_compare(),
+        );
+
+        Self {
+            water_depth,
+            water,
+            projected_water_depth,
+            projected_water,
+            quadtree_water_depth,
+            quadtree_water,
+            splash,
+            seabed,
+            reflected_seabed,
+            body,
+            reflected_body,
+            hull_mask,
+            terrain,
+            basin,
+            reflected_terrain,
+            reflected_basin,
+        }
+    }
+}
+
+// Every mesh shares the water's opaque, depth-tested setup.
+#[allow(clippy::too_many_arguments)]
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    vertex_entry_point: &str,
+    fragment_entry_point: &str,
+    buffers: &[wgpu::VertexBufferLayout],
+    format: wgpu::TextureFormat,
+    cull_mode: Option<wgpu::Face>,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    create_masked_render_pipeline(
+        device,
+        layout,
+        module,
+        vertex_entry_point,
+        fragment_entry_point,
+        buffers,
+        format,
+        cull_mode,
+        wgpu::ColorWrites::ALL,
+        wgpu::DepthStencilState {
+            format: Texture::depth_format(device),
+            depth_write_enabled: true,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        },
+    )
+}
+
+// The same, for the passes that lay down the hull mask and test the water against it.
+#[allow(clippy::too_many_arguments)]
+fn create_masked_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    vertex_entry_point: &str,
+    fragment_entry_point: &str,
+    buffers: &[wgpu::VertexBufferLayout],
+    format: wgpu::TextureFormat,
+    cull_mode: Option<wgpu::Face>,
+    write_mask: wgpu::ColorWrites,
+    depth_stencil: wgpu::DepthStencilState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some(vertex_entry_point),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        depth_stencil: Some(depth_stencil),
+        multiview: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,278 @@
+#![allow(dead_code)]
+
+use crate::{
+    carving::SubmergedVolume,
+    compute::create_compute_pipeline,
+    fixed_step::{FixedStep, MAX_STEPS_PER_UPDATE},
+    texture::Texture,
+};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+pub const MAX_IMPULSES: usize = 32;
+const WORKGROUP_SIZE: u32 = 8;
+const MAX_TIME_STEP: f32 = 1.0 / 120.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RippleBoundary {
+    Reflective,
+    Absorbing,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RippleSettings {
+    pub resolution: u32,
+    /// Side length in metres of the square simulated area, centred on the origin.
+    pub size: f32,
+    pub wave_speed: f32,
+    pub damping: f32,
+    pub boundary: RippleBoundary,
+}
+
+impl Default for RippleSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            size: 32.0,
+            wave_speed: 2.0,
+            damping: 0.3,
+            boundary: RippleBoundary::Absorbing,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct RippleUniform {
+    resolution: u32,
+    size: f32,
+    wave_speed: f32,
+    damping: f32,
+    time_step: f32,
+    boundary: u32,
+    impulse_count: u32,
+    _padding: u32,
+    impulses: [[f32; 4]; MAX_IMPULSES],
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Impulse {
+    position: na::Point2<f32>,
+    radius: f32,
+    strength: f32,
+}
+
+/// Interactive ripples on a height field, solving the 2D wave equation with ping-pong buffers.
+pub struct Ripples {
+    settings: RippleSettings,
+    uniform: RippleUniform,
+    uniform_buffer: wgpu::Buffer,
+    states: [wgpu::Buffer; 2],
+    pub output: Texture,
+    inject_pipeline: wgpu::ComputePipeline,
+    step_pipeline: wgpu::ComputePipeline,
+    displace_pipeline: wgpu::ComputePipeline,
+    inject_bind_groups: [wgpu::BindGroup; 2],
+    step_bind_groups: [wgpu::BindGroup; 2],
+    displace_bind_groups: Option<[wgpu::BindGroup; 2]>,
+    current: usize,
+    pending_steps: u32,
+    fixed_step: FixedStep,
+    impulses: Vec<Impulse>,
+}
+
+impl Ripples {
+    pub fn new(device: &wgpu::Device, settings: RippleSettings) -> Self {
+        let n = settings.resolution;
+        let cell = settings.size / n as f32;
+        // Keep the Courant number below 1 / sqrt(2), which the explicit 2D scheme needs to stay stable.
+        let time_step = MAX_TIME_STEP.min(0.5 * cell / settings.wave_speed);
+        let uniform = RippleUniform {
+            resolution: n,
+            size: settings.size,
+            wave_speed: settings.wave_speed,
+            damping: settings.damping,
+            time_step,
+            boundary: settings.boundary as u32,
+            impulse_count: 0,
+            _padding: 0,
+            impulses: [[0.0; 4]; MAX_IMPULSES],
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ripple Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let states = [0, 1].map(|i| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("Ripple State Buffer {i}")),
+            size: (n * n) as u64 * size_of::<[f32; 2]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        }));
+        let output = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Ripple Output"));
+
+        let inject_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ripple Inject",
+            &[include_str!("ripple_common.wgsl"), include_str!("ripple_inject.wgsl")],
+        );
+        let step_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ripple Step",
+            &[include_str!("ripple_common.wgsl"), include_str!("ripple_step.wgsl")],
+        );
+        let displace_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Ripple Displace",
+            &[include_str!("ripple_common.wgsl"), include_str!("ripple_displace.wgsl")],
+        );
+
+        let inject_bind_groups = [0, 1].map(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ripple Inject Bind Group"),
+            layout: &inject_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: states[i].as_entire_binding(),
+                },
+            ],
+        }));
+        let step_bind_groups = [0, 1].map(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ripple Step Bind Group"),
+            layout: &step_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: states[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: states[1 - i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&output.view),
+                },
+            ],
+        }));
+
+        Self {
+            settings,
+            uniform,
+            uniform_buffer,
+            states,
+            output,
+            inject_pipeline,
+            step_pipeline,
+            displace_pipeline,
+            inject_bind_groups,
+            step_bind_groups,
+            displace_bind_groups: None,
+            current: 0,
+            pending_steps: 0,
+            fixed_step: FixedStep::default(),
+            impulses: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &RippleSettings {
+        &self.settings
+    }
+
+    pub fn uniform_buffer(&self) -> &wgpu::Buffer {
+        &self.uniform_buffer
+    }
+
+    /// Queues an impulse that pushes the water down by `strength` metres around `position`,
+    /// given in world xz coordinates.
+    pub fn add_impulse(&mut self, position: na::Point2<f32>, radius: f32, strength: f32) {
+        self.impulses.push(Impulse {
+            position,
+            radius,
+            strength,
+        });
+    }
+
+    /// Lets the bodies in `volume`, which must cover the same cells as the ripples, push the
+    /// water aside on every dispatch.
+    pub fn set_displacement(&mut self, device: &wgpu::Device, volume: &SubmergedVolume) {
+        self.displace_bind_groups = Some([0, 1].map(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ripple Displace Bind Group"),
+            layout: &self.displace_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.states[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&volume.volume.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&volume.previous.view),
+                },
+            ],
+        })));
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: f32) {
+        let steps = self.fixed_step.advance(dt, self.uniform.time_step);
+        // Steps queued since the last dispatch are kept.
+        self.pending_steps = (self.pending_steps + steps).min(MAX_STEPS_PER_UPDATE);
+
+        // Impulses not yet dispatched are kept, and those that do not fit wait for the next update.
+        let start = self.uniform.impulse_count as usize;
+        let count = self.impulses.len().min(MAX_IMPULSES - start);
+        for (slot, impulse) in self.uniform.impulses[start..].iter_mut().zip(self.impulses.drain(..count)) {
+            *slot = [impulse.position.x, impulse.position.y, impulse.radius, impulse.strength];
+        }
+        self.uniform.impulse_count = (start + count) as u32;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    pub fn dispatch(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let groups = self.settings.resolution.div_ceil(WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Ripple Pass"),
+            timestamp_writes: None,
+        });
+        if self.uniform.impulse_count > 0 {
+            compute_pass.set_pipeline(&self.inject_pipeline);
+            compute_pass.set_bind_group(0, &self.inject_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+            self.uniform.impulse_count = 0;
+        }
+        if let Some(displace_bind_groups) = &self.displace_bind_groups {
+            compute_pass.set_pipeline(&self.displace_pipeline);
+            compute_pass.set_bind_group(0, &displace_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+        }
+
+        compute_pass.set_pipeline(&self.step_pipeline);
+        for _ in 0..self.pending_steps {
+            compute_pass.set_bind_group(0, &self.step_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+            self.current = 1 - self.current;
+        }
+        self.pending_steps = 0;
+    }
+}
+
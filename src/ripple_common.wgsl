@@ -0,0 +1,24 @@
+const MAX_IMPULSES: u32 = 32u;
+const BOUNDARY_REFLECTIVE: u32 = 0u;
+const SPONGE_WIDTH: f32 = 12.0;
+const SPONGE_DAMPING: f32 = 30.0;
+
+struct Ripple {
+    resolution: u32,
+    size: f32,
+    wave_speed: f32,
+    damping: f32,
+    time_step: f32,
+    boundary: u32,
+    impulse_count: u32,
+    _padding: u32,
+    // xy = world xz position, z = radius, w = strength.
+    impulses: array<vec4<f32>, MAX_IMPULSES>,
+}
+
+@group(0) @binding(0)
+var<uniform> ripple: Ripple;
+
+fn cell_position(id: vec2<u32>) -> vec2<f32> {
+    return ((vec2<f32>(id) + 0.5) / f32(ripple.resolution) - 0.5) * ripple.size;
+}
@@ -0,0 +1,18 @@
+@group(0) @binding(1)
+var<storage, read_write> state: array<vec2<f32>>;
+@group(0) @binding(2)
+var volume: texture_2d<f32>;
+@group(0) @binding(3)
+var previous_volume: texture_2d<f32>;
+
+// Water a body sinks into rises in its place, and the waves carry the bump out around the hull.
+// Both time levels move, like an impulse, so the rise starts at rest.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ripple.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let sunk = textureLoad(volume, id.xy, 0).x - textureLoad(previous_volume, id.xy, 0).x;
+    state[id.y * n + id.x] += vec2(sunk);
+}
@@ -0,0 +1,19 @@
+@group(0) @binding(1)
+var<storage, read_write> state: array<vec2<f32>>;
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ripple.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let p = cell_position(id.xy);
+    var offset = 0.0;
+    for (var i = 0u; i < min(ripple.impulse_count, MAX_IMPULSES); i++) {
+        let impulse = ripple.impulses[i];
+        let d = distance(p, impulse.xy) / impulse.z;
+        offset -= impulse.w * exp(-d * d);
+    }
+    // Displace both time levels so the impulse starts at rest instead of with a velocity kick.
+    state[id.y * n + id.x] += vec2(offset);
+}
@@ -0,0 +1,45 @@
+@group(0) @binding(1)
+var<storage, read> current: array<vec2<f32>>;
+@group(0) @binding(2)
+var<storage, read_write> next: array<vec2<f32>>;
+@group(0) @binding(3)
+var output: texture_storage_2d<rgba16float, write>;
+
+// Neighbours outside the grid mirror the edge cell, which reflects waves back in.
+fn height(x: i32, y: i32) -> f32 {
+    let n = i32(ripple.resolution);
+    let cell = clamp(vec2(x, y), vec2(0), vec2(n - 1));
+    return current[cell.y * n + cell.x].x;
+}
+
+// Absorbing boundaries damp the motion in a layer along the edges instead of reflecting waves.
+fn sponge(id: vec2<u32>) -> f32 {
+    if ripple.boundary == BOUNDARY_REFLECTIVE {
+        return 0.0;
+    }
+    let n = f32(ripple.resolution);
+    let edge = min(min(f32(id.x), f32(id.y)), min(n - 1.0 - f32(id.x), n - 1.0 - f32(id.y)));
+    let t = clamp(1.0 - edge / SPONGE_WIDTH, 0.0, 1.0);
+    return SPONGE_DAMPING * t * t;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = ripple.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let x = i32(id.x);
+    let y = i32(id.y);
+    let cell = ripple.size / f32(n);
+    let h = current[id.y * n + id.x];
+
+    let laplacian = height(x - 1, y) + height(x + 1, y) + height(x, y - 1) + height(x, y + 1) - 4.0 * h.x;
+    let courant = ripple.wave_speed * ripple.time_step / cell;
+    let damping = max(1.0 - (ripple.damping + sponge(id.xy)) * ripple.time_step, 0.0);
+    let velocity = (h.x - h.y) * damping;
+    next[id.y * n + id.x] = vec2(h.x + velocity + courant * courant * laplacian, h.x);
+
+    let gradient = vec2(height(x + 1, y) - height(x - 1, y), height(x, y + 1) - height(x, y - 1)) / (2.0 * cell);
+    textureStore(output, id.xy, vec4(h.x, gradient, 0.0));
+}
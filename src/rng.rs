@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use std::f64::consts::TAU;
+
+// PCG32, so spectra, rain and anything else seeded come out the same on every platform.
+pub struct Rng(u64);
+
+impl Rng {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+    const INCREMENT: u64 = 1_442_695_040_888_963_407;
+
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self(seed.wrapping_add(Self::INCREMENT));
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.0;
+        self.0 = state.wrapping_mul(Self::MULTIPLIER).wrapping_add(Self::INCREMENT);
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+
+    /// Uniform in (0, 1].
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u32() as f64 + 1.0) / (u32::MAX as f64 + 1.0)
+    }
+
+    pub fn gaussian(&mut self) -> (f32, f32) {
+        let r = (-2.0 * self.uniform().ln()).sqrt();
+        let angle = TAU * self.uniform();
+        ((r * angle.cos()) as f32, (r * angle.sin()) as f32)
+    }
+}
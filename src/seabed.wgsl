@@ -0,0 +1,68 @@
+const SUN_DIRECTION: vec3<f32> = vec3(0.4082483, 0.8164966, 0.4082483);
+const SAND_COLOR: vec3<f32> = vec3(0.55, 0.5, 0.38);
+const SEA_DEPTH: f32 = 8.0;
+// Height of the beach where it meets the shallow water basin at the +x edge.
+const BEACH_HEIGHT: f32 = 0.6;
+const HALF_SIZE: f32 = 16.0;
+const WATER_LEVEL: f32 = 0.0;
+
+struct Vertex {
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) height: f32,
+    @location(2) light_uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// World to caustic texture space.
+@group(1) @binding(0)
+var<uniform> shadow: mat4x4<f32>;
+@group(1) @binding(1)
+var caustic_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var caustic_sampler: sampler;
+
+// A shelf rising from deep water towards a beach along +x, with some dunes on it.
+fn height(p: vec2<f32>) -> f32 {
+    let shelf = smoothstep(-HALF_SIZE, HALF_SIZE, p.x);
+    let dunes = 0.3 * sin(0.7 * p.x + 0.3 * p.y) * cos(0.5 * p.y);
+    return mix(-SEA_DEPTH, BEACH_HEIGHT, shelf * shelf) + dunes;
+}
+
+@vertex
+fn vs_main(vertex: Vertex) -> Fragment {
+    let p = vertex.pos.xz;
+    let e = 0.1;
+    let position = vec4(p.x, height(p), p.y, 1.0);
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * position;
+    fragment.height = position.y;
+    fragment.light_uv = (shadow * position).xy;
+    fragment.normal = vec3(
+        height(p - vec2(e, 0.0)) - height(p + vec2(e, 0.0)),
+        2.0 * e,
+        height(p - vec2(0.0, e)) - height(p + vec2(0.0, e)),
+    );
+    return fragment;
+}
+
+// Caustics brighten and darken the sunlight below the water, fading in over the last few centimetres.
+fn caustics(fragment: Fragment) -> f32 {
+    let caustic = textureSample(caustic_texture, caustic_sampler, fragment.light_uv).r;
+    let inside = all(fragment.light_uv >= vec2(0.0)) && all(fragment.light_uv <= vec2(1.0));
+    let underwater = 1.0 - smoothstep(WATER_LEVEL - 0.2, WATER_LEVEL, fragment.height);
+    return mix(1.0, caustic, select(0.0, underwater, inside));
+}
+
+@fragment
+fn fs_main(fragment: Fragment) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(fragment.normal), SUN_DIRECTION), 0.0);
+    return vec4(SAND_COLOR * (0.3 + 0.7 * diffuse * caustics(fragment)), 1.0);
+}
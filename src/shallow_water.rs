@@ -0,0 +1,483 @@
+#![allow(dead_code)]
+
+use crate::{
+    compute::create_compute_pipeline,
+    fixed_step::{FixedStep, MAX_STEPS_PER_UPDATE},
+    texture::Texture,
+};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+pub const MAX_SOURCES: usize = 8;
+const WORKGROUP_SIZE: u32 = 8;
+const GRAVITY: f32 = 9.81;
+const MAX_TIME_STEP: f32 = 1.0 / 120.0;
+// Below this depth a cell counts as dry and reports no velocity.
+const DRY_DEPTH: f32 = 0.001;
+// Caps bed friction in the thinnest water, where it would otherwise stop the flow at once.
+const MIN_FRICTION_DEPTH: f32 = 0.01;
+const VISCOSITY: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShallowWaterSettings {
+    pub resolution: u32,
+    /// Side length in metres of the square simulated area.
+    pub size: f32,
+    /// World xz position of the centre of the simulated area.
+    pub center: na::Point2<f32>,
+    /// Deepest water expected. It bounds the wave speed, which sets the stable time step.
+    pub max_depth: f32,
+    /// Bed friction in m/s. It slows the flow in proportion to its speed, and more so in shallow water.
+    pub friction: f32,
+}
+
+impl Default for ShallowWaterSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 128,
+            size: 16.0,
+            center: na::Point2::origin(),
+            max_depth: 2.0,
+            friction: 0.2,
+        }
+    }
+}
+
+impl ShallowWaterSettings {
+    pub fn cell_size(&self) -> f32 {
+        self.size / self.resolution as f32
+    }
+
+    pub fn time_step(&self) -> f32 {
+        // Keep the Courant number of the fastest wave, sqrt(g * depth), below 1 / sqrt(2).
+        MAX_TIME_STEP.min(0.5 * self.cell_size() / (GRAVITY * self.max_depth).sqrt())
+    }
+
+    pub fn cell_position(&self, x: u32, z: u32) -> na::Point2<f32> {
+        let n = self.resolution as f32;
+        let offset = na::Vector2::new((x as f32 + 0.5) / n - 0.5, (z as f32 + 0.5) / n - 0.5) * self.size;
+        self.center + offset
+    }
+}
+
+/// Adds water over a disc, or drains it with a negative rate.
+#[derive(Clone, Copy, Debug)]
+pub struct WaterSource {
+    pub position: na::Point2<f32>,
+    pub radius: f32,
+    /// Volume in m^3 per second.
+    pub rate: f32,
+}
+
+impl WaterSource {
+    fn covers(&self, p: na::Point2<f32>) -> bool {
+        (p - self.position).norm_squared() <= self.radius * self.radius
+    }
+
+    // [x, z, radius, depth added to each covered cell per second], as the compute shader reads it.
+    fn pack(&self, settings: &ShallowWaterSettings) -> Option<[f32; 4]> {
+        let n = settings.resolution;
+        let cells = (0..n * n).filter(|i| self.covers(settings.cell_position(i % n, i / n))).count();
+        if cells == 0 {
+            log::warn!("Water source at {:?} covers no cells and is ignored", self.position);
+            return None;
+        }
+        let rate = self.rate / (cells as f32 * settings.cell_size().powi(2));
+        Some([self.position.x, self.position.y, self.radius, rate])
+    }
+}
+
+/// CPU reference of the shallow-water simulation, using the same virtual pipe scheme as
+/// `GpuShallowWater`. Water moves between neighbouring cells through pipes whose flow is
+/// accelerated by the difference in surface height, so every cell's loss is another's gain.
+pub struct ShallowWater {
+    settings: ShallowWaterSettings,
+    bed: Vec<f32>,
+    depth: Vec<f32>,
+    // Outflow in m^3 per second towards -x, +x, -z and +z.
+    flux: Vec<[f32; 4]>,
+    sources: Vec<[f32; 4]>,
+    fixed_step: FixedStep,
+}
+
+impl ShallowWater {
+    pub fn new(settings: ShallowWaterSettings, bed: Vec<f32>, depth: Vec<f32>) -> Self {
+        let cells = (settings.resolution * settings.resolution) as usize;
+        assert_eq!(bed.len(), cells, "Bed must have resolution^2 cells");
+        assert_eq!(depth.len(), cells, "Depth must have resolution^2 cells");
+        Self {
+            settings,
+            bed,
+            depth,
+            flux: vec![[0.0; 4]; cells],
+            sources: Vec::new(),
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    /// Builds the initial state from a function of the world xz position returning the bed
+    /// height and the water depth.
+    pub fn from_fn(settings: ShallowWaterSettings, terrain: impl Fn(na::Point2<f32>) -> (f32, f32)) -> Self {
+        let n = settings.resolution;
+        let (bed, depth) = (0..n * n).map(|i| terrain(settings.cell_position(i % n, i / n))).unzip();
+        Self::new(settings, bed, depth)
+    }
+
+    pub fn settings(&self) -> &ShallowWaterSettings {
+        &self.settings
+    }
+
+    pub fn bed(&self) -> &[f32] {
+        &self.bed
+    }
+
+    pub fn depth(&self) -> &[f32] {
+        &self.depth
+    }
+
+    pub fn add_source(&mut self, source: WaterSource) {
+        if self.sources.len() == MAX_SOURCES {
+            log::warn!("Only {MAX_SOURCES} water sources are supported");
+            return;
+        }
+        self.sources.extend(source.pack(&self.settings));
+    }
+
+    /// Total water volume in m^3.
+    pub fn volume(&self) -> f64 {
+        let cell_area = self.settings.cell_size() as f64 * self.settings.cell_size() as f64;
+        self.depth.iter().map(|&depth| depth as f64).sum::<f64>() * cell_area
+    }
+
+    pub fn velocity(&self, x: u32, z: u32) -> na::Vector2<f32> {
+        let i = self.index(x, z);
+        let (inflow, outflow) = (self.inflow(x, z), self.flux[i]);
+        let discharge = 0.5 * na::Vector2::new(
+            inflow[0] - outflow[0] + outflow[1] - inflow[1],
+            inflow[2] - outflow[2] + outflow[3] - inflow[3],
+        );
+        if self.depth[i] > DRY_DEPTH {
+            discharge / (self.settings.cell_size() * self.depth[i])
+        } else {
+            na::Vector2::zeros()
+        }
+    }
+
+    /// Advances by `dt` seconds in fixed steps, carrying the remainder over to the next update.
+    pub fn update(&mut self, dt: f32) {
+        for _ in 0..self.fixed_step.advance(dt, self.settings.time_step()) {
+            self.step();
+        }
+    }
+
+    pub fn step(&mut self) {
+        let n = self.settings.resolution;
+        let dt = self.settings.time_step();
+        let cell_area = self.settings.cell_size().powi(2);
+
+        let previous = self.flux.clone();
+        for z in 0..n {
+            for x in 0..n {
+                let i = self.index(x, z);
+                let (bed, depth) = (self.bed[i], self.depth[i]);
+                let surface = bed + depth;
+                let neighbours = self.neighbours(x, z);
+                // Blend each pipe with the parallel pipes before and after it, which damps
+                // oscillations on the scale of a cell without moving any water.
+                let mut outflow: [f32; 4] = std::array::from_fn(|direction| {
+                    let along = |k: Option<usize>| k.map_or(previous[i][direction], |k| previous[k][direction]);
+                    let behind = along(neighbours[direction ^ 1]);
+                    let ahead = along(neighbours[direction]);
+                    previous[i][direction] + VISCOSITY * (behind + ahead - 2.0 * previous[i][direction])
+                });
+                for (direction, neighbour) in neighbours.into_iter().enumerate() {
+                    outflow[direction] = match neighbour {
+                        Some(j) => {
+                            let neighbour_surface = self.bed[j] + self.depth[j];
+                            let face = (surface.max(neighbour_surface) - bed.max(self.bed[j])).max(0.0);
+                            let damping = (1.0 - dt * self.settings.friction / face.max(MIN_FRICTION_DEPTH)).max(0.0);
+                            (outflow[direction] * damping + dt * GRAVITY * face * (surface - neighbour_surface)).max(0.0)
+                        }
+                        None => 0.0,
+                    };
+                }
+                let total = outflow.iter().sum::<f32>() * dt;
+                // Never let a cell drain more water than it holds.
+                if total > depth * cell_area {
+                    let scale = depth * cell_area / total;
+                    outflow.iter_mut().for_each(|flux| *flux *= scale);
+                }
+                self.flux[i] = outflow;
+            }
+        }
+
+        for z in 0..n {
+            for x in 0..n {
+                let i = self.index(x, z);
+                let inflow = self.inflow(x, z);
+                let net = inflow.iter().sum::<f32>() - self.flux[i].iter().sum::<f32>();
+                let mut depth = self.depth[i] + dt * net / cell_area;
+                let p = self.settings.cell_position(x, z);
+                for source in &self.sources {
+                    let offset = p - na::Point2::new(source[0], source[1]);
+                    if offset.norm_squared() <= source[2] * source[2] {
+                        depth += dt * source[3];
+                    }
+                }
+                self.depth[i] = depth.max(0.0);
+            }
+        }
+    }
+
+    fn index(&self, x: u32, z: u32) -> usize {
+        (z * self.settings.resolution + x) as usize
+    }
+
+    // Neighbours towards -x, +x, -z and +z, or `None` at the closed edges.
+    fn neighbours(&self, x: u32, z: u32) -> [Option<usize>; 4] {
+        let n = self.settings.resolution;
+        [
+            (x > 0).then(|| self.index(x - 1, z)),
+            (x + 1 < n).then(|| self.index(x + 1, z)),
+            (z > 0).then(|| self.index(x, z - 1)),
+            (z + 1 < n).then(|| self.index(x, z + 1)),
+        ]
+    }
+
+    // Flow into the cell through each of its faces.
+    fn inflow(&self, x: u32, z: u32) -> [f32; 4] {
+        let neighbours = self.neighbours(x, z);
+        std::array::from_fn(|direction| {
+            neighbours[direction].map_or(0.0, |j| self.flux[j][direction ^ 1])
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ShallowWaterUniform {
+    resolution: u32,
+    size: f32,
+    time_step: f32,
+    friction: f32,
+    center: [f32; 2],
+    source_count: u32,
+    _padding: u32,
+    sources: [[f32; 4]; MAX_SOURCES],
+}
+
+/// The shallow-water simulation in compute shaders, writing the surface height, depth and
+/// velocity of each cell to `output`.
+pub struct GpuShallowWater {
+    settings: ShallowWaterSettings,
+    uniform: ShallowWaterUniform,
+    uniform_buffer: wgpu::Buffer,
+    terrain: wgpu::Buffer,
+    fluxes: [wgpu::Buffer; 2],
+    pub output: Texture,
+    flux_pipeline: wgpu::ComputePipeline,
+    depth_pipeline: wgpu::ComputePipeline,
+    flux_bind_groups: [wgpu::BindGroup; 2],
+    depth_bind_groups: [wgpu::BindGroup; 2],
+    current: usize,
+    pending_steps: u32,
+    fixed_step: FixedStep,
+}
+
+impl GpuShallowWater {
+    /// Uploads the current state and sources of `water`.
+    pub fn new(device: &wgpu::Device, water: &ShallowWater) -> Self {
+        let settings = water.settings;
+        let n = settings.resolution;
+        let mut sources = [[0.0; 4]; MAX_SOURCES];
+        sources[..water.sources.len()].copy_from_slice(&water.sources);
+        let uniform = ShallowWaterUniform {
+            resolution: n,
+            size: settings.size,
+            time_step: settings.time_step(),
+            friction: settings.friction,
+            center: settings.center.into(),
+            source_count: water.sources.len() as u32,
+            _padding: 0,
+            sources,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shallow Water Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let cells = water.bed.iter().zip(&water.depth).map(|(&bed, &depth)| [bed, depth]).collect::<Vec<_>>();
+        let terrain = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shallow Water Terrain Buffer"),
+            contents: bytemuck::cast_slice(&cells),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let fluxes = [0, 1].map(|i| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("Shallow Water Flux Buffer {i}")),
+            contents: bytemuck::cast_slice(&water.flux),
+            usage: wgpu::BufferUsages::STORAGE,
+        }));
+        let output = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Shallow Water Output"));
+
+        let flux_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Shallow Water Flux",
+            &[include_str!("shallow_water_common.wgsl"), include_str!("shallow_water_flux.wgsl")],
+        );
+        let depth_pipeline = create_compute_pipeline(
+            device,
+            None,
+            "Shallow Water Depth",
+            &[include_str!("shallow_water_common.wgsl"), include_str!("shallow_water_depth.wgsl")],
+        );
+
+        let flux_bind_groups = [0, 1].map(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shallow Water Flux Bind Group"),
+            layout: &flux_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: terrain.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: fluxes[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: fluxes[1 - i].as_entire_binding(),
+                },
+            ],
+        }));
+        // Reads the fluxes the matching flux bind group has just written.
+        let depth_bind_groups = [0, 1].map(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shallow Water Depth Bind Group"),
+            layout: &depth_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: terrain.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: fluxes[1 - i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&output.view),
+                },
+            ],
+        }));
+
+        Self {
+            settings,
+            uniform,
+            uniform_buffer,
+            terrain,
+            fluxes,
+            output,
+            flux_pipeline,
+            depth_pipeline,
+            flux_bind_groups,
+            depth_bind_groups,
+            current: 0,
+            pending_steps: 0,
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &ShallowWaterSettings {
+        &self.settings
+    }
+
+    pub fn add_source(&mut self, source: WaterSource) {
+        let count = self.uniform.source_count as usize;
+        if count == MAX_SOURCES {
+            log::warn!("Only {MAX_SOURCES} water sources are supported");
+            return;
+        }
+        if let Some(packed) = source.pack(&self.settings) {
+            self.uniform.sources[count] = packed;
+            self.uniform.source_count += 1;
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: f32) {
+        let steps = self.fixed_step.advance(dt, self.uniform.time_step);
+        // Steps queued since the last dispatch are kept.
+        self.pending_steps = (self.pending_steps + steps).min(MAX_STEPS_PER_UPDATE);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    pub fn dispatch(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let groups = self.settings.resolution.div_ceil(WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Shallow Water Pass"),
+            timestamp_writes: None,
+        });
+        for _ in 0..self.pending_steps {
+            compute_pass.set_pipeline(&self.flux_pipeline);
+            compute_pass.set_bind_group(0, &self.flux_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+            compute_pass.set_pipeline(&self.depth_pipeline);
+            compute_pass.set_bind_group(0, &self.depth_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+            self.current = 1 - self.current;
+        }
+        self.pending_steps = 0;
+    }
+
+    pub fn create_bind_group(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shallow Water Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.output.view),
+                },
+            ],
+        })
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Shallow Water Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+        ],
+    };
+}
@@ -0,0 +1,117 @@
+const SUN_DIRECTION: vec3<f32> = vec3(0.4082483, 0.8164966, 0.4082483);
+const DEEP_COLOR: vec3<f32> = vec3(0.0, 0.12, 0.2);
+const SHALLOW_COLOR: vec3<f32> = vec3(0.1, 0.45, 0.5);
+const FOAM_COLOR: vec3<f32> = vec3(0.8, 0.85, 0.85);
+const BED_COLOR: vec3<f32> = vec3(0.45, 0.4, 0.3);
+// How much the flowing detail tilts the simulated surface normal.
+const DETAIL_STRENGTH: f32 = 0.5;
+// Water thinner than this is not drawn, and its surface is sunk below the bed.
+const DRY_DEPTH: f32 = 0.005;
+
+struct ShallowWater {
+    resolution: u32,
+    size: f32,
+    time_step: f32,
+    friction: f32,
+    center: vec2<f32>,
+}
+
+struct Vertex {
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) depth: f32,
+    @location(2) speed: f32,
+    @location(3) world: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> water: ShallowWater;
+// x = surface height, y = depth, zw = velocity.
+@group(1) @binding(1)
+var water_texture: texture_2d<f32>;
+
+fn load(cell: vec2<i32>) -> vec4<f32> {
+    let n = i32(water.resolution);
+    return textureLoad(water_texture, clamp(cell, vec2(0), vec2(n - 1)), 0);
+}
+
+// The grid has one vertex per cell, so texture coordinates map straight to cells.
+fn vertex_cell(tex_coords: vec2<f32>) -> vec2<i32> {
+    return vec2<i32>(round(tex_coords * f32(water.resolution - 1u)));
+}
+
+fn bed(cell: vec2<i32>) -> f32 {
+    let sample = load(cell);
+    return sample.x - sample.y;
+}
+
+fn normal_from(left: f32, right: f32, down: f32, up: f32) -> vec3<f32> {
+    let cell_size = water.size / f32(water.resolution);
+    return normalize(vec3(left - right, 2.0 * cell_size, down - up));
+}
+
+fn shade(albedo: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    return albedo * (0.3 + 0.7 * max(dot(normalize(normal), SUN_DIRECTION), 0.0));
+}
+
+@vertex
+fn vs_terrain(vertex: Vertex) -> Fragment {
+    let cell = vertex_cell(vertex.tex_coords);
+    let position = vec3(water.center.x + vertex.pos.x, bed(cell), water.center.y + vertex.pos.z);
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * vec4(position, 1.0);
+    fragment.normal = normal_from(
+        bed(cell - vec2(1, 0)),
+        bed(cell + vec2(1, 0)),
+        bed(cell - vec2(0, 1)),
+        bed(cell + vec2(0, 1)),
+    );
+    return fragment;
+}
+
+@fragment
+fn fs_terrain(fragment: Fragment) -> @location(0) vec4<f32> {
+    return vec4(shade(BED_COLOR, fragment.normal), 1.0);
+}
+
+@vertex
+fn vs_water(vertex: Vertex) -> Fragment {
+    let cell = vertex_cell(vertex.tex_coords);
+    let sample = load(cell);
+    // Dry cells sink below the bed, so the terrain hides their edges along the shoreline.
+    let height = select(sample.x, sample.x - 2.0 * DRY_DEPTH, sample.y < DRY_DEPTH);
+    let position = vec3(water.center.x + vertex.pos.x, height, water.center.y + vertex.pos.z);
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * vec4(position, 1.0);
+    fragment.normal = normal_from(
+        load(cell - vec2(1, 0)).x,
+        load(cell + vec2(1, 0)).x,
+        load(cell - vec2(0, 1)).x,
+        load(cell + vec2(0, 1)).x,
+    );
+    fragment.depth = sample.y;
+    fragment.speed = length(sample.zw);
+    fragment.world = position.xz;
+    return fragment;
+}
+
+@fragment
+fn fs_water(fragment: Fragment) -> @location(0) vec4<f32> {
+    let detail = flow_detail(fragment.world);
+    if fragment.depth < DRY_DEPTH {
+        discard;
+    }
+    let albedo = mix(SHALLOW_COLOR, DEEP_COLOR, clamp(fragment.depth * 0.5, 0.0, 1.0));
+    // Fast water breaks up into foam, which the flow drags along in streaks.
+    let foam = clamp(fragment.speed * 0.15 - 0.2, 0.0, 0.6) * (0.5 + detail.w);
+    let normal = normalize(fragment.normal) + DETAIL_STRENGTH * vec3(detail.x, 0.0, detail.z);
+    return vec4(shade(mix(albedo, FOAM_COLOR, min(foam, 0.8)), normal), 1.0);
+}
@@ -0,0 +1,39 @@
+const MAX_SOURCES: u32 = 8u;
+const GRAVITY: f32 = 9.81;
+const DRY_DEPTH: f32 = 0.001;
+const MIN_FRICTION_DEPTH: f32 = 0.01;
+const VISCOSITY: f32 = 0.1;
+
+struct ShallowWater {
+    resolution: u32,
+    size: f32,
+    time_step: f32,
+    friction: f32,
+    center: vec2<f32>,
+    source_count: u32,
+    _padding: u32,
+    // xy = world xz position, z = radius, w = depth added to each covered cell per second.
+    sources: array<vec4<f32>, MAX_SOURCES>,
+}
+
+@group(0) @binding(0)
+var<uniform> water: ShallowWater;
+
+fn cell_position(id: vec2<u32>) -> vec2<f32> {
+    return water.center + ((vec2<f32>(id) + 0.5) / f32(water.resolution) - 0.5) * water.size;
+}
+
+fn cell_index(cell: vec2<i32>) -> u32 {
+    return u32(cell.y) * water.resolution + u32(cell.x);
+}
+
+// Neighbour towards -x, +x, -z or +z, matching the order of the flux components.
+fn neighbour(cell: vec2<i32>, direction: u32) -> vec2<i32> {
+    var offsets = array(vec2(-1, 0), vec2(1, 0), vec2(0, -1), vec2(0, 1));
+    return cell + offsets[direction];
+}
+
+// Edges are closed walls, so there is no flow to or from cells outside the grid.
+fn inside(cell: vec2<i32>) -> bool {
+    return all(cell >= vec2(0)) && all(cell < vec2(i32(water.resolution)));
+}
@@ -0,0 +1,47 @@
+@group(0) @binding(1)
+var<storage, read_write> terrain: array<vec2<f32>>;
+@group(0) @binding(2)
+var<storage, read> flux: array<vec4<f32>>;
+// x = surface height, y = depth, zw = velocity.
+@group(0) @binding(3)
+var output: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = water.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let cell = vec2<i32>(id.xy);
+    let i = cell_index(cell);
+    let cell_size = water.size / f32(n);
+    let dt = water.time_step;
+
+    let outflow = flux[i];
+    var inflow = vec4(0.0);
+    for (var direction = 0u; direction < 4u; direction++) {
+        let other = neighbour(cell, direction);
+        if inside(other) {
+            inflow[direction] = flux[cell_index(other)][direction ^ 1u];
+        }
+    }
+    var depth = terrain[i].y + dt * (dot(inflow, vec4(1.0)) - dot(outflow, vec4(1.0))) / (cell_size * cell_size);
+    let p = cell_position(id.xy);
+    for (var s = 0u; s < min(water.source_count, MAX_SOURCES); s++) {
+        let source = water.sources[s];
+        let offset = p - source.xy;
+        if dot(offset, offset) <= source.z * source.z {
+            depth += dt * source.w;
+        }
+    }
+    depth = max(depth, 0.0);
+    terrain[i].y = depth;
+
+    // Flow through the cell averaged over its two opposite faces.
+    let discharge = 0.5 * vec2(
+        inflow.x - outflow.x + outflow.y - inflow.y,
+        inflow.z - outflow.z + outflow.w - inflow.w,
+    );
+    let velocity = select(vec2(0.0), discharge / (cell_size * depth), depth > DRY_DEPTH);
+    textureStore(output, id.xy, vec4(terrain[i].x + depth, depth, velocity));
+}
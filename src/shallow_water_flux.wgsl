@@ -0,0 +1,54 @@
+// x = bed height, y = water depth.
+@group(0) @binding(1)
+var<storage, read> terrain: array<vec2<f32>>;
+// Outflow in m^3 per second towards -x, +x, -z and +z.
+@group(0) @binding(2)
+var<storage, read> previous: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read_write> flux: array<vec4<f32>>;
+
+// Pipe of the cell at `cell` in `direction`, or of the cell at `fallback` beyond the edges.
+fn parallel(cell: vec2<i32>, fallback: vec2<i32>, direction: u32) -> f32 {
+    return previous[cell_index(select(fallback, cell, inside(cell)))][direction];
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = water.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let cell = vec2<i32>(id.xy);
+    let i = cell_index(cell);
+    let here = terrain[i];
+    let surface = here.x + here.y;
+    let dt = water.time_step;
+    let cell_area = pow(water.size / f32(n), 2.0);
+
+    var outflow = vec4(0.0);
+    for (var direction = 0u; direction < 4u; direction++) {
+        let other = neighbour(cell, direction);
+        if !inside(other) {
+            continue;
+        }
+        // Blend each pipe with the parallel pipes before and after it, which damps
+        // oscillations on the scale of a cell without moving any water.
+        let behind = parallel(neighbour(cell, direction ^ 1u), cell, direction);
+        let ahead = parallel(other, cell, direction);
+        let current = previous[i][direction];
+        let smoothed = current + VISCOSITY * (behind + ahead - 2.0 * current);
+
+        let there = terrain[cell_index(other)];
+        let neighbour_surface = there.x + there.y;
+        // Water above the higher of the two beds, which is what can flow through the pipe.
+        let face = max(max(surface, neighbour_surface) - max(here.x, there.x), 0.0);
+        let damping = max(1.0 - dt * water.friction / max(face, MIN_FRICTION_DEPTH), 0.0);
+        outflow[direction] = max(smoothed * damping + dt * GRAVITY * face * (surface - neighbour_surface), 0.0);
+    }
+    let total = dot(outflow, vec4(1.0)) * dt;
+    // Never let a cell drain more water than it holds.
+    if total > here.y * cell_area {
+        outflow *= here.y * cell_area / total;
+    }
+    flux[i] = outflow;
+}
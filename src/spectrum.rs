@@ -0,0 +1,167 @@
+#![allow(dead_code)]
+
+use crate::rng::Rng;
+
+use std::f64::consts::{PI, TAU};
+
+const GRAVITY: f64 = 9.81;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WaveSpectrum {
+    Phillips { amplitude: f32 },
+    PiersonMoskowitz,
+    Jonswap { gamma: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpectrumSettings {
+    pub spectrum: WaveSpectrum,
+    pub wind_speed: f32,
+    pub wind_direction: f32,
+    /// Distance in metres over which the wind has been blowing. Only used by JONSWAP.
+    pub fetch: f32,
+    /// Exponent of the cos-2s spreading function. Larger values narrow waves around the wind.
+    pub spreading: f32,
+    /// Extra alignment of long waves with the wind, from 0 to 1.
+    pub swell: f32,
+    pub seed: u64,
+}
+
+impl Default for SpectrumSettings {
+    fn default() -> Self {
+        Self {
+            spectrum: WaveSpectrum::Jonswap { gamma: 3.3 },
+            wind_speed: 8.0,
+            wind_direction: 0.45,
+            fetch: 20_000.0,
+            spreading: 4.0,
+            swell: 0.3,
+            seed: 0,
+        }
+    }
+}
+
+impl SpectrumSettings {
+    pub fn peak_frequency(&self) -> f32 {
+        let u = self.wind_speed as f64;
+        let omega = match self.spectrum {
+            // The radial Phillips spectrum peaks at k = sqrt(2 / 3) / L with L = U^2 / g.
+            WaveSpectrum::Phillips { .. } => (2.0f64 / 3.0).sqrt().sqrt() * GRAVITY / u,
+            WaveSpectrum::PiersonMoskowitz => 0.855 * GRAVITY / u,
+            WaveSpectrum::Jonswap { .. } => 22.0 * (GRAVITY * GRAVITY / (u * self.fetch as f64)).cbrt(),
+        };
+        omega as f32
+    }
+
+    /// Non-directional frequency spectrum S(omega) in m^2 s.
+    pub fn frequency_spectrum(&self, omega: f32) -> f32 {
+        let omega = omega as f64;
+        if omega <= 0.0 {
+            return 0.0;
+        }
+        let u = self.wind_speed as f64;
+        let peak = self.peak_frequency() as f64;
+        let spectrum = match self.spectrum {
+            WaveSpectrum::Phillips { amplitude } => {
+                let k = omega * omega / GRAVITY;
+                let l = u * u / GRAVITY;
+                let damping = 0.001 * l;
+                // Angular integral of the directional Phillips spectrum, converted from k to omega.
+                PI * amplitude as f64 * (-1.0 / (k * k * l * l)).exp() / (k * k * k)
+                    * (-k * k * damping * damping).exp()
+                    * 2.0 * omega / GRAVITY
+            }
+            WaveSpectrum::PiersonMoskowitz => {
+                let alpha = 8.1e-3;
+                alpha * GRAVITY * GRAVITY / omega.powi(5) * (-1.25 * (peak / omega).powi(4)).exp()
+            }
+            WaveSpectrum::Jonswap { gamma } => {
+                let chi = GRAVITY * self.fetch as f64 / (u * u);
+                let alpha = 0.076 * chi.powf(-0.22);
+                let sigma = if omega <= peak { 0.07 } else { 0.09 };
+                let r = (-(omega - peak).powi(2) / (2.0 * sigma * sigma * peak * peak)).exp();
+                alpha * GRAVITY * GRAVITY / omega.powi(5)
+                    * (-1.25 * (peak / omega).powi(4)).exp()
+                    * (gamma as f64).powf(r)
+            }
+        };
+        spectrum as f32
+    }
+
+    /// Directional spreading D(omega, theta), normalised to integrate to one over the circle.
+    /// `theta` is measured from the wind direction.
+    pub fn directional_spreading(&self, omega: f32, theta: f32) -> f32 {
+        let theta = theta as f64;
+        let spreading = match self.spectrum {
+            WaveSpectrum::Phillips { .. } => theta.cos().powi(2) / PI,
+            WaveSpectrum::PiersonMoskowitz | WaveSpectrum::Jonswap { .. } => {
+                let peak = self.peak_frequency() as f64;
+                let swell = self.swell as f64;
+                let s = self.spreading as f64 + 16.0 * (peak / omega as f64).tanh() * swell * swell;
+                let normalization = (ln_gamma(s + 1.0) - ln_gamma(s + 0.5)).exp() / (2.0 * PI.sqrt());
+                normalization * (theta * 0.5).cos().abs().powf(2.0 * s)
+            }
+        };
+        spreading as f32
+    }
+
+    /// Directional wavenumber spectrum E(k), such that the height variance is its integral over k.
+    pub fn density(&self, k: [f32; 2]) -> f32 {
+        let k_length = (k[0] * k[0] + k[1] * k[1]).sqrt();
+        if k_length < 1e-6 {
+            return 0.0;
+        }
+        let omega = (GRAVITY as f32 * k_length).sqrt();
+        let d_omega_dk = GRAVITY as f32 / (2.0 * omega);
+        let theta = k[1].atan2(k[0]) - self.wind_direction;
+        self.frequency_spectrum(omega) * self.directional_spreading(omega, theta) * d_omega_dk / k_length
+    }
+
+    /// Generates the initial spectrum for a `resolution` by `resolution` grid covering `patch_size`
+    /// metres. Each texel holds h0(k) in `xy` and conj(h0(-k)) in `zw`, with k = 0 at the centre.
+    pub fn generate(&self, resolution: u32, patch_size: f32) -> Vec<[f32; 4]> {
+        let n = resolution as usize;
+        let dk = TAU as f32 / patch_size;
+        let mut rng = Rng::new(self.seed);
+
+        let mut h0 = Vec::with_capacity(n * n);
+        for y in 0..n {
+            for x in 0..n {
+                let k = [
+                    (x as f32 - (n / 2) as f32) * dk,
+                    (y as f32 - (n / 2) as f32) * dk,
+                ];
+                let (xi_r, xi_i) = rng.gaussian();
+                // E|h0|^2 = E(k) dk^2 / 2, since h(k, t) gets energy from both k and -k.
+                let scale = (self.density(k) * dk * dk * 0.5).sqrt() * std::f32::consts::FRAC_1_SQRT_2;
+                h0.push([xi_r * scale, xi_i * scale]);
+            }
+        }
+
+        (0..n * n).map(|i| {
+            let (x, y) = (i % n, i / n);
+            let mirrored = h0[(n - y) % n * n + (n - x) % n];
+            [h0[i][0], h0[i][1], mirrored[0], -mirrored[1]]
+        }).collect()
+    }
+}
+
+// Lanczos approximation, accurate to about 15 significant digits for positive arguments.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..].iter().enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * TAU.ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
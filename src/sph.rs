@@ -0,0 +1,480 @@
+#![allow(dead_code)]
+
+use crate::{
+    compute::create_compute_pipeline,
+    fixed_step::{FixedStep, MAX_STEPS_PER_UPDATE},
+    vertex::Vertex,
+};
+
+use std::{collections::HashMap, f32::consts::PI};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_TIME_STEP: f32 = 1.0 / 120.0;
+// Particles a hash bucket can hold. Matches CELL_CAPACITY in sph_common.wgsl.
+const CELL_CAPACITY: u32 = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SphSettings {
+    /// Kernel radius, which is also the size of the hash grid cells.
+    pub smoothing_radius: f32,
+    /// Density in kg/m^3 the pressure pushes the fluid back towards.
+    pub rest_density: f32,
+    /// Pressure in Pa per kg/m^3 above the rest density. Stiffer fluid compresses less, but
+    /// needs shorter time steps, as sound travels through it at the square root of this.
+    pub stiffness: f32,
+    /// Dynamic viscosity in Pa s.
+    pub viscosity: f32,
+    pub gravity: f32,
+    /// Corners of the box the particles are kept in.
+    pub bounds_min: na::Point3<f32>,
+    pub bounds_max: na::Point3<f32>,
+    /// Fraction of the speed into a wall that is kept when bouncing off it.
+    pub restitution: f32,
+}
+
+impl Default for SphSettings {
+    fn default() -> Self {
+        Self {
+            smoothing_radius: 0.1,
+            rest_density: 1000.0,
+            stiffness: 100.0,
+            viscosity: 2.0,
+            gravity: 9.81,
+            bounds_min: na::Point3::new(-0.5, 0.0, -0.5),
+            bounds_max: na::Point3::new(0.5, 1.0, 0.5),
+            restitution: 0.3,
+        }
+    }
+}
+
+impl SphSettings {
+    /// Particles start half a smoothing radius apart, each carrying the mass of the cube of
+    /// fluid around it.
+    pub fn particle_spacing(&self) -> f32 {
+        0.5 * self.smoothing_radius
+    }
+
+    pub fn particle_mass(&self) -> f32 {
+        self.rest_density * self.particle_spacing().powi(3)
+    }
+
+    pub fn time_step(&self) -> f32 {
+        // Sound must not cross more than part of a kernel radius per step.
+        MAX_TIME_STEP.min(0.4 * self.smoothing_radius / self.stiffness.sqrt())
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SphParticle {
+    pub position: [f32; 3],
+    pub density: f32,
+    pub velocity: [f32; 3],
+    pub pressure: f32,
+}
+
+impl Vertex for SphParticle {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: size_of::<[f32; 4]>() as u64,
+                shader_location: 1,
+            },
+        ],
+    };
+}
+
+// Müller et al. 2003: poly6 for density, the spiky gradient for pressure and the viscosity
+// Laplacian, all zero beyond the smoothing radius h. sph_common.wgsl has the same kernels.
+pub(crate) fn poly6(r2: f32, h: f32) -> f32 {
+    315.0 / (64.0 * PI * h.powi(9)) * (h * h - r2).powi(3)
+}
+
+fn spiky_gradient(r: f32, h: f32) -> f32 {
+    -45.0 / (PI * h.powi(6)) * (h - r).powi(2)
+}
+
+fn viscosity_laplacian(r: f32, h: f32) -> f32 {
+    45.0 / (PI * h.powi(6)) * (h - r)
+}
+
+/// CPU reference of the particle fluid, with the same kernels and steps as `GpuSph`.
+/// Particles are looked up through a hash of the grid cell they are in, so only the 27 cells
+/// around a particle need searching for neighbours.
+pub struct Sph {
+    settings: SphSettings,
+    particles: Vec<SphParticle>,
+    fixed_step: FixedStep,
+}
+
+impl Sph {
+    pub fn new(settings: SphSettings, particles: Vec<SphParticle>) -> Self {
+        Self {
+            settings,
+            particles,
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    /// Fills the box from `min` to `max` with particles at rest on a regular lattice.
+    pub fn block(settings: SphSettings, min: na::Point3<f32>, max: na::Point3<f32>) -> Self {
+        let spacing = settings.particle_spacing();
+        let counts = ((max - min) / spacing).map(|extent| extent.floor().max(1.0) as u32);
+        let mut particles = Vec::with_capacity((counts.x * counts.y * counts.z) as usize);
+        for y in 0..counts.y {
+            for z in 0..counts.z {
+                for x in 0..counts.x {
+                    let offset = na::Vector3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) * spacing;
+                    particles.push(SphParticle {
+                        position: (min + offset).into(),
+                        density: settings.rest_density,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        Self::new(settings, particles)
+    }
+
+    pub fn settings(&self) -> &SphSettings {
+        &self.settings
+    }
+
+    pub fn particles(&self) -> &[SphParticle] {
+        &self.particles
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        let mass = self.settings.particle_mass();
+        self.particles.iter()
+            .map(|particle| 0.5 * mass * na::Vector3::from(particle.velocity).norm_squared())
+            .sum()
+    }
+
+    /// Advances by `dt` seconds in fixed steps, carrying the remainder over to the next update.
+    pub fn update(&mut self, dt: f32) {
+        for _ in 0..self.fixed_step.advance(dt, self.settings.time_step()) {
+            self.step();
+        }
+    }
+
+    pub fn step(&mut self) {
+        let settings = self.settings;
+        let h = settings.smoothing_radius;
+        let mass = settings.particle_mass();
+        let dt = settings.time_step();
+        let grid = self.grid();
+
+        for i in 0..self.particles.len() {
+            let density: f32 = self.neighbours(&grid, i).map(|(_, d)| mass * poly6(d.norm_squared(), h)).sum();
+            let particle = &mut self.particles[i];
+            particle.density = density;
+            // Only pushing, as pulling makes particles clump together at the free surface.
+            particle.pressure = (settings.stiffness * (density - settings.rest_density)).max(0.0);
+        }
+
+        let accelerations: Vec<_> = (0..self.particles.len())
+            .map(|i| {
+                let particle = &self.particles[i];
+                let velocity = na::Vector3::from(particle.velocity);
+                let mut force = na::Vector3::zeros();
+                for (j, d) in self.neighbours(&grid, i) {
+                    let r = d.norm();
+                    if j == i || r < 1e-6 {
+                        continue;
+                    }
+                    let other = &self.particles[j];
+                    let pressure = (particle.pressure + other.pressure) / (2.0 * other.density);
+                    force -= mass * pressure * spiky_gradient(r, h) * d / r;
+                    let relative = na::Vector3::from(other.velocity) - velocity;
+                    force += settings.viscosity * mass * relative / other.density * viscosity_laplacian(r, h);
+                }
+                force / particle.density - na::Vector3::y() * settings.gravity
+            })
+            .collect();
+
+        for (particle, acceleration) in self.particles.iter_mut().zip(accelerations) {
+            let mut velocity = na::Vector3::from(particle.velocity) + acceleration * dt;
+            let mut position = na::Point3::from(particle.position) + velocity * dt;
+            for axis in 0..3 {
+                if position[axis] < settings.bounds_min[axis] {
+                    position[axis] = settings.bounds_min[axis];
+                    velocity[axis] = velocity[axis].abs() * settings.restitution;
+                } else if position[axis] > settings.bounds_max[axis] {
+                    position[axis] = settings.bounds_max[axis];
+                    velocity[axis] = -velocity[axis].abs() * settings.restitution;
+                }
+            }
+            particle.position = position.into();
+            particle.velocity = velocity.into();
+        }
+    }
+
+    fn cell(&self, position: [f32; 3]) -> [i32; 3] {
+        position.map(|x| (x / self.settings.smoothing_radius).floor() as i32)
+    }
+
+    fn grid(&self) -> HashMap<[i32; 3], Vec<usize>> {
+        let mut grid: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, particle) in self.particles.iter().enumerate() {
+            grid.entry(self.cell(particle.position)).or_default().push(i);
+        }
+        grid
+    }
+
+    // Particles within the smoothing radius of particle `i`, including itself, with the
+    // offsets from them to it.
+    fn neighbours<'a>(
+        &'a self,
+        grid: &'a HashMap<[i32; 3], Vec<usize>>,
+        i: usize,
+    ) -> impl Iterator<Item = (usize, na::Vector3<f32>)> + 'a {
+        let position = na::Point3::from(self.particles[i].position);
+        let [x, y, z] = self.cell(self.particles[i].position);
+        let h2 = self.settings.smoothing_radius.powi(2);
+        (0..27)
+            .filter_map(move |k| grid.get(&[x + k % 3 - 1, y + k / 3 % 3 - 1, z + k / 9 - 1]))
+            .flatten()
+            .filter_map(move |&j| {
+                let d = position - na::Point3::from(self.particles[j].position);
+                (d.norm_squared() < h2).then_some((j, d))
+            })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SphUniform {
+    bounds_min: [f32; 3],
+    smoothing_radius: f32,
+    bounds_max: [f32; 3],
+    particle_mass: f32,
+    rest_density: f32,
+    stiffness: f32,
+    viscosity: f32,
+    gravity: f32,
+    restitution: f32,
+    time_step: f32,
+    particle_count: u32,
+    table_size: u32,
+}
+
+/// The particle fluid in compute shaders. Every step hashes the particles into a table of
+/// fixed-size buckets, then works out densities, then forces, then moves the particles.
+/// `particles` doubles as an instance buffer for drawing them.
+pub struct GpuSph {
+    settings: SphSettings,
+    uniform: SphUniform,
+    uniform_buffer: wgpu::Buffer,
+    pub particles: wgpu::Buffer,
+    clear_pipeline: wgpu::ComputePipeline,
+    insert_pipeline: wgpu::ComputePipeline,
+    density_pipeline: wgpu::ComputePipeline,
+    force_pipeline: wgpu::ComputePipeline,
+    integrate_pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    pending_steps: u32,
+    fixed_step: FixedStep,
+}
+
+impl GpuSph {
+    /// Uploads the current particles of `sph`.
+    pub fn new(device: &wgpu::Device, sph: &Sph) -> Self {
+        let settings = sph.settings;
+        let particle_count = sph.particles.len() as u32;
+        // Twice as many buckets as particles keeps collisions between cells rare.
+        let table_size = (2 * particle_count).next_power_of_two();
+        let uniform = SphUniform {
+            bounds_min: settings.bounds_min.into(),
+            smoothing_radius: settings.smoothing_radius,
+            bounds_max: settings.bounds_max.into(),
+            particle_mass: settings.particle_mass(),
+            rest_density: settings.rest_density,
+            stiffness: settings.stiffness,
+            viscosity: settings.viscosity,
+            gravity: settings.gravity,
+            restitution: settings.restitution,
+            time_step: settings.time_step(),
+            particle_count,
+            table_size,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SPH Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let particles = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SPH Particle Buffer"),
+            contents: bytemuck::cast_slice(&sph.particles),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let accelerations = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SPH Acceleration Buffer"),
+            size: particle_count.max(1) as u64 * size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cell_counts = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SPH Cell Count Buffer"),
+            size: table_size as u64 * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cell_particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SPH Cell Particle Buffer"),
+            size: (table_size * CELL_CAPACITY) as u64 * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // All passes share one layout, so the pipelines get an explicit one.
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("SPH Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    count: None,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                },
+                storage_layout_entry(1),
+                storage_layout_entry(2),
+                storage_layout_entry(3),
+                storage_layout_entry(4),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SPH Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, source| {
+            create_compute_pipeline(device, Some(&pipeline_layout), label, &[include_str!("sph_common.wgsl"), source])
+        };
+        let clear_pipeline = create_pipeline("SPH Clear", include_str!("sph_clear.wgsl"));
+        let insert_pipeline = create_pipeline("SPH Insert", include_str!("sph_insert.wgsl"));
+        let density_pipeline = create_pipeline("SPH Density", include_str!("sph_density.wgsl"));
+        let force_pipeline = create_pipeline("SPH Force", include_str!("sph_force.wgsl"));
+        let integrate_pipeline = create_pipeline("SPH Integrate", include_str!("sph_integrate.wgsl"));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("SPH Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particles.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: accelerations.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: cell_counts.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: cell_particles.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            uniform,
+            uniform_buffer,
+            particles,
+            clear_pipeline,
+            insert_pipeline,
+            density_pipeline,
+            force_pipeline,
+            integrate_pipeline,
+            bind_group,
+            pending_steps: 0,
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &SphSettings {
+        &self.settings
+    }
+
+    pub fn particle_count(&self) -> u32 {
+        self.uniform.particle_count
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let steps = self.fixed_step.advance(dt, self.uniform.time_step);
+        // Steps queued since the last dispatch are kept.
+        self.pending_steps = (self.pending_steps + steps).min(MAX_STEPS_PER_UPDATE);
+    }
+
+    /// Replaces the particles, which must be as many as the simulation started with.
+    pub fn reset(&mut self, queue: &wgpu::Queue, particles: &[SphParticle]) {
+        assert_eq!(particles.len(), self.uniform.particle_count as usize, "Particle count must not change");
+        queue.write_buffer(&self.particles, 0, bytemuck::cast_slice(particles));
+        self.pending_steps = 0;
+        self.fixed_step.reset();
+    }
+
+    /// Queues a single step for the next dispatch, whatever time has passed.
+    pub fn step(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    pub fn dispatch(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let particle_groups = self.uniform.particle_count.div_ceil(WORKGROUP_SIZE);
+        let table_groups = self.uniform.table_size.div_ceil(WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("SPH Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        for _ in 0..self.pending_steps {
+            compute_pass.set_pipeline(&self.clear_pipeline);
+            compute_pass.dispatch_workgroups(table_groups, 1, 1);
+            for pipeline in [&self.insert_pipeline, &self.density_pipeline, &self.force_pipeline, &self.integrate_pipeline] {
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.dispatch_workgroups(particle_groups, 1, 1);
+            }
+        }
+        self.pending_steps = 0;
+    }
+}
+
+fn storage_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        count: None,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+    }
+}
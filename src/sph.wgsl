@@ -0,0 +1,70 @@
+// Particles are splatted as spheres into the fluid depth and thickness targets, which
+// fluid_surface.wgsl smooths into a surface. Matches PARTICLE_RADIUS in fluid_surface.rs.
+const PARTICLE_RADIUS: f32 = 0.04;
+
+struct Particle {
+    @location(0) position_density: vec4<f32>,
+    @location(1) velocity_pressure: vec4<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) right: vec3<f32>,
+    @location(2) up: vec3<f32>,
+    @location(3) toward_eye: vec3<f32>,
+    @location(4) center: vec3<f32>,
+}
+
+struct FluidDepth {
+    @builtin(frag_depth) depth: f32,
+    @location(0) distance: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Two triangles per particle, facing the camera.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, particle: Particle) -> Fragment {
+    var corners = array(vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(-1.0, 1.0), vec2(-1.0, 1.0), vec2(1.0, -1.0), vec2(1.0, 1.0));
+    let corner = corners[index];
+    let position = particle.position_density.xyz;
+    let toward_eye = normalize(camera.eye - position);
+    let right = normalize(cross(vec3(0.0, 1.0, 0.0), toward_eye));
+    let up = cross(toward_eye, right);
+
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * vec4(position + (corner.x * right + corner.y * up) * PARTICLE_RADIUS, 1.0);
+    fragment.corner = corner;
+    fragment.right = right;
+    fragment.up = up;
+    fragment.toward_eye = toward_eye;
+    fragment.center = position;
+    return fragment;
+}
+
+// Offset from the centre, along the unit sphere each quad stands in for, of the point it shows.
+fn sphere_normal(fragment: Fragment) -> vec3<f32> {
+    let r2 = dot(fragment.corner, fragment.corner);
+    if r2 > 1.0 {
+        discard;
+    }
+    return fragment.corner.x * fragment.right + fragment.corner.y * fragment.up + sqrt(1.0 - r2) * fragment.toward_eye;
+}
+
+// View depth of the front of the sphere, nearest of all particles thanks to the depth test.
+// The float's bits are written, as 32-bit float targets are not renderable everywhere.
+@fragment
+fn fs_depth(fragment: Fragment) -> FluidDepth {
+    let position = vec4(fragment.center + sphere_normal(fragment) * PARTICLE_RADIUS, 1.0);
+    let clip = camera.view_proj * position;
+    let view = camera.view * position;
+    return FluidDepth(clip.z / clip.w, bitcast<u32>(-view.z));
+}
+
+// Length of the view ray inside the sphere, added up over all particles.
+@fragment
+fn fs_thickness(fragment: Fragment) -> @location(0) f32 {
+    return 2.0 * PARTICLE_RADIUS * dot(sphere_normal(fragment), fragment.toward_eye);
+}
@@ -0,0 +1,7 @@
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sph.table_size {
+        return;
+    }
+    atomicStore(&cell_counts[id.x], 0u);
+}
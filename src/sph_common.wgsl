@@ -0,0 +1,60 @@
+const PI: f32 = 3.14159265;
+// Particles a hash bucket can hold. Any more in the bucket are left out of the neighbour search.
+const CELL_CAPACITY: u32 = 32u;
+
+struct Sph {
+    bounds_min: vec3<f32>,
+    smoothing_radius: f32,
+    bounds_max: vec3<f32>,
+    particle_mass: f32,
+    rest_density: f32,
+    stiffness: f32,
+    viscosity: f32,
+    gravity: f32,
+    restitution: f32,
+    time_step: f32,
+    particle_count: u32,
+    table_size: u32,
+}
+
+struct Particle {
+    position: vec3<f32>,
+    density: f32,
+    velocity: vec3<f32>,
+    pressure: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> sph: Sph;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> accelerations: array<vec4<f32>>;
+@group(0) @binding(3)
+var<storage, read_write> cell_counts: array<atomic<u32>>;
+@group(0) @binding(4)
+var<storage, read_write> cell_particles: array<u32>;
+
+fn cell_of(position: vec3<f32>) -> vec3<i32> {
+    return vec3<i32>(floor(position / sph.smoothing_radius));
+}
+
+// Table sizes are powers of two.
+fn cell_hash(cell: vec3<i32>) -> u32 {
+    let c = bitcast<vec3<u32>>(cell);
+    return ((c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u)) & (sph.table_size - 1u);
+}
+
+// Müller et al. 2003, as in sph.rs.
+fn poly6(r2: f32, h: f32) -> f32 {
+    let d = h * h - r2;
+    return 315.0 / (64.0 * PI * pow(h, 9.0)) * d * d * d;
+}
+
+fn spiky_gradient(r: f32, h: f32) -> f32 {
+    return -45.0 / (PI * pow(h, 6.0)) * (h - r) * (h - r);
+}
+
+fn viscosity_laplacian(r: f32, h: f32) -> f32 {
+    return 45.0 / (PI * pow(h, 6.0)) * (h - r);
+}
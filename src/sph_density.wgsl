@@ -0,0 +1,31 @@
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sph.particle_count {
+        return;
+    }
+    let h = sph.smoothing_radius;
+    let position = particles[id.x].position;
+    let cell = cell_of(position);
+    var density = 0.0;
+    for (var k = 0; k < 27; k++) {
+        let neighbour_cell = cell + vec3(k % 3, k / 3 % 3, k / 9) - 1;
+        let bucket = cell_hash(neighbour_cell);
+        let count = min(atomicLoad(&cell_counts[bucket]), CELL_CAPACITY);
+        for (var slot = 0u; slot < count; slot++) {
+            let j = cell_particles[bucket * CELL_CAPACITY + slot];
+            let other = particles[j].position;
+            // Other cells may share the bucket.
+            if any(cell_of(other) != neighbour_cell) {
+                continue;
+            }
+            let d = position - other;
+            let r2 = dot(d, d);
+            if r2 < h * h {
+                density += sph.particle_mass * poly6(r2, h);
+            }
+        }
+    }
+    particles[id.x].density = density;
+    // Only pushing, as pulling makes particles clump together at the free surface.
+    particles[id.x].pressure = max(sph.stiffness * (density - sph.rest_density), 0.0);
+}
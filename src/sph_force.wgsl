@@ -0,0 +1,32 @@
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sph.particle_count {
+        return;
+    }
+    let h = sph.smoothing_radius;
+    let particle = particles[id.x];
+    let cell = cell_of(particle.position);
+    var force = vec3(0.0);
+    for (var k = 0; k < 27; k++) {
+        let neighbour_cell = cell + vec3(k % 3, k / 3 % 3, k / 9) - 1;
+        let bucket = cell_hash(neighbour_cell);
+        let count = min(atomicLoad(&cell_counts[bucket]), CELL_CAPACITY);
+        for (var slot = 0u; slot < count; slot++) {
+            let j = cell_particles[bucket * CELL_CAPACITY + slot];
+            let other = particles[j];
+            if j == id.x || any(cell_of(other.position) != neighbour_cell) {
+                continue;
+            }
+            let d = particle.position - other.position;
+            let r = length(d);
+            if r >= h || r < 1e-6 {
+                continue;
+            }
+            let pressure = (particle.pressure + other.pressure) / (2.0 * other.density);
+            force -= sph.particle_mass * pressure * spiky_gradient(r, h) * d / r;
+            let relative = other.velocity - particle.velocity;
+            force += sph.viscosity * sph.particle_mass * relative / other.density * viscosity_laplacian(r, h);
+        }
+    }
+    accelerations[id.x] = vec4(force / particle.density - vec3(0.0, sph.gravity, 0.0), 0.0);
+}
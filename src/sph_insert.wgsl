@@ -0,0 +1,11 @@
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sph.particle_count {
+        return;
+    }
+    let bucket = cell_hash(cell_of(particles[id.x].position));
+    let slot = atomicAdd(&cell_counts[bucket], 1u);
+    if slot < CELL_CAPACITY {
+        cell_particles[bucket * CELL_CAPACITY + slot] = id.x;
+    }
+}
@@ -0,0 +1,17 @@
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sph.particle_count {
+        return;
+    }
+    var particle = particles[id.x];
+    var velocity = particle.velocity + accelerations[id.x].xyz * sph.time_step;
+    var position = particle.position + velocity * sph.time_step;
+    // Walls clamp the particle back inside and bounce it off with part of its speed.
+    let below = position < sph.bounds_min;
+    let above = position > sph.bounds_max;
+    velocity = select(velocity, abs(velocity) * sph.restitution, below);
+    velocity = select(velocity, -abs(velocity) * sph.restitution, above);
+    particle.position = clamp(position, sph.bounds_min, sph.bounds_max);
+    particle.velocity = velocity;
+    particles[id.x] = particle;
+}
@@ -0,0 +1,157 @@
+#![allow(dead_code)]
+
+use crate::{compute::create_compute_pipeline, rng::Rng, vertex::Vertex, wave::GRAVITY};
+
+use std::f32::consts::TAU;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+pub const MAX_SPLASH_PARTICLES: u32 = 4096;
+const WORKGROUP_SIZE: u32 = 64;
+const PARTICLES_PER_SPLASH: u32 = 6;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SplashParticle {
+    pub position: [f32; 3],
+    /// Seconds since the particle was emitted. It is dead once this reaches `lifetime`.
+    pub age: f32,
+    pub velocity: [f32; 3],
+    pub lifetime: f32,
+}
+
+impl Vertex for SplashParticle {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: size_of::<[f32; 4]>() as u64,
+                shader_location: 1,
+            },
+        ],
+    };
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SplashUniform {
+    gravity: f32,
+    time_step: f32,
+    particle_count: u32,
+    _padding: u32,
+}
+
+/// Droplets thrown up where rain hits the water. New particles are written over the oldest
+/// ones in a ring, and a compute pass moves them all every frame.
+pub struct Splashes {
+    uniform_buffer: wgpu::Buffer,
+    pub particles: wgpu::Buffer,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    rng: Rng,
+    pending: Vec<SplashParticle>,
+    next: u32,
+}
+
+impl Splashes {
+    pub fn new(device: &wgpu::Device, seed: u64) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Splash Uniform Buffer"),
+            contents: bytemuck::bytes_of(&SplashUniform {
+                gravity: GRAVITY,
+                time_step: 0.0,
+                particle_count: MAX_SPLASH_PARTICLES,
+                _padding: 0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // Zeroed particles have no lifetime, so they start out dead.
+        let particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Splash Particle Buffer"),
+            size: MAX_SPLASH_PARTICLES as u64 * size_of::<SplashParticle>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pipeline = create_compute_pipeline(device, None, "Splash Step", &[include_str!("splash_step.wgsl")]);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Splash Step Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particles.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            uniform_buffer,
+            particles,
+            pipeline,
+            bind_group,
+            rng: Rng::new(seed),
+            pending: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Queues a splash at `position`, scaled by the `strength` of the drop.
+    pub fn emit(&mut self, position: na::Point3<f32>, strength: f32) {
+        for _ in 0..PARTICLES_PER_SPLASH {
+            if self.pending.len() == MAX_SPLASH_PARTICLES as usize {
+                return;
+            }
+            let angle = TAU * self.rng.uniform() as f32;
+            let spread = 0.3 + 0.5 * self.rng.uniform() as f32;
+            let rise = strength * (1.0 + self.rng.uniform() as f32);
+            self.pending.push(SplashParticle {
+                position: position.into(),
+                age: 0.0,
+                velocity: [spread * angle.cos(), rise, spread * angle.sin()],
+                lifetime: 0.3 + 0.3 * self.rng.uniform() as f32,
+            });
+        }
+    }
+
+    /// Uploads the splashes emitted since the last update and sets the step of the next dispatch.
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: f32) {
+        let mut pending = &self.pending[..];
+        while !pending.is_empty() {
+            let count = pending.len().min((MAX_SPLASH_PARTICLES - self.next) as usize);
+            let offset = self.next as u64 * size_of::<SplashParticle>() as u64;
+            queue.write_buffer(&self.particles, offset, bytemuck::cast_slice(&pending[..count]));
+            self.next = (self.next + count as u32) % MAX_SPLASH_PARTICLES;
+            pending = &pending[count..];
+        }
+        self.pending.clear();
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&SplashUniform {
+            gravity: GRAVITY,
+            time_step: dt,
+            particle_count: MAX_SPLASH_PARTICLES,
+            _padding: 0,
+        }));
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Splash Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(MAX_SPLASH_PARTICLES.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
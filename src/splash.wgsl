@@ -0,0 +1,52 @@
+const SPLASH_COLOR: vec3<f32> = vec3(0.75, 0.85, 0.9);
+const PARTICLE_SIZE: f32 = 0.03;
+// Particles are stretched along the distance they cover in this many seconds.
+const STREAK_TIME: f32 = 0.03;
+
+struct Particle {
+    @location(0) position_age: vec4<f32>,
+    @location(1) velocity_lifetime: vec4<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) fade: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Each particle is a quad drawn as a four-vertex strip, facing the camera and stretched
+// along its velocity.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, particle: Particle) -> Fragment {
+    let corner = vec2(f32(index & 1u), f32(index >> 1u)) * 2.0 - 1.0;
+    let position = particle.position_age.xyz;
+    let velocity = particle.velocity_lifetime.xyz;
+    let life = particle.position_age.w / particle.velocity_lifetime.w;
+    let alive = particle.position_age.w < particle.velocity_lifetime.w;
+
+    let view = normalize(camera.eye - position);
+    let streak = velocity * STREAK_TIME;
+    var side = cross(view, streak);
+    if dot(side, side) < 1e-8 {
+        side = cross(view, vec3(0.0, 1.0, 0.0));
+    }
+    side = normalize(side) * PARTICLE_SIZE;
+    let along = streak + normalize(cross(side, view)) * PARTICLE_SIZE;
+    let world = position + corner.x * side + corner.y * along;
+
+    var fragment: Fragment;
+    // Dead particles collapse to a point, which rasterizes nothing.
+    fragment.pos = select(vec4(0.0, 0.0, 0.0, 1.0), camera.view_proj * vec4(world, 1.0), alive);
+    fragment.corner = corner;
+    fragment.fade = 1.0 - life;
+    return fragment;
+}
+
+@fragment
+fn fs_main(fragment: Fragment) -> @location(0) vec4<f32> {
+    let falloff = max(1.0 - dot(fragment.corner, fragment.corner), 0.0);
+    return vec4(SPLASH_COLOR, falloff * fragment.fade * 0.8);
+}
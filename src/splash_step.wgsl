@@ -0,0 +1,33 @@
+struct Splash {
+    gravity: f32,
+    time_step: f32,
+    particle_count: u32,
+    _padding: u32,
+}
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> splash: Splash;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= splash.particle_count {
+        return;
+    }
+    var particle = particles[id.x];
+    if particle.age >= particle.lifetime {
+        return;
+    }
+    particle.velocity.y -= splash.gravity * splash.time_step;
+    particle.position += particle.velocity * splash.time_step;
+    particle.age += splash.time_step;
+    particles[id.x] = particle;
+}
@@ -0,0 +1,47 @@
+struct Volume {
+    size: f32,
+    water_level: f32,
+}
+
+struct Vertex {
+    @location(0) pos: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+}
+
+struct Instance {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) height: f32,
+    @location(1) @interpolate(flat) facing: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> volume: Volume;
+
+// Looks straight down, with texel rows running along +z like the ripple cells.
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> Fragment {
+    let model = mat4x4(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world = model * vec4(vertex.pos, 1.0);
+    // A rotation times a positive scale keeps which way the faces point.
+    let normal = model * vec4(vertex.normal, 0.0);
+    var fragment: Fragment;
+    fragment.pos = vec4(world.x, -world.z, 0.0, 0.5 * volume.size);
+    fragment.height = world.y;
+    fragment.facing = sign(normal.y);
+    return fragment;
+}
+
+// Faces looking up add their height below the water level and faces looking down take it away,
+// which leaves the submerged thickness of every closed mesh.
+@fragment
+fn fs_main(fragment: Fragment) -> @location(0) vec4<f32> {
+    return vec4(fragment.facing * (min(fragment.height, volume.water_level) - volume.water_level), 0.0, 0.0, 1.0);
+}
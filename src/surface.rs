@@ -0,0 +1,246 @@
+#![allow(dead_code)]
+
+use crate::{
+    ocean::OceanSettings,
+    spectrum::SpectrumSettings,
+    wave::{GerstnerWave, WaveUniform, GRAVITY},
+};
+
+use std::{
+    f32::consts::TAU,
+    ops::{Add, Mul},
+};
+
+use nalgebra as na;
+
+type Complex = na::Complex<f32>;
+
+const REST_POSITION_ITERATIONS: usize = 8;
+const RAY_MARCH_STEP: f32 = 0.25;
+const RAY_BISECTION_ITERATIONS: usize = 16;
+
+/// CPU evaluation of the same wave model the water shader renders: the Gerstner swell plus the
+/// FFT ocean, generated from the same seeded spectrum. The ripples and wakes the shader adds on
+/// top only exist on the GPU, so heights, normals and ray hits here leave them out.
+pub struct WaterSurface {
+    waves: WaveUniform,
+    settings: OceanSettings,
+    spectrum: Vec<[f32; 4]>,
+    displacement: Vec<na::Vector3<f32>>,
+    normal: Vec<na::Vector3<f32>>,
+}
+
+impl WaterSurface {
+    pub fn new(waves: &[GerstnerWave], settings: OceanSettings) -> Self {
+        let n = settings.resolution as usize;
+        let mut surface = Self {
+            waves: WaveUniform::new(waves),
+            spectrum: settings.spectrum.generate(settings.resolution, settings.patch_size),
+            settings,
+            displacement: vec![na::Vector3::zeros(); n * n],
+            normal: vec![na::Vector3::y(); n * n],
+        };
+        surface.update(0.0);
+        surface
+    }
+
+    pub fn waves(&self) -> &WaveUniform {
+        &self.waves
+    }
+
+    pub fn settings(&self) -> &OceanSettings {
+        &self.settings
+    }
+
+    pub fn time(&self) -> f32 {
+        self.waves.time
+    }
+
+    pub fn set_waves(&mut self, waves: &[GerstnerWave]) {
+        self.waves.set_waves(waves);
+    }
+
+    pub fn set_spectrum(&mut self, spectrum: SpectrumSettings) {
+        self.settings.spectrum = spectrum;
+        self.spectrum = spectrum.generate(self.settings.resolution, self.settings.patch_size);
+        self.update(self.time());
+    }
+
+    /// Advances the surface to `time`. All queries evaluate the surface at the last updated time.
+    pub fn update(&mut self, time: f32) {
+        self.waves.time = time;
+
+        let n = self.settings.resolution as usize;
+        let dk = TAU / self.settings.patch_size;
+        let mut horizontal = vec![Complex::default(); n * n];
+        let mut height = vec![Complex::default(); n * n];
+        let mut slope_z = vec![Complex::default(); n * n];
+        for (index, h0) in self.spectrum.iter().enumerate() {
+            let k = na::Vector2::new(
+                (index % n) as f32 - (n / 2) as f32,
+                (index / n) as f32 - (n / 2) as f32,
+            ) * dk;
+            let k_length = k.norm().max(0.0001);
+            let phase = Complex::from_polar(1.0, (GRAVITY * k_length).sqrt() * time);
+            let h = Complex::new(h0[0], h0[1]) * phase + Complex::new(h0[2], h0[3]) * phase.conj();
+
+            let i = Complex::i();
+            let dx = i * (k.x / k_length) * h;
+            let dz = i * (k.y / k_length) * h;
+            horizontal[index] = dx + i * dz;
+            height[index] = h + i * (i * k.x * h);
+            slope_z[index] = i * k.y * h;
+        }
+        inverse_fft_2d(&mut horizontal, n);
+        inverse_fft_2d(&mut height, n);
+        inverse_fft_2d(&mut slope_z, n);
+
+        let choppiness = self.settings.choppiness;
+        for index in 0..n * n {
+            // Undo the shift from storing k = 0 at the centre of the spectrum.
+            let sign = if (index % n + index / n).is_multiple_of(2) { 1.0 } else { -1.0 };
+            let (horizontal, height, slope_z) = (horizontal[index] * sign, height[index] * sign, slope_z[index] * sign);
+            self.displacement[index] = na::Vector3::new(choppiness * horizontal.re, height.re, choppiness * horizontal.im);
+            self.normal[index] = na::Vector3::new(-height.im, 1.0, -slope_z.re).normalize();
+        }
+    }
+
+    /// Offset of the surface point whose undisplaced rest position is (`x`, `z`).
+    pub fn displacement_at(&self, x: f32, z: f32) -> na::Vector3<f32> {
+        let p = na::Vector2::new(x, z);
+        let (position, _) = self.waves.gerstner(p, self.time());
+        position + self.sample(&self.displacement, p) - na::Vector3::new(x, 0.0, z)
+    }
+
+    /// Rest position of the surface point that ends up above world position (`x`, `z`).
+    pub fn rest_position(&self, x: f32, z: f32) -> na::Vector2<f32> {
+        let target = na::Vector2::new(x, z);
+        let mut p = target;
+        for _ in 0..REST_POSITION_ITERATIONS {
+            p = target - self.displacement_at(p.x, p.y).xz();
+        }
+        p
+    }
+
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let p = self.rest_position(x, z);
+        self.displacement_at(p.x, p.y).y
+    }
+
+    /// Bound on how far the surface rises above or sinks below its rest level at the last updated
+    /// time, without the ripples and wakes.
+    pub fn max_height(&self) -> f32 {
+        let swell: f32 = self.waves.waves().iter().map(|wave| wave.amplitude.abs()).sum();
+        let ocean = self.displacement.iter().map(|offset| offset.y.abs()).fold(0.0, f32::max);
+        swell + ocean
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> na::Vector3<f32> {
+        let p = self.rest_position(x, z);
+        let (_, swell) = self.waves.gerstner(p, self.time());
+        let detail = self.sample(&self.normal, p);
+        let slope = swell.xz() / swell.y + detail.xz() / detail.y.max(0.001);
+        na::Vector3::new(slope.x, 1.0, slope.y).normalize()
+    }
+
+    /// First point where a ray crosses the surface within `max_distance`, found by marching
+    /// along the ray and refining the crossing by bisection.
+    pub fn intersect_ray(
+        &self,
+        origin: na::Point3<f32>,
+        direction: na::Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<na::Point3<f32>> {
+        let above = |t: f32| {
+            let p = origin + direction * t;
+            p.y - self.height_at(p.x, p.z)
+        };
+        let start = above(0.0);
+        let mut previous = 0.0;
+        let mut t = RAY_MARCH_STEP;
+        while t <= max_distance {
+            if above(t).signum() != start.signum() {
+                let (mut low, mut high) = (previous, t);
+                for _ in 0..RAY_BISECTION_ITERATIONS {
+                    let middle = 0.5 * (low + high);
+                    if above(middle).signum() == start.signum() {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                return Some(origin + direction * high);
+            }
+            previous = t;
+            t += RAY_MARCH_STEP;
+        }
+        None
+    }
+
+    // Bilinear, repeating lookup matching the water shader's sampler.
+    fn sample<T>(&self, field: &[T], p: na::Vector2<f32>) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let n = self.settings.resolution as i64;
+        let texel = p * (n as f32 / self.settings.patch_size);
+        let (x, z) = (texel.x.floor(), texel.y.floor());
+        let (tx, tz) = (texel.x - x, texel.y - z);
+        let at = |dx: i64, dz: i64| {
+            let column = (x as i64 + dx).rem_euclid(n);
+            let row = (z as i64 + dz).rem_euclid(n);
+            field[(row * n + column) as usize]
+        };
+        let top = at(0, 0) * (1.0 - tx) + at(1, 0) * tx;
+        let bottom = at(0, 1) * (1.0 - tx) + at(1, 1) * tx;
+        top * (1.0 - tz) + bottom * tz
+    }
+}
+
+fn inverse_fft_2d(data: &mut [Complex], n: usize) {
+    for row in data.chunks_exact_mut(n) {
+        inverse_fft(row);
+    }
+    let mut column = vec![Complex::default(); n];
+    for x in 0..n {
+        for y in 0..n {
+            column[y] = data[y * n + x];
+        }
+        inverse_fft(&mut column);
+        for y in 0..n {
+            data[y * n + x] = column[y];
+        }
+    }
+}
+
+// Unnormalised radix-2 inverse FFT, matching the compute shader.
+fn inverse_fft(data: &mut [Complex]) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let half = length / 2;
+        for start in (0..n).step_by(length) {
+            for k in 0..half {
+                let w = Complex::from_polar(1.0, TAU * k as f32 / length as f32);
+                let a = data[start + k];
+                let b = data[start + k + half] * w;
+                data[start + k] = a + b;
+                data[start + k + half] = a - b;
+            }
+        }
+        length *= 2;
+    }
+}
@@ -0,0 +1,221 @@
+#![allow(dead_code)]
+
+use crate::{compute::create_compute_pipeline, ripple::Ripples, wake::Wakes};
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+const WORKGROUP_SIZE: u32 = 8;
+// The largest ripple height and the largest wake height.
+const MAX_HEIGHTS_SIZE: u64 = 2 * size_of::<f32>() as u64;
+
+/// The ripples and wakes the water shader adds on top of the ocean. Both are read through one
+/// group, as the water pipelines have none left for a group of each. Their largest heights are
+/// also found on the GPU and read back, a frame or two late, to bound how far they move the water,
+/// which `WaterSurface` cannot.
+pub struct SurfaceDetail {
+    max_heights: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    groups: [u32; 2],
+    readback_state: ReadbackState,
+    mapped: Arc<AtomicBool>,
+    max_height: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReadbackState {
+    Idle,
+    Copied,
+    Mapping,
+}
+
+impl SurfaceDetail {
+    pub fn new(device: &wgpu::Device, ripples: &Ripples, wakes: &Wakes) -> Self {
+        let max_heights = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Surface Detail Max Height Buffer"),
+            size: MAX_HEIGHTS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Surface Detail Readback Buffer"),
+            size: MAX_HEIGHTS_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let pipeline = create_compute_pipeline(device, None, "Surface Detail Max Height", &[include_str!("surface_detail.wgsl")]);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Surface Detail Max Height Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&ripples.output.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&wakes.output.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: max_heights.as_entire_binding(),
+                },
+            ],
+        });
+        // Enough workgroups to cover the larger of the two textures along each side.
+        let (ripple_size, wake_size) = (ripples.output.texture.size(), wakes.output.texture.size());
+        let groups = [
+            ripple_size.width.max(wake_size.width).div_ceil(WORKGROUP_SIZE),
+            ripple_size.height.max(wake_size.height).div_ceil(WORKGROUP_SIZE),
+        ];
+        Self {
+            max_heights,
+            readback,
+            pipeline,
+            bind_group,
+            groups,
+            readback_state: ReadbackState::Idle,
+            mapped: Arc::new(AtomicBool::new(false)),
+            max_height: 0.0,
+        }
+    }
+
+    /// Largest ripple and wake heights, summed, as of the last readback.
+    pub fn max_height(&self) -> f32 {
+        self.max_height
+    }
+
+    /// Finds the largest heights after the ripples and wakes were dispatched and copies them out,
+    /// unless the last copy is still being read.
+    pub fn reduce_heights(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.readback_state != ReadbackState::Idle {
+            return;
+        }
+        encoder.clear_buffer(&self.max_heights, 0, None);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Surface Detail Max Height Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.groups[0], self.groups[1], 1);
+        drop(compute_pass);
+        encoder.copy_buffer_to_buffer(&self.max_heights, 0, &self.readback, 0, MAX_HEIGHTS_SIZE);
+        self.readback_state = ReadbackState::Copied;
+    }
+
+    /// Starts reading the copy once the encoder it was recorded to has been submitted.
+    pub fn map_heights(&mut self) {
+        if self.readback_state != ReadbackState::Copied {
+            return;
+        }
+        let mapped = self.mapped.clone();
+        self.readback.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            mapped.store(result.is_ok(), Ordering::Release);
+        });
+        self.readback_state = ReadbackState::Mapping;
+    }
+
+    /// Takes the largest heights from the copy if the device has finished mapping it.
+    pub fn read_heights(&mut self) {
+        if self.readback_state != ReadbackState::Mapping || !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+        let data = self.readback.slice(..).get_mapped_range();
+        self.max_height = bytemuck::cast_slice::<u8, f32>(&data).iter().sum();
+        drop(data);
+        self.readback.unmap();
+        self.readback_state = ReadbackState::Idle;
+    }
+
+    /// The wakes are sampled with the ripples' sampler.
+    pub fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        ripples: &Ripples,
+        wakes: &Wakes,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Surface Detail Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: ripples.uniform_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&ripples.output.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&ripples.output.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wakes.uniform_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&wakes.output.view),
+                },
+            ],
+        })
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Surface Detail Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                count: None,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            },
+        ],
+    };
+}
@@ -0,0 +1,30 @@
+// Largest height of the ripples and of the wakes, each kept in its own slot. Non-negative floats
+// order the same as their bits do as integers, so the maxima can be taken with integer atomics.
+
+@group(0) @binding(0)
+var ripple_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var wake_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var<storage, read_write> max_heights: array<atomic<u32>, 2>;
+
+// Each workgroup settles on its own maxima before touching the ones in memory.
+var<workgroup> workgroup_max: array<atomic<u32>, 2>;
+
+fn height_bits(texture: texture_2d<f32>, texel: vec2<u32>) -> u32 {
+    if any(texel >= textureDimensions(texture)) {
+        return 0u;
+    }
+    return bitcast<u32>(abs(textureLoad(texture, texel, 0).x));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) index: u32) {
+    atomicMax(&workgroup_max[0], height_bits(ripple_texture, id.xy));
+    atomicMax(&workgroup_max[1], height_bits(wake_texture, id.xy));
+    workgroupBarrier();
+    if index == 0u {
+        atomicMax(&max_heights[0], atomicLoad(&workgroup_max[0]));
+        atomicMax(&max_heights[1], atomicLoad(&workgroup_max[1]));
+    }
+}
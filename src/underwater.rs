@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+use crate::{camera::Camera, surface::WaterSurface};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+
+/// Where the water is relative to the camera, for the post-processing pass. The surface is
+/// approximated by its tangent plane below the camera, which is enough to split the near plane
+/// into the parts above and below the water.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UnderwaterUniform {
+    /// World-space plane with its normal pointing out of the water.
+    pub waterline: [f32; 4],
+    /// Depth of the camera below the surface, negative above it.
+    pub depth: f32,
+    _padding: [f32; 3],
+}
+
+impl UnderwaterUniform {
+    /// A plane no point is below, for cameras away from the water.
+    pub const DRY: Self = Self {
+        waterline: [0.0, 0.0, 0.0, 1.0],
+        depth: f32::NEG_INFINITY,
+        _padding: [0.0; 3],
+    };
+
+    /// `half_size` is the extent of the water around the origin; beyond it nothing is underwater.
+    pub fn new(camera: &Camera, water: &WaterSurface, half_size: f32) -> Self {
+        let eye = camera.eye();
+        if eye.x.abs() > half_size || eye.z.abs() > half_size {
+            return Self::DRY;
+        }
+        let height = water.height_at(eye.x, eye.z);
+        let normal = water.normal_at(eye.x, eye.z);
+        let point = na::Vector3::new(eye.x, height, eye.z);
+        Self {
+            waterline: [normal.x, normal.y, normal.z, -normal.dot(&point)],
+            depth: height - eye.y,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn is_submerged(&self) -> bool {
+        self.depth > 0.0
+    }
+}
@@ -69,6 +69,37 @@ impl TextureVertex {
         0, 1, 2,
         0, 2, 3,
     ];
+
+    pub fn grid(size: f32, subdivisions: u32) -> (Vec<Self>, Vec<u32>) {
+        let row = subdivisions + 1;
+        let step = size / subdivisions as f32;
+        let half = size * 0.5;
+
+        let mut vertices = Vec::with_capacity((row * row) as usize);
+        for z in 0..row {
+            for x in 0..row {
+                let u = x as f32 / subdivisions as f32;
+                let v = z as f32 / subdivisions as f32;
+                vertices.push(Self {
+                    position: [x as f32 * step - half, 0.0, z as f32 * step - half],
+                    tex_coords: [u, v],
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity((subdivisions * subdivisions * 6) as usize);
+        for z in 0..subdivisions {
+            for x in 0..subdivisions {
+                let i = z * row + x;
+                indices.extend_from_slice(&[
+                    i, i + row, i + row + 1,
+                    i, i + row + 1, i + 1,
+                ]);
+            }
+        }
+
+        (vertices, indices)
+    }
 }
 
 #[repr(C)]
@@ -102,3 +133,74 @@ impl Vertex for ModelVertex {
         ],
     };
 }
+
+impl ModelVertex {
+    /// Cube from -1 to 1 with outward normals and each face mapped to the whole texture.
+    pub fn cube() -> (Vec<Self>, Vec<u32>) {
+        // Normal, then the axes the face's u and v run along.
+        let faces = [
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+        ];
+
+        let mut vertices = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for (normal, u, v) in faces {
+            let first = vertices.len() as u32;
+            for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let position = [0, 1, 2].map(|i| normal[i] + su * u[i] + sv * v[i]);
+                vertices.push(Self {
+                    position,
+                    uv: [(su + 1.0) * 0.5, (sv + 1.0) * 0.5],
+                    normal,
+                });
+            }
+            indices.extend_from_slice(&[
+                first, first + 2, first + 1,
+                first, first + 3, first + 2,
+            ]);
+        }
+
+        (vertices, indices)
+    }
+}
+
+/// Per-instance model matrix, read from the vertex locations after the mesh's own.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ModelInstance {
+    pub model: [[f32; 4]; 4],
+}
+
+impl Vertex for ModelInstance {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: size_of::<[f32; 4]>() as u64,
+                shader_location: 4,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: size_of::<[f32; 8]>() as u64,
+                shader_location: 5,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: size_of::<[f32; 12]>() as u64,
+                shader_location: 6,
+            },
+        ],
+    };
+}
@@ -0,0 +1,208 @@
+#![allow(dead_code)]
+
+use crate::{compute::create_compute_pipeline, texture::Texture};
+
+use std::collections::VecDeque;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+use wgpu::util::DeviceExt as _;
+
+pub const MAX_WAKE_SAMPLES: usize = 1024;
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct WakeSettings {
+    pub resolution: u32,
+    /// Side length in metres of the square the wakes are rendered over, centred on the origin.
+    pub size: f32,
+    /// Seconds a trail sample keeps spreading waves before it is dropped.
+    pub trail_duration: f32,
+    /// Seconds between trail samples. Much longer than a twentieth of a second and the wave
+    /// trains of neighbouring samples stop adding up to a wake.
+    pub sample_interval: f32,
+    /// Scales the wave height.
+    pub amplitude: f32,
+}
+
+impl Default for WakeSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            size: 32.0,
+            trail_duration: 6.0,
+            sample_interval: 0.05,
+            amplitude: 0.05,
+        }
+    }
+}
+
+/// Something moving over the water, as seen by the wake.
+#[derive(Clone, Copy, Debug)]
+pub struct WakeEmitter {
+    /// World xz position.
+    pub position: na::Point2<f32>,
+    /// World xz velocity.
+    pub velocity: na::Vector2<f32>,
+    /// Half the width of the hull, which sets the width of the foam trail.
+    pub half_width: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TrailSample {
+    emitter: WakeEmitter,
+    time: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct WakeUniform {
+    resolution: u32,
+    size: f32,
+    sample_count: u32,
+    amplitude: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuTrailSample {
+    position: [f32; 2],
+    age: f32,
+    /// Distance covered since the previous sample, which sets how much water it pushed aside.
+    strength: f32,
+    velocity: [f32; 2],
+    half_width: f32,
+    _padding: f32,
+}
+
+/// Kelvin wakes behind moving objects. Each object leaves a trail of samples, and every sample
+/// spreads deep-water waves from where the object was. Summed, the waves cancel out everywhere
+/// but in the Kelvin wedge behind the object. The output holds the wave height, its xz gradient
+/// and foam along the track, over the same kind of square as the ripples.
+pub struct Wakes {
+    settings: WakeSettings,
+    uniform: WakeUniform,
+    uniform_buffer: wgpu::Buffer,
+    sample_buffer: wgpu::Buffer,
+    pub output: Texture,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    trails: Vec<VecDeque<TrailSample>>,
+}
+
+impl Wakes {
+    pub fn new(device: &wgpu::Device, settings: WakeSettings) -> Self {
+        let n = settings.resolution;
+        let uniform = WakeUniform {
+            resolution: n,
+            size: settings.size,
+            sample_count: 0,
+            amplitude: settings.amplitude,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wake Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let sample_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Wake Sample Buffer"),
+            size: (MAX_WAKE_SAMPLES * size_of::<GpuTrailSample>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let output = Texture::create_storage_texture(device, n, n, wgpu::TextureFormat::Rgba16Float, Some("Wake Output"));
+        let pipeline = create_compute_pipeline(device, None, "Wake", &[include_str!("wake.wgsl")]);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Wake Bind Group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sample_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&output.view),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            uniform,
+            uniform_buffer,
+            sample_buffer,
+            output,
+            pipeline,
+            bind_group,
+            trails: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &WakeSettings {
+        &self.settings
+    }
+
+    pub fn uniform_buffer(&self) -> &wgpu::Buffer {
+        &self.uniform_buffer
+    }
+
+    /// Extends the trail of each emitter, given in the same order every frame, and uploads
+    /// the samples with their ages at `time`.
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32, emitters: &[WakeEmitter]) {
+        self.trails.resize_with(emitters.len(), VecDeque::new);
+        for (trail, emitter) in self.trails.iter_mut().zip(emitters) {
+            if trail.back().is_none_or(|last| time - last.time >= self.settings.sample_interval) {
+                trail.push_back(TrailSample {
+                    emitter: *emitter,
+                    time,
+                });
+            }
+            while trail.front().is_some_and(|first| time - first.time > self.settings.trail_duration) {
+                trail.pop_front();
+            }
+        }
+
+        // Newest first, so the oldest samples are the ones left out if there are too many.
+        let mut samples = Vec::with_capacity(MAX_WAKE_SAMPLES);
+        for age_index in 0.. {
+            let before = samples.len();
+            for trail in &self.trails {
+                if let Some(sample) = trail.iter().rev().nth(age_index) {
+                    let speed = sample.emitter.velocity.norm();
+                    samples.push(GpuTrailSample {
+                        position: sample.emitter.position.into(),
+                        age: time - sample.time,
+                        strength: speed * self.settings.sample_interval,
+                        velocity: sample.emitter.velocity.into(),
+                        half_width: sample.emitter.half_width,
+                        _padding: 0.0,
+                    });
+                }
+            }
+            if samples.len() == before || samples.len() >= MAX_WAKE_SAMPLES {
+                break;
+            }
+        }
+        samples.truncate(MAX_WAKE_SAMPLES);
+
+        self.uniform.sample_count = samples.len() as u32;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+        queue.write_buffer(&self.sample_buffer, 0, bytemuck::cast_slice(&samples));
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let groups = self.settings.resolution.div_ceil(WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Wake Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+    }
+}
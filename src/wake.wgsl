@@ -0,0 +1,84 @@
+const PI: f32 = 3.14159265;
+const GRAVITY: f32 = 9.81;
+// Keeps the waves from a sample finite right where it was taken.
+const CORE_RADIUS: f32 = 0.5;
+// The wave trains are only right once a few wavelengths have left the source.
+const MIN_PHASE: f32 = 1.0;
+const FULL_PHASE: f32 = 4.0;
+const FOAM_LIFETIME: f32 = 2.5;
+// How fast the foam trail widens, in metres per second.
+const FOAM_SPREAD: f32 = 0.3;
+// Speed in metres per second at which the foam trail is at full strength.
+const FOAM_SPEED: f32 = 2.0;
+// Heights in metres and foam below this are left out.
+const NEGLIGIBLE: f32 = 1e-4;
+// Where exp(-fade) drops below NEGLIGIBLE.
+const MAX_FOAM_FADE: f32 = 9.2;
+
+struct Wake {
+    resolution: u32,
+    size: f32,
+    sample_count: u32,
+    amplitude: f32,
+}
+
+struct TrailSample {
+    position: vec2<f32>,
+    age: f32,
+    strength: f32,
+    velocity: vec2<f32>,
+    half_width: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> wake: Wake;
+@group(0) @binding(1)
+var<storage, read> samples: array<TrailSample>;
+@group(0) @binding(2)
+var output: texture_storage_2d<rgba16float, write>;
+
+// Every sample is a point disturbance of deep water. After t seconds the waves at distance r
+// from it have wavenumber k = g t² / 4r² and phase k r, and their height falls off as t² / r³.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let n = wake.resolution;
+    if id.x >= n || id.y >= n {
+        return;
+    }
+    let cell = wake.size / f32(n);
+    let p = (vec2<f32>(id.xy) + 0.5) * cell - 0.5 * wake.size;
+    // Shorter waves than four texels would alias.
+    let max_wavenumber = 0.5 * PI / cell;
+
+    var height = 0.0;
+    var gradient = vec2(0.0);
+    var foam = 0.0;
+    for (var i = 0u; i < wake.sample_count; i++) {
+        let sample = samples[i];
+        let t = sample.age;
+        let d = p - sample.position;
+        let d2 = dot(d, d);
+        let r2 = d2 + CORE_RADIUS * CORE_RADIUS;
+        let k = GRAVITY * t * t / (4.0 * r2);
+        // Waves only reach the ring where the phase has passed MIN_PHASE but the wavenumber is
+        // still below the aliasing cutoff, and only matter while t² / r³ is large enough.
+        let reach = sample.strength * t * t * wake.amplitude;
+        if k * k * r2 > MIN_PHASE * MIN_PHASE && k < max_wavenumber && reach * reach > NEGLIGIBLE * NEGLIGIBLE * r2 * r2 * r2 {
+            let r = sqrt(r2);
+            let phase = k * r;
+            let cutoff = 1.0 - smoothstep(0.5 * max_wavenumber, max_wavenumber, k);
+            let envelope = sample.strength * t * t / (r2 * r) * cutoff * smoothstep(MIN_PHASE, FULL_PHASE, phase);
+            height += envelope * cos(phase);
+            // The phase changes much faster with distance than the envelope does.
+            gradient += envelope * sin(phase) * k * d / r;
+        }
+
+        let width = sample.half_width + FOAM_SPREAD * t;
+        let fade = t / FOAM_LIFETIME + d2 / (width * width);
+        if fade < MAX_FOAM_FADE {
+            let churn = clamp(length(sample.velocity) / FOAM_SPEED, 0.0, 1.0);
+            foam = max(foam, churn * exp(-fade));
+        }
+    }
+    textureStore(output, id.xy, vec4(height * wake.amplitude, gradient * wake.amplitude, foam));
+}
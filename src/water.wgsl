@@ -0,0 +1,302 @@
+const MAX_WAVES: u32 = 8u;
+const GRAVITY: f32 = 9.81;
+const TAU: f32 = 6.28318530718;
+
+// Screen-space offset of the refraction lookup per unit of horizontal normal, through a metre or
+// more of water.
+const REFRACTION_DISTORTION: f32 = 0.03;
+const FOAM_COLOR: vec3<f32> = vec3(0.9, 0.95, 0.95);
+// Water shallower than this along the view ray foams where it meets the shore or other geometry.
+const SHORE_FOAM_DEPTH: f32 = 0.6;
+// Quads along each side of a water patch, and the bits of the edges stitched to a coarser one.
+const PATCH_RESOLUTION: f32 = 32.0;
+const STITCH_NEG_X: u32 = 1u;
+const STITCH_POS_X: u32 = 2u;
+const STITCH_NEG_Z: u32 = 4u;
+const STITCH_POS_Z: u32 = 8u;
+
+struct GerstnerWave {
+    direction: vec2<f32>,
+    amplitude: f32,
+    wavelength: f32,
+    steepness: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+
+struct Waves {
+    waves: array<GerstnerWave, MAX_WAVES>,
+    count: u32,
+    time: f32,
+}
+
+struct Ocean {
+    resolution: u32,
+    patch_size: f32,
+    time: f32,
+    choppiness: f32,
+    time_step: f32,
+    foam_coverage: f32,
+    foam_decay: f32,
+    foam_threshold: f32,
+}
+
+struct Ripple {
+    resolution: u32,
+    size: f32,
+}
+
+struct Wake {
+    resolution: u32,
+    size: f32,
+}
+
+// Where the corners of the projected grid meet the water plane, as homogeneous points.
+struct ProjectedGrid {
+    corners: array<vec4<f32>, 4>,
+}
+
+struct Vertex {
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+// A square of the quadtree, drawn with the shared patch mesh. See WaterPatch.
+struct Patch {
+    @location(2) origin: vec2<f32>,
+    @location(3) size: f32,
+    @location(4) stitch: u32,
+}
+
+struct Fragment {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) grid_pos: vec2<f32>,
+}
+
+struct Surface {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var reflection_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var screen_sampler: sampler;
+// Copy of the opaque scene, and its depth, bound as an unfilterable float texture.
+@group(0) @binding(3)
+var refraction_texture: texture_2d<f32>;
+@group(0) @binding(4)
+var scene_depth: texture_2d<f32>;
+@group(0) @binding(5)
+var<uniform> projected_grid: ProjectedGrid;
+
+@group(1) @binding(0)
+var<uniform> waves: Waves;
+
+@group(2) @binding(0)
+var<uniform> ocean: Ocean;
+@group(2) @binding(1)
+var ocean_displacement: texture_2d<f32>;
+@group(2) @binding(2)
+var ocean_normal: texture_2d<f32>;
+@group(2) @binding(3)
+var ocean_sampler: sampler;
+@group(2) @binding(4)
+var ocean_foam: texture_2d<f32>;
+
+@group(3) @binding(0)
+var<uniform> ripple: Ripple;
+@group(3) @binding(1)
+var ripple_texture: texture_2d<f32>;
+@group(3) @binding(2)
+var ripple_sampler: sampler;
+@group(3) @binding(3)
+var<uniform> wake: Wake;
+@group(3) @binding(4)
+var wake_texture: texture_2d<f32>;
+
+fn gerstner(p: vec2<f32>, time: f32) -> Surface {
+    var position = vec3(p.x, 0.0, p.y);
+    var normal = vec3(0.0, 1.0, 0.0);
+    let count = min(waves.count, MAX_WAVES);
+    for (var i = 0u; i < count; i++) {
+        let wave = waves.waves[i];
+        let k = TAU / wave.wavelength;
+        let omega = sqrt(GRAVITY * k);
+        let theta = k * dot(wave.direction, p) - omega * time;
+        let s = sin(theta);
+        let c = cos(theta);
+        // Q * A, with Q = steepness / (k * A * count) so crests never loop over.
+        let qa = wave.steepness / (k * f32(count));
+        let wa = k * wave.amplitude;
+
+        position.x += qa * wave.direction.x * c;
+        position.z += qa * wave.direction.y * c;
+        position.y += wave.amplitude * s;
+
+        normal.x -= wave.direction.x * wa * c;
+        normal.z -= wave.direction.y * wa * c;
+        normal.y -= qa * k * s;
+    }
+    return Surface(position, normalize(normal));
+}
+
+// Ocean texel (x, y) holds the sample at (x, y) * patch_size / resolution, not at the texel centre.
+fn ocean_uv(p: vec2<f32>) -> vec2<f32> {
+    return p / ocean.patch_size + 0.5 / f32(ocean.resolution);
+}
+
+// Ripple height and gradient, or zero outside the simulated area.
+fn ripple_at(p: vec2<f32>) -> vec3<f32> {
+    let uv = p / ripple.size + 0.5;
+    if any(uv < vec2(0.0)) || any(uv > vec2(1.0)) {
+        return vec3(0.0);
+    }
+    let half_texel = 0.5 / f32(ripple.resolution);
+    return textureSampleLevel(ripple_texture, ripple_sampler, clamp(uv, vec2(half_texel), vec2(1.0 - half_texel)), 0.0).xyz;
+}
+
+// Wake height, gradient and foam, or zero outside the area the wakes are rendered over.
+fn wake_at(p: vec2<f32>) -> vec4<f32> {
+    let uv = p / wake.size + 0.5;
+    if any(uv < vec2(0.0)) || any(uv > vec2(1.0)) {
+        return vec4(0.0);
+    }
+    let half_texel = 0.5 / f32(wake.resolution);
+    return textureSampleLevel(wake_texture, ripple_sampler, clamp(uv, vec2(half_texel), vec2(1.0 - half_texel)), 0.0);
+}
+
+// Displaced surface point and swell normal for the grid point at rest position `p`.
+// `WaterSurface` mirrors the swell and ocean parts on the CPU, so keep the two in sync.
+fn displace(p: vec2<f32>) -> Surface {
+    let surface = gerstner(p, waves.time);
+    let offset = textureSampleLevel(ocean_displacement, ocean_sampler, ocean_uv(p), 0.0).xyz;
+    let height = ripple_at(p).x + wake_at(p).x;
+    return Surface(surface.position + offset + vec3(0.0, height, 0.0), surface.normal);
+}
+
+// Sums the slopes of the Gerstner swell and the FFT ocean rather than their normals.
+fn surface_normal(swell_normal: vec3<f32>, p: vec2<f32>) -> vec3<f32> {
+    let swell = normalize(swell_normal);
+    let detail = textureSample(ocean_normal, ocean_sampler, ocean_uv(p)).xyz;
+    let slope = swell.xz / swell.y + detail.xz / max(detail.y, 0.001) - ripple_at(p).yz - wake_at(p).yz;
+    return normalize(vec3(slope.x, 1.0, slope.y));
+}
+
+fn water_vertex(rest: vec2<f32>) -> Fragment {
+    let surface = displace(rest);
+    var fragment: Fragment;
+    fragment.pos = camera.view_proj * vec4(surface.position, 1.0);
+    fragment.world_pos = surface.position;
+    fragment.normal = surface.normal;
+    fragment.grid_pos = rest;
+    return fragment;
+}
+
+@vertex
+fn vs_main(vertex: Vertex) -> Fragment {
+    return water_vertex(vertex.pos.xz);
+}
+
+// Edges stitched to a coarser neighbour move their odd vertices onto the even ones before them, so
+// both sides of the seam have the same vertices and no T-junctions open between them.
+@vertex
+fn vs_patch(vertex: Vertex, instance: Patch) -> Fragment {
+    var cell = round(vertex.tex_coords * PATCH_RESOLUTION);
+    let odd = cell % 2.0 == vec2(1.0);
+    let x_edge = (cell.x == 0.0 && (instance.stitch & STITCH_NEG_X) != 0u)
+        || (cell.x == PATCH_RESOLUTION && (instance.stitch & STITCH_POS_X) != 0u);
+    let z_edge = (cell.y == 0.0 && (instance.stitch & STITCH_NEG_Z) != 0u)
+        || (cell.y == PATCH_RESOLUTION && (instance.stitch & STITCH_POS_Z) != 0u);
+    if x_edge && odd.y {
+        cell.y -= 1.0;
+    }
+    if z_edge && odd.x {
+        cell.x -= 1.0;
+    }
+    return water_vertex(instance.origin + cell / PATCH_RESOLUTION * instance.size);
+}
+
+// The grid is laid out evenly on screen, and each vertex rests where its point meets the water plane.
+// Interpolating the homogeneous corners keeps the spacing even after the perspective divide.
+@vertex
+fn vs_projected(vertex: Vertex) -> Fragment {
+    let corners = projected_grid.corners;
+    let uv = vertex.tex_coords;
+    let point = mix(mix(corners[0], corners[1], uv.x), mix(corners[2], corners[3], uv.x), uv.y);
+    return water_vertex(point.xz / point.w);
+}
+
+// The reflection target is rendered from the mirrored camera, so it lines up with the screen.
+fn reflection(screen_pos: vec2<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let uv = screen_pos / vec2<f32>(textureDimensions(reflection_texture)) + normal.xz * REFLECTION_DISTORTION;
+    return textureSample(reflection_texture, screen_sampler, uv).rgb;
+}
+
+// Distance the view ray travels through the water before reaching the opaque scene.
+fn water_thickness(screen_pos: vec2<f32>, depth: f32) -> f32 {
+    let uv = screen_pos / vec2<f32>(textureDimensions(scene_depth));
+    return max(scene_distance(uv) - linear_depth(depth), 0.0);
+}
+
+// The opaque scene seen through the water, dimmed by absorption and tinted by scattering.
+fn refraction(screen_pos: vec2<f32>, depth: f32, normal: vec3<f32>, light: f32) -> vec3<f32> {
+    let uv = screen_pos / vec2<f32>(textureDimensions(refraction_texture));
+    let distance = linear_depth(depth);
+    let thickness = water_thickness(screen_pos, depth);
+    // Bend less where the water is thin, and not at all onto things in front of the water.
+    var refracted_uv = uv + normal.xz * REFRACTION_DISTORTION * min(thickness, 1.0);
+    if scene_distance(refracted_uv) < distance {
+        refracted_uv = uv;
+    }
+    let refracted_thickness = max(scene_distance(refracted_uv) - distance, 0.0);
+    let background = textureSample(refraction_texture, screen_sampler, refracted_uv).rgb;
+    let transmittance = exp(-ABSORPTION * refracted_thickness);
+    return background * transmittance + SCATTER_COLOR * light * (1.0 - transmittance);
+}
+
+// Whitecaps accumulated by the foam pass, plus a band along the shore broken up by the same pattern.
+fn foam(fragment: Fragment) -> f32 {
+    let whitecaps = textureSample(ocean_foam, ocean_sampler, ocean_uv(fragment.grid_pos)).x;
+    let shore = 1.0 - smoothstep(0.0, SHORE_FOAM_DEPTH, water_thickness(fragment.pos.xy, fragment.pos.z));
+    let breakup = textureSample(ocean_foam, ocean_sampler, ocean_uv(fragment.grid_pos * 4.0)).y;
+    let wake_foam = wake_at(fragment.grid_pos).w;
+    return max(whitecaps, max(shore, wake_foam) * clamp(2.0 - breakup * 1.5, 0.0, 1.0));
+}
+
+// Seen from below, the scene above shows through Snell's window, and outside it the surface
+// reflects the water back, as all the light is reflected internally.
+fn underside(fragment: Fragment, normal: vec3<f32>, light: f32) -> vec3<f32> {
+    let view = normalize(fragment.world_pos - camera.eye);
+    let transmitted = refract(view, -normal, WATER_TO_AIR);
+    let internal = SCATTER_COLOR * light;
+    if all(transmitted == vec3(0.0)) {
+        return internal;
+    }
+    let fresnel = BASE_REFLECTANCE + (1.0 - BASE_REFLECTANCE) * pow(1.0 - max(dot(transmitted, normal), 0.0), 5.0);
+    let uv = fragment.pos.xy / vec2<f32>(textureDimensions(refraction_texture)) + normal.xz * REFRACTION_DISTORTION;
+    let above = textureSampleLevel(refraction_texture, screen_sampler, uv, 0.0).rgb;
+    return mix(above, internal, fresnel);
+}
+
+// Lays down the water's depth for the hull mask to be tested against, without shading it.
+@fragment
+fn fs_depth() {}
+
+@fragment
+fn fs_main(fragment: Fragment, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    let normal = surface_normal(fragment.normal, fragment.grid_pos);
+    let diffuse = max(dot(normal, SUN_DIRECTION), 0.0);
+    let transmitted = refraction(fragment.pos.xy, fragment.pos.z, normal, 0.3 + 0.7 * diffuse);
+    let color = mix(transmitted, reflection(fragment.pos.xy, normal), REFLECTIVITY);
+    let above = mix(color, FOAM_COLOR * (0.5 + 0.5 * diffuse), foam(fragment));
+    // Texture sampling needs uniform control flow, so both sides are always shaded. Alpha tells
+    // the post pass the view ray reached the underside, so it started in the water.
+    let below = underside(fragment, normal, 0.3 + 0.7 * diffuse);
+    return select(vec4(below, 0.0), vec4(above, 1.0), front_facing);
+}
@@ -0,0 +1,28 @@
+// How the water and the particle fluid are lit and see the scene through them, shared by both.
+// Each shader binds `camera` and `scene_depth` where its own layout puts them.
+
+const SUN_DIRECTION: vec3<f32> = vec3(0.4082483, 0.8164966, 0.4082483);
+const REFLECTIVITY: f32 = 0.4;
+// Screen-space offset of the reflection lookup per unit of horizontal normal.
+const REFLECTION_DISTORTION: f32 = 0.04;
+// Beer-Lambert extinction per metre; red is absorbed first.
+const ABSORPTION: vec3<f32> = vec3(0.45, 0.09, 0.06);
+// Colour of the light scattered back out of the water, which replaces what is absorbed.
+const SCATTER_COLOR: vec3<f32> = vec3(0.0, 0.2, 0.25);
+// Relative index of refraction for light leaving the water.
+const WATER_TO_AIR: f32 = 1.33;
+// Reflectance at normal incidence between water and air.
+const BASE_REFLECTANCE: f32 = 0.02;
+
+// View-space distance along the view axis of a depth buffer value.
+fn linear_depth(depth: f32) -> f32 {
+    let view = camera.inverse_proj * vec4(0.0, 0.0, depth, 1.0);
+    // The sky is at w = 0 when the projection has no far plane, which this keeps finite.
+    return -view.z / max(view.w, 1e-6);
+}
+
+fn scene_distance(uv: vec2<f32>) -> f32 {
+    let size = textureDimensions(scene_depth);
+    let texel = clamp(vec2<u32>(uv * vec2<f32>(size)), vec2(0u), size - 1u);
+    return linear_depth(textureLoad(scene_depth, texel, 0).r);
+}
@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra as na;
+
+pub const MAX_WAVES: usize = 8;
+pub const GRAVITY: f32 = 9.81;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct GerstnerWave {
+    pub direction: [f32; 2],
+    pub amplitude: f32,
+    pub wavelength: f32,
+    pub steepness: f32,
+    _padding: [f32; 3],
+}
+
+impl GerstnerWave {
+    /// A zero `direction` has no heading, so the wave runs along +x instead.
+    pub fn new(direction: [f32; 2], amplitude: f32, wavelength: f32, steepness: f32) -> Self {
+        assert!(wavelength > 0.0 && wavelength.is_finite(), "Wavelength must be positive and finite, not {wavelength}");
+        let direction = na::Vector2::from(direction).try_normalize(f32::EPSILON).unwrap_or_else(|| {
+            log::warn!("Wave direction {direction:?} has no length, so the wave runs along +x");
+            na::Vector2::x()
+        });
+        Self {
+            direction: direction.into(),
+            amplitude,
+            wavelength,
+            steepness: steepness.clamp(0.0, 1.0),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct WaveUniform {
+    pub waves: [GerstnerWave; MAX_WAVES],
+    pub count: u32,
+    pub time: f32,
+    _padding: [f32; 2],
+}
+
+impl WaveUniform {
+    pub fn new(waves: &[GerstnerWave]) -> Self {
+        let mut uniform = Self::zeroed();
+        uniform.set_waves(waves);
+        uniform
+    }
+
+    pub fn set_waves(&mut self, waves: &[GerstnerWave]) {
+        let count = waves.len().min(MAX_WAVES);
+        if count < waves.len() {
+            log::warn!("Only the first {MAX_WAVES} of {} waves are used", waves.len());
+        }
+        self.waves = [GerstnerWave::zeroed(); MAX_WAVES];
+        self.waves[..count].copy_from_slice(&waves[..count]);
+        self.count = count as u32;
+    }
+
+    pub fn waves(&self) -> &[GerstnerWave] {
+        &self.waves[..self.count as usize]
+    }
+
+    /// Displaced position and normal of the grid point at rest position `p`.
+    /// Mirrors `gerstner` in `water.wgsl`.
+    pub fn gerstner(&self, p: na::Vector2<f32>, time: f32) -> (na::Vector3<f32>, na::Vector3<f32>) {
+        let mut position = na::Vector3::new(p.x, 0.0, p.y);
+        let mut normal = na::Vector3::y();
+        let count = self.count as f32;
+        for wave in self.waves() {
+            let direction = na::Vector2::from(wave.direction);
+            let k = std::f32::consts::TAU / wave.wavelength;
+            let omega = (GRAVITY * k).sqrt();
+            let theta = k * direction.dot(&p) - omega * time;
+            let (s, c) = theta.sin_cos();
+            let qa = wave.steepness / (k * count);
+            let wa = k * wave.amplitude;
+
+            position.x += qa * direction.x * c;
+            position.z += qa * direction.y * c;
+            position.y += wave.amplitude * s;
+
+            normal.x -= direction.x * wa * c;
+            normal.z -= direction.y * wa * c;
+            normal.y -= qa * k * s;
+        }
+        (position, normal.normalize())
+    }
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("Wave Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                count: None,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+        ],
+    };
+}
+
+impl Default for WaveUniform {
+    fn default() -> Self {
+        Self::new(&[
+            GerstnerWave::new([1.0, 0.0], 0.25, 8.0, 0.5),
+            GerstnerWave::new([0.8, 0.6], 0.15, 5.0, 0.4),
+            GerstnerWave::new([-0.3, 1.0], 0.08, 2.5, 0.3),
+            GerstnerWave::new([0.6, -0.7], 0.04, 1.2, 0.3),
+        ])
+    }
+}
@@ -0,0 +1,106 @@
+use nalgebra as na;
+use water::{Buoyancy, BuoyancySettings, FloatingBody, GerstnerWave, HeightField, OceanSettings, RigidBody, WaterSurface};
+
+struct FlatWater(f32);
+
+impl HeightField for FlatWater {
+    fn height_at(&self, _x: f32, _z: f32) -> f32 {
+        self.0
+    }
+}
+
+fn run(buoyancy: &mut Buoyancy, water: &impl HeightField, seconds: f32) {
+    let steps = (seconds / buoyancy.settings().time_step).round() as u32;
+    for _ in 0..steps {
+        buoyancy.step(water);
+    }
+}
+
+fn cube(density: f32, position: na::Point3<f32>) -> FloatingBody {
+    FloatingBody::cuboid(density, na::Vector3::repeat(0.5), position)
+}
+
+#[test]
+fn body_settles_at_its_density_ratio() {
+    let water = FlatWater(0.0);
+    let mut buoyancy = Buoyancy::new(BuoyancySettings::default());
+    buoyancy.add_body(cube(250.0, na::Point3::new(0.0, 1.0, 0.0)));
+    buoyancy.add_body(cube(500.0, na::Point3::new(3.0, 1.0, 0.0)));
+    run(&mut buoyancy, &water, 30.0);
+
+    for (body, expected) in buoyancy.bodies().iter().zip([0.25, 0.5]) {
+        let submerged = body.submerged(&water);
+        assert!((submerged - expected).abs() < 0.01, "submerged {submerged}, expected {expected}");
+        assert!(body.body.linear_velocity.norm() < 0.01);
+    }
+}
+
+#[test]
+fn dense_body_sinks() {
+    let water = FlatWater(0.0);
+    let mut buoyancy = Buoyancy::new(BuoyancySettings::default());
+    buoyancy.add_body(cube(2000.0, na::Point3::new(0.0, 0.0, 0.0)));
+    run(&mut buoyancy, &water, 5.0);
+
+    let body = &buoyancy.bodies()[0];
+    assert!(body.body.position.y < -2.0);
+    assert_eq!(body.submerged(&water), 1.0);
+}
+
+#[test]
+fn flat_body_rights_itself() {
+    let water = FlatWater(0.0);
+    let mut buoyancy = Buoyancy::new(BuoyancySettings::default());
+    let mut raft = FloatingBody::cuboid(400.0, na::Vector3::new(1.0, 0.2, 1.0), na::Point3::origin());
+    raft.body.orientation = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), 0.5);
+    buoyancy.add_body(raft);
+    run(&mut buoyancy, &water, 20.0);
+
+    let up = buoyancy.bodies()[0].body.orientation * na::Vector3::y();
+    assert!(up.y > 0.999, "up {up:?}");
+}
+
+#[test]
+fn off_centre_force_spins_body() {
+    let mut body = RigidBody::cuboid(1.0, na::Vector3::repeat(0.5), na::Point3::origin());
+    body.apply_force_at_point(na::Vector3::new(0.0, 0.0, 1.0), &na::Point3::new(0.5, 0.0, 0.0));
+    body.integrate(0.1);
+
+    assert!((body.linear_velocity - na::Vector3::new(0.0, 0.0, 0.1)).norm() < 1e-6);
+    // Torque 0.5 about -y on a unit cube with inertia 1/6.
+    assert!((body.angular_velocity - na::Vector3::new(0.0, -0.3, 0.0)).norm() < 1e-5);
+}
+
+#[test]
+fn simulation_is_deterministic_on_waves() {
+    let waves = [
+        GerstnerWave::new([1.0, 0.3], 0.3, 8.0, 0.5),
+        GerstnerWave::new([-0.4, 1.0], 0.15, 4.0, 0.4),
+    ];
+    let simulate = || {
+        let mut water = WaterSurface::new(&waves, OceanSettings {
+            resolution: 32,
+            ..Default::default()
+        });
+        let mut buoyancy = Buoyancy::new(BuoyancySettings::default());
+        buoyancy.add_body(cube(400.0, na::Point3::new(0.0, 0.5, 0.0)));
+        buoyancy.add_body(FloatingBody::cuboid(300.0, na::Vector3::new(1.5, 0.3, 0.5), na::Point3::new(4.0, 0.5, 2.0)));
+        let mut time = 0.0;
+        // Uneven frame times, which the fixed step absorbs.
+        for frame in 0..240 {
+            let dt = if frame % 3 == 0 { 0.02 } else { 0.013 };
+            time += dt;
+            water.update(time);
+            buoyancy.update(&water, dt);
+        }
+        buoyancy.model_matrices()
+    };
+
+    let first = simulate();
+    assert_eq!(first, simulate());
+    for model in &first {
+        assert!(model.iter().all(|x| x.is_finite()));
+        // Still afloat near the surface.
+        assert!(model[(1, 3)].abs() < 1.0);
+    }
+}
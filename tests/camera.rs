@@ -0,0 +1,247 @@
+use std::f32::consts::FRAC_PI_2;
+
+use nalgebra as na;
+use water::{Angle, Camera, CameraController, CameraUniform, OrbitController, Projection};
+use winit::{event::ElementState, keyboard::KeyCode};
+
+const SPEED: f32 = 2.0;
+const SENSITIVITY: f32 = 0.01;
+
+fn assert_close(a: na::Point3<f32>, b: na::Point3<f32>) {
+    assert!((a - b).norm() < 1e-4, "{a} is not {b}");
+}
+
+fn assert_same_view(a: &Camera, b: &Camera) {
+    assert!((a.0.to_homogeneous() - b.0.to_homogeneous()).norm() < 1e-4, "{:?} is not {:?}", a.0, b.0);
+}
+
+#[test]
+fn yaw_and_pitch_round_trip() {
+    let eye = na::Point3::new(1.0, 2.0, 3.0);
+    let camera = Camera::new(eye, 0.7, -0.3);
+    assert!((camera.yaw() - 0.7).abs() < 1e-5);
+    assert!((camera.pitch() + 0.3).abs() < 1e-5);
+    assert_close(camera.eye(), eye);
+}
+
+#[test]
+fn keys_move_the_camera_by_speed_times_delta_time() {
+    let mut controller = CameraController::new(SPEED, SENSITIVITY);
+    let mut camera = Camera::new(na::Point3::origin(), 0.0, 0.0);
+    assert!(controller.process_keyboard(KeyCode::KeyW, ElementState::Pressed));
+    controller.update_camera(&mut camera, 0.5);
+    assert_close(camera.eye(), na::Point3::new(1.0, 0.0, 0.0));
+
+    controller.process_keyboard(KeyCode::KeyW, ElementState::Released);
+    controller.process_keyboard(KeyCode::KeyD, ElementState::Pressed);
+    controller.process_keyboard(KeyCode::Space, ElementState::Pressed);
+    controller.update_camera(&mut camera, 0.25);
+    assert_close(camera.eye(), na::Point3::new(1.0, 0.5, 0.5));
+
+    assert!(!controller.process_keyboard(KeyCode::KeyQ, ElementState::Pressed));
+}
+
+#[test]
+fn forward_follows_the_pitch() {
+    let mut controller = CameraController::new(SPEED, SENSITIVITY);
+    let mut camera = Camera::new(na::Point3::origin(), FRAC_PI_2, -0.5);
+    controller.process_keyboard(KeyCode::KeyW, ElementState::Pressed);
+    controller.update_camera(&mut camera, 1.0);
+    assert_close(camera.eye(), na::Point3::new(0.0, -(0.5f32.sin()), 0.5f32.cos()) * SPEED);
+}
+
+#[test]
+fn mouse_turns_the_camera_and_pitch_stops_short_of_vertical() {
+    let mut controller = CameraController::new(SPEED, SENSITIVITY);
+    let mut camera = Camera::new(na::Point3::origin(), 0.0, 0.0);
+    controller.process_mouse(30.0, -20.0);
+    controller.update_camera(&mut camera, 0.016);
+    assert!((camera.yaw() - 0.3).abs() < 1e-4);
+    assert!((camera.pitch() - 0.2).abs() < 1e-4);
+
+    // Movement is used up by the update that applies it.
+    controller.update_camera(&mut camera, 0.016);
+    assert!((camera.yaw() - 0.3).abs() < 1e-4);
+
+    controller.process_mouse(0.0, -1000.0);
+    controller.update_camera(&mut camera, 0.016);
+    assert!(camera.pitch() < FRAC_PI_2 && camera.pitch() > FRAC_PI_2 - 0.01);
+    assert!((camera.yaw() - 0.3).abs() < 1e-2, "yaw {} drifted looking up", camera.yaw());
+    assert!(camera.forward().iter().all(|x| x.is_finite()));
+}
+
+#[test]
+fn switching_between_fly_and_orbit_keeps_the_view() {
+    let start = Camera::new(na::Point3::new(2.0, 3.0, 4.0), 1.2, -0.4);
+    let mut camera = Camera::new(start.eye(), start.yaw(), start.pitch());
+    let mut orbit = OrbitController::new(SENSITIVITY, 0.1, 0.0);
+    orbit.attach(&camera, 5.0);
+    orbit.update_camera(&mut camera, 0.016);
+    assert_same_view(&camera, &start);
+    assert_close(orbit.target(), start.eye() + start.forward() * 5.0);
+
+    orbit.process_mouse(40.0, 10.0);
+    orbit.update_camera(&mut camera, 0.016);
+    let orbited = Camera::new(camera.eye(), camera.yaw(), camera.pitch());
+    let mut fly = CameraController::new(SPEED, SENSITIVITY);
+    fly.update_camera(&mut camera, 0.016);
+    assert_same_view(&camera, &orbited);
+}
+
+#[test]
+fn orbiting_keeps_the_target_ahead_at_the_same_distance() {
+    let mut camera = Camera::new(na::Point3::new(0.0, 2.0, 6.0), -FRAC_PI_2, -0.3);
+    let mut orbit = OrbitController::new(SENSITIVITY, 0.1, 0.0);
+    orbit.attach(&camera, 6.0);
+    orbit.process_mouse(100.0, -50.0);
+    orbit.update_camera(&mut camera, 0.016);
+    assert!((camera.yaw() - (-FRAC_PI_2 + 1.0)).abs() < 1e-4);
+    assert!((camera.pitch() - 0.2).abs() < 1e-4);
+    assert_close(camera.eye() + camera.forward() * 6.0, orbit.target());
+}
+
+#[test]
+fn scrolling_zooms_and_panning_drags_the_target() {
+    let mut camera = Camera::new(na::Point3::new(0.0, 0.0, 10.0), -FRAC_PI_2, 0.0);
+    let mut orbit = OrbitController::new(SENSITIVITY, 0.1, 0.0);
+    orbit.attach(&camera, 10.0);
+    orbit.process_scroll(2.0);
+    orbit.update_camera(&mut camera, 0.016);
+    assert!((orbit.distance() - 8.1).abs() < 1e-4);
+    assert_close(camera.eye(), na::Point3::new(0.0, 0.0, 8.1));
+
+    orbit.process_scroll(-1000.0);
+    assert_eq!(orbit.distance(), orbit.max_distance);
+    orbit.process_scroll(1000.0);
+    assert_eq!(orbit.distance(), orbit.min_distance);
+
+    // Dragging right and down pulls the scene along, so the target goes left and up.
+    let mut orbit = OrbitController::new(SENSITIVITY, 0.1, 0.0);
+    orbit.attach(&Camera::new(na::Point3::new(0.0, 0.0, 10.0), -FRAC_PI_2, 0.0), 10.0);
+    orbit.process_pan(10.0, 5.0);
+    orbit.update_camera(&mut camera, 0.016);
+    assert_close(orbit.target(), na::Point3::new(-1.0, 0.5, 0.0));
+    assert!((camera.yaw() + FRAC_PI_2).abs() < 1e-4 && camera.pitch().abs() < 1e-4);
+}
+
+#[test]
+fn smoothing_eases_towards_the_input() {
+    let mut camera = Camera::new(na::Point3::new(0.0, 0.0, 10.0), -FRAC_PI_2, 0.0);
+    let mut orbit = OrbitController::new(SENSITIVITY, 0.1, 0.2);
+    orbit.attach(&camera, 10.0);
+    orbit.process_mouse(50.0, 0.0);
+    orbit.update_camera(&mut camera, 0.05);
+    let turned = camera.yaw() + FRAC_PI_2;
+    assert!(turned > 0.05 && turned < 0.45, "turned {turned} of 0.5 in the first frame");
+    for _ in 0..100 {
+        orbit.update_camera(&mut camera, 0.05);
+    }
+    assert!((camera.yaw() + FRAC_PI_2 - 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn camera_uniform_inverses_undo_the_matrices() {
+    let camera = Camera::new(na::Point3::new(1.0, 2.0, 3.0), 0.4, -0.2);
+    let projection = Projection::perspective(16.0 / 9.0, Angle::Radians(1.0), 0.1, 100.0);
+    let uniform = CameraUniform::from_camera(&camera, &projection);
+    let [view, proj, view_proj, inverse_view, inverse_proj, inverse_view_proj] = [
+        uniform.view,
+        uniform.proj,
+        uniform.view_proj,
+        uniform.inverse_view,
+        uniform.inverse_proj,
+        uniform.inverse_view_proj,
+    ]
+    .map(na::Matrix4::from);
+    assert!((proj * view - view_proj).norm() < 1e-4);
+    for (matrix, inverse) in [(view, inverse_view), (proj, inverse_proj), (view_proj, inverse_view_proj)] {
+        assert!((matrix * inverse - na::Matrix4::identity()).norm() < 1e-3);
+    }
+    assert_close(na::Point3::from(uniform.eye), camera.eye());
+
+    // A point on the far plane in the middle of the screen lies straight ahead of the eye.
+    let far = inverse_view_proj.transform_point(&na::Point3::new(0.0, 0.0, 1.0));
+    let ahead = far - camera.eye();
+    assert!((ahead.normalize() - camera.forward()).norm() < 1e-4);
+    assert!((ahead.norm() / projection.z_far() - 1.0).abs() < 1e-3);
+}
+
+fn projections() -> [Projection; 3] {
+    [
+        Projection::perspective(16.0 / 9.0, Angle::Degrees(60.0), 0.1, 100.0),
+        Projection::orthographic(16.0 / 9.0, 20.0, 0.1, 100.0),
+        Projection::infinite_reverse_z(16.0 / 9.0, Angle::Degrees(60.0), 0.1),
+    ]
+}
+
+#[test]
+fn fovy_is_taken_in_degrees_or_radians() {
+    let degrees = Projection::perspective(2.0, Angle::Degrees(45.0), 0.1, 100.0);
+    let radians = Projection::perspective(2.0, Angle::Radians(std::f32::consts::FRAC_PI_4), 0.1, 100.0);
+    assert!((degrees.to_matrix() - radians.to_matrix()).norm() < 1e-6);
+
+    // The top of the screen is half the field of view above the view axis.
+    for projection in [degrees, Projection::infinite_reverse_z(2.0, Angle::Degrees(45.0), 0.1)] {
+        let top = projection.to_matrix().transform_point(&na::Point3::new(0.0, 22.5f32.to_radians().tan() * 10.0, -10.0));
+        assert!((top.y - 1.0).abs() < 1e-5, "{projection:?} puts the top at {}", top.y);
+    }
+}
+
+#[test]
+fn depth_and_its_test_follow_the_projection() {
+    for projection in projections() {
+        assert!((projection.depth(projection.z_near()) - projection.near_depth()).abs() < 1e-5, "{projection:?}");
+        let far = projection.z_far().min(1e7);
+        assert!((projection.depth(far) - projection.far_depth()).abs() < 1e-5, "{projection:?}");
+        assert_eq!(projection.far_depth(), 1.0 - projection.near_depth());
+
+        // The nearer of two points passes the test against the further one.
+        let (near, further) = (projection.depth(5.0), projection.depth(50.0));
+        let passes = match projection.depth_compare() {
+            wgpu::CompareFunction::Less => near < further,
+            wgpu::CompareFunction::Greater => near > further,
+            compare => panic!("{compare:?} for {projection:?}"),
+        };
+        assert!(passes, "{projection:?} puts 5 m at {near} and 50 m at {further}");
+    }
+    assert!(projections()[2].reversed_depth() && projections()[2].z_far().is_infinite());
+}
+
+#[test]
+fn orthographic_rays_are_parallel() {
+    let camera = Camera::new(na::Point3::new(0.0, 5.0, 0.0), 0.3, -0.6);
+    let projection = projections()[1];
+    let (a, da) = camera.ray(&projection, na::Point2::new(-0.5, -0.5));
+    let (b, db) = camera.ray(&projection, na::Point2::new(0.5, 0.5));
+    assert!((da - camera.forward()).norm() < 1e-4 && (db - camera.forward()).norm() < 1e-4);
+    assert!(((b - a).norm() - (10.0f32 * 16.0 / 9.0).hypot(10.0)).abs() < 1e-3);
+
+    // Sizes on screen do not change with distance.
+    let matrix = projection.to_matrix();
+    for distance in [1.0, 10.0, 90.0] {
+        assert!((matrix.transform_point(&na::Point3::new(0.0, 10.0, -distance)).y - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn rays_leave_from_the_near_plane_without_a_far_plane() {
+    let camera = Camera::new(na::Point3::new(1.0, 2.0, 3.0), 0.4, -0.2);
+    let (origin, direction) = camera.ray(&projections()[2], na::Point2::origin());
+    assert_close(origin, camera.eye() + camera.forward() * 0.1);
+    assert!((direction - camera.forward()).norm() < 1e-4);
+}
+
+#[test]
+fn oblique_near_plane_is_the_clip_plane() {
+    // Keeps what is below y = -2 in view space.
+    let plane = na::Vector4::new(0.0, -1.0, 0.0, -2.0);
+    for projection in projections() {
+        let matrix = projection.to_oblique_matrix(plane);
+        for point in [na::Point3::new(0.0, -2.0, -5.0), na::Point3::new(3.0, -2.0, -40.0)] {
+            let depth = matrix.transform_point(&point).z;
+            assert!((depth - projection.near_depth()).abs() < 1e-3, "{projection:?} puts {point} at {depth}");
+        }
+        let below = matrix.transform_point(&na::Point3::new(0.0, -4.0, -20.0)).z;
+        assert!(below > 0.0 && below < 1.0, "{projection:?} puts a kept point at {below}");
+    }
+}
@@ -0,0 +1,87 @@
+use pollster::FutureExt as _;
+
+/// Any adapter will do, as the GPU tests compare against CPU references. `None` when there is none.
+pub fn request_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).block_on()?;
+    adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            memory_hints: wgpu::MemoryHints::default(),
+        },
+        None,
+    ).block_on().ok()
+}
+
+// Copies the first channel of a float texture into a buffer of f32, whatever the texture's format.
+const HEIGHT_SHADER: &str = "
+@group(0) @binding(0) var texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> heights: array<f32>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture);
+    if id.x < size.x && id.y < size.y {
+        heights[id.y * size.x + id.x] = textureLoad(texture, id.xy, 0).x;
+    }
+}
+";
+
+/// Largest magnitude of the heights stored in the first channel of `texture`.
+#[allow(dead_code)]
+pub fn max_height(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> f32 {
+    let size = (texture.width() * texture.height()) as u64 * size_of::<f32>() as u64;
+    let heights = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(HEIGHT_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.create_view(&wgpu::TextureViewDescriptor::default())),
+            },
+            wgpu::BindGroupEntry { binding: 1, resource: heights.as_entire_binding() },
+        ],
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+    pass.set_pipeline(&pipeline);
+    pass.set_bind_group(0, &bind_group, &[]);
+    pass.dispatch_workgroups(texture.width().div_ceil(8), texture.height().div_ceil(8), 1);
+    drop(pass);
+    encoder.copy_buffer_to_buffer(&heights, 0, &readback, 0, size);
+    queue.submit(Some(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |result| result.expect("Failed to map heights"));
+    device.poll(wgpu::Maintain::Wait);
+    let data = readback.slice(..).get_mapped_range();
+    bytemuck::cast_slice::<u8, f32>(&data).iter().fold(0.0, |max, height| max.max(height.abs()))
+}
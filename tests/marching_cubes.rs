@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use nalgebra as na;
+use water::{DensityGrid, GpuMarchingCubes, ModelVertex, Sph, SphSettings};
+
+mod common;
+
+const RADIUS: f32 = 0.8;
+
+// Higher inside a sphere, so its surface is where the field crosses zero.
+fn sphere() -> DensityGrid {
+    let size = 24;
+    let spacing = 2.0 / (size - 1) as f32;
+    DensityGrid::from_fn(na::Point3::new(-1.0, -1.0, -1.0), spacing, [size; 3], |p| RADIUS - p.coords.norm())
+}
+
+fn position(vertex: &ModelVertex) -> na::Point3<f32> {
+    na::Point3::from(vertex.position)
+}
+
+fn assert_closed(indices: &[u32]) {
+    let mut edges = HashMap::new();
+    for triangle in indices.chunks(3) {
+        for i in 0..3 {
+            *edges.entry((triangle[i], triangle[(i + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+    for (&(a, b), &count) in &edges {
+        assert_eq!(count, 1, "edge {a}-{b} is used {count} times in the same direction");
+        assert_eq!(edges.get(&(b, a)), Some(&1), "edge {a}-{b} has no triangle on the other side");
+    }
+}
+
+// By the divergence theorem, positive when the triangles face outwards.
+fn enclosed_volume(vertices: &[ModelVertex], indices: &[u32]) -> f32 {
+    indices.chunks(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| position(&vertices[triangle[i] as usize]).coords);
+            a.dot(&b.cross(&c)) / 6.0
+        })
+        .sum()
+}
+
+fn read_buffer<T: bytemuck::Pod>(device: &wgpu::Device, queue: &wgpu::Queue, source: &wgpu::Buffer, count: usize) -> Vec<T> {
+    let size = (count * size_of::<T>()) as u64;
+    if size == 0 {
+        return Vec::new();
+    }
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(source, 0, &buffer, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+    buffer.slice(..).map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let values = bytemuck::cast_slice(&buffer.slice(..).get_mapped_range()).to_vec();
+    values
+}
+
+#[test]
+fn sphere_mesh_is_closed_and_faces_out() {
+    let (vertices, indices) = sphere().extract_surface(0.0);
+    assert!(!indices.is_empty());
+    assert_eq!(indices.len() % 3, 0);
+    assert_closed(&indices);
+
+    let volume = enclosed_volume(&vertices, &indices);
+    let expected = 4.0 / 3.0 * std::f32::consts::PI * RADIUS.powi(3);
+    assert!((volume - expected).abs() < 0.02 * expected, "volume {volume}, expected {expected}");
+}
+
+#[test]
+fn vertices_lie_on_the_surface_with_outward_normals() {
+    let grid = sphere();
+    let (vertices, _) = grid.extract_surface(0.0);
+    for vertex in &vertices {
+        let p = position(vertex);
+        assert!((p.coords.norm() - RADIUS).abs() < 0.1 * grid.spacing, "vertex at {p} is off the sphere");
+        let normal = na::Vector3::from(vertex.normal);
+        assert!(normal.dot(&p.coords.normalize()) > 0.95, "normal {normal:?} at {p}");
+    }
+}
+
+#[test]
+fn particle_block_becomes_a_closed_box() {
+    let settings = SphSettings::default();
+    let (min, max) = (na::Point3::new(-0.2, 0.0, -0.2), na::Point3::new(0.2, 0.3, 0.2));
+    let sph = Sph::block(settings, min, max);
+    let grid = DensityGrid::from_particles(sph.particles(), &settings, 0.25 * settings.smoothing_radius);
+    let (vertices, indices) = grid.extract_surface(0.5 * settings.rest_density);
+    assert_closed(&indices);
+    for vertex in &vertices {
+        let p = position(vertex);
+        let margin = settings.smoothing_radius;
+        assert!((0..3).all(|axis| p[axis] > min[axis] - margin && p[axis] < max[axis] + margin), "vertex at {p}");
+    }
+    let volume = enclosed_volume(&vertices, &indices);
+    let expected = (max - min).product();
+    assert!((volume - expected).abs() < 0.25 * expected, "volume {volume}, expected {expected}");
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let grid = sphere();
+    let (vertices, indices) = grid.extract_surface(0.0);
+
+    let mut marching_cubes = GpuMarchingCubes::new(&device, grid.size);
+    marching_cubes.write(&queue, &grid, 0.0);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    marching_cubes.dispatch(&mut encoder);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let counters: Vec<u32> = read_buffer(&device, &queue, &marching_cubes.counters, 6);
+    let (index_count, vertex_count) = (counters[0] as usize, counters[5] as usize);
+    assert_eq!(counters[1], 1, "instance count");
+    assert_eq!(vertex_count, vertices.len());
+    assert_eq!(index_count, indices.len());
+    let gpu_vertices: Vec<ModelVertex> = read_buffer(&device, &queue, &marching_cubes.vertices, vertex_count);
+    let gpu_indices: Vec<u32> = read_buffer(&device, &queue, &marching_cubes.indices, index_count);
+
+    // The GPU writes its vertices in any order, so they are matched up by position.
+    let key = |vertex: &ModelVertex| vertex.position.map(|x| (x * 1e4).round() as i32);
+    let cpu_index: HashMap<_, _> = vertices.iter().enumerate().map(|(i, vertex)| (key(vertex), i as u32)).collect();
+    let to_cpu: Vec<u32> = gpu_vertices.iter()
+        .map(|vertex| *cpu_index.get(&key(vertex)).unwrap_or_else(|| panic!("no CPU vertex at {:?}", vertex.position)))
+        .collect();
+    for (gpu, &cpu) in gpu_vertices.iter().zip(&to_cpu) {
+        let cpu = &vertices[cpu as usize];
+        let normal_error = (na::Vector3::from(gpu.normal) - na::Vector3::from(cpu.normal)).norm();
+        assert!(normal_error < 1e-3, "gpu normal {:?}, cpu {:?}", gpu.normal, cpu.normal);
+    }
+
+    // Same triangles with the same winding, each rotated to start at its lowest index.
+    let canonical = |indices: &[u32]| {
+        let mut triangles: Vec<[u32; 3]> = indices.chunks(3)
+            .map(|t| {
+                let first = (0..3).min_by_key(|&i| t[i]).unwrap();
+                [t[first], t[(first + 1) % 3], t[(first + 2) % 3]]
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    };
+    let gpu_in_cpu_indices: Vec<u32> = gpu_indices.iter().map(|&i| to_cpu[i as usize]).collect();
+    assert_eq!(canonical(&gpu_in_cpu_indices), canonical(&indices));
+}
@@ -0,0 +1,101 @@
+use nalgebra as na;
+use water::{Angle, Camera, ProjectedGridUniform, Projection};
+
+const LEVEL: f32 = 0.0;
+const MAX_HEIGHT: f32 = 1.0;
+const STEPS: usize = 8;
+
+fn projection() -> Projection {
+    Projection::perspective(16.0 / 9.0, Angle::Radians(1.0), 0.1, 100.0)
+}
+
+fn look_at(eye: [f32; 3], target: [f32; 3]) -> Camera {
+    Camera(na::Isometry3::look_at_rh(&eye.into(), &target.into(), &na::Vector3::y()))
+}
+
+fn ndc(camera: &Camera, point: na::Point3<f32>) -> na::Point3<f32> {
+    (projection().to_matrix() * camera.0.to_homogeneous()).transform_point(&point)
+}
+
+fn grid_points(grid: &ProjectedGridUniform) -> Vec<Vec<na::Point3<f32>>> {
+    (0..=STEPS)
+        .map(|j| {
+            (0..=STEPS)
+                .map(|i| grid.position(na::Point2::new(i as f32, j as f32) / STEPS as f32))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn grid_lies_on_the_water_plane_evenly_spaced_on_screen() {
+    let camera = look_at([0.0, 4.0, 12.0], [0.0, 0.0, 0.0]);
+    let grid = ProjectedGridUniform::new(&camera, &projection(), LEVEL, MAX_HEIGHT).expect("water in view");
+    let rows: Vec<Vec<_>> = grid_points(&grid)
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|p| {
+                    assert!((p.y - LEVEL).abs() < 1e-3, "grid point {p} is off the plane");
+                    ndc(&camera, p).xy()
+                })
+                .collect()
+        })
+        .collect();
+    let step = rows[1][1] - rows[0][0];
+    for j in 0..=STEPS {
+        for i in 0..=STEPS {
+            let expected = rows[0][0].coords + na::Vector2::new(step.x * i as f32, step.y * j as f32);
+            assert!((rows[j][i].coords - expected).norm() < 1e-3, "grid point ({i}, {j}) at {} on screen", rows[j][i]);
+        }
+    }
+}
+
+#[test]
+fn grid_covers_the_visible_water_to_the_far_plane() {
+    let camera = look_at([0.0, 4.0, 12.0], [0.0, 0.0, 0.0]);
+    let grid = ProjectedGridUniform::new(&camera, &projection(), LEVEL, MAX_HEIGHT).expect("water in view");
+    let points = grid_points(&grid);
+    let (min, max) = (ndc(&camera, points[0][0]), ndc(&camera, points[STEPS][STEPS]));
+    let (min, max) = (min.xy().coords.inf(&max.xy().coords), min.xy().coords.sup(&max.xy().coords));
+    assert!(min.x <= -1.0 && max.x >= 1.0, "grid spans {min:?} to {max:?}");
+    assert!(min.y <= -1.0, "grid spans {min:?} to {max:?}");
+
+    // Everywhere the camera sees the water plane before the far plane is under the grid.
+    let eye = camera.eye();
+    let forward = camera.0.inverse() * -na::Vector3::z();
+    for j in 0..=20 {
+        for i in 0..=20 {
+            let point = na::Point2::new(i as f32, j as f32) / 10.0 - na::Vector2::repeat(1.0);
+            let (origin, direction) = camera.ray(&projection(), point);
+            if direction.y >= 0.0 {
+                continue;
+            }
+            let hit = origin + direction * ((LEVEL - origin.y) / direction.y);
+            if (hit - eye).dot(&forward) < projection().z_far() {
+                assert!(point.y <= max.y, "water at {point} on screen is above the grid");
+            }
+        }
+    }
+    let furthest = points[STEPS].iter().chain(&points[0]).map(|p| (p - eye).dot(&forward)).fold(0.0, f32::max);
+    assert!(furthest > 0.9 * projection().z_far(), "grid ends {furthest} away");
+}
+
+#[test]
+fn no_grid_when_looking_at_the_sky() {
+    let camera = look_at([0.0, 10.0, 0.0], [0.0, 20.0, -5.0]);
+    assert!(ProjectedGridUniform::new(&camera, &projection(), LEVEL, MAX_HEIGHT).is_none());
+}
+
+#[test]
+fn grid_stays_finite_in_and_under_the_water() {
+    for (eye, target) in [([0.0, -3.0, 0.0], [0.0, -1.0, -5.0]), ([0.0, 0.3, 0.0], [0.0, 0.0, -5.0])] {
+        let camera = look_at(eye, target);
+        let grid = ProjectedGridUniform::new(&camera, &projection(), LEVEL, MAX_HEIGHT).expect("water in view");
+        for p in grid_points(&grid).into_iter().flatten() {
+            assert!(p.coords.iter().all(|x| x.is_finite()), "grid point {p} from {eye:?}");
+            assert!((p.y - LEVEL).abs() < 1e-3, "grid point {p} from {eye:?} is off the plane");
+            assert!((p - na::Point3::from(eye)).norm() < 2.0 * projection().z_far(), "grid point {p} from {eye:?}");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+
+use nalgebra as na;
+use water::{
+    Angle, Camera, Projection, QuadtreeSettings, WaterPatch, PATCH_RESOLUTION, STITCH_NEG_X, STITCH_NEG_Z,
+    STITCH_POS_X, STITCH_POS_Z,
+};
+
+const LEVEL: f32 = 0.0;
+const MAX_HEIGHT: f32 = 1.0;
+
+fn projection() -> Projection {
+    Projection::perspective(16.0 / 9.0, Angle::Radians(1.0), 0.1, 100.0)
+}
+
+fn camera() -> Camera {
+    look_at([3.3, 4.0, 7.1], [0.0, 0.0, -20.0])
+}
+
+fn look_at(eye: [f32; 3], target: [f32; 3]) -> Camera {
+    Camera(na::Isometry3::look_at_rh(&eye.into(), &target.into(), &na::Vector3::y()))
+}
+
+fn select(camera: &Camera) -> Vec<WaterPatch> {
+    QuadtreeSettings::default().select_patches(camera, &projection(), LEVEL, MAX_HEIGHT)
+}
+
+fn contains(patch: &WaterPatch, p: na::Point2<f32>) -> bool {
+    let [x, z] = patch.origin;
+    p.x >= x && p.x < x + patch.size && p.y >= z && p.y < z + patch.size
+}
+
+// Each edge as the fixed coordinate, the axis it runs along, its start and end, and its stitch bit.
+fn edges(patch: &WaterPatch) -> [(f32, usize, f32, f32, u32); 4] {
+    let [x, z] = patch.origin;
+    let s = patch.size;
+    [
+        (x, 1, z, z + s, STITCH_NEG_X),
+        (x + s, 1, z, z + s, STITCH_POS_X),
+        (z, 0, x, x + s, STITCH_NEG_Z),
+        (z + s, 0, x, x + s, STITCH_POS_Z),
+    ]
+}
+
+// Distinct positions of the vertices along an edge once stitched, mirroring `vs_patch`, between
+// `from` and `to`, in thousandths of a metre.
+fn edge_vertices(patch: &WaterPatch, edge: usize, from: f32, to: f32) -> BTreeSet<i64> {
+    let (_, _, start, _, bit) = edges(patch)[edge];
+    let step = patch.size / PATCH_RESOLUTION as f32;
+    (0..=PATCH_RESOLUTION)
+        .map(|i| if patch.stitch & bit != 0 && i % 2 == 1 { i - 1 } else { i })
+        .map(|i| start + i as f32 * step)
+        .filter(|&t| t >= from - 1e-3 && t <= to + 1e-3)
+        .map(|t| (t * 1000.0).round() as i64)
+        .collect()
+}
+
+#[test]
+fn patches_cover_the_visible_water_once() {
+    let camera = camera();
+    let patches = select(&camera);
+    assert!(!patches.is_empty());
+    let eye = camera.eye();
+    let forward = camera.0.inverse() * -na::Vector3::z();
+    for j in 0..=20 {
+        for i in 0..=20 {
+            let ndc = na::Point2::new(i as f32, j as f32) / 10.0 - na::Vector2::repeat(1.0);
+            let (origin, direction) = camera.ray(&projection(), ndc);
+            if direction.y >= 0.0 {
+                continue;
+            }
+            let hit = origin + direction * ((LEVEL - origin.y) / direction.y);
+            if (hit - eye).dot(&forward) >= projection().z_far() {
+                continue;
+            }
+            let count = patches.iter().filter(|patch| contains(patch, hit.xz())).count();
+            assert_eq!(count, 1, "water at {hit} is under {count} patches");
+        }
+    }
+}
+
+#[test]
+fn patches_grow_with_distance() {
+    let camera = camera();
+    let settings = QuadtreeSettings::default();
+    let patches = select(&camera);
+    let eye = camera.eye();
+    let smallest = patches.iter().map(|patch| patch.size).fold(f32::MAX, f32::min);
+    assert_eq!(smallest, settings.min_patch_size);
+    for patch in &patches {
+        let low = na::Vector3::new(patch.origin[0], LEVEL - MAX_HEIGHT, patch.origin[1]);
+        let high = low + na::Vector3::new(patch.size, 2.0 * MAX_HEIGHT, patch.size);
+        let distance = (eye.coords - eye.coords.sup(&low).inf(&high)).norm();
+        if patch.size >= 2.0 * settings.min_patch_size {
+            assert!(distance >= settings.split_distance * patch.size, "patch {patch:?} is {distance} away");
+        }
+    }
+    assert!(patches.iter().any(|patch| patch.size >= 16.0 * settings.min_patch_size));
+}
+
+#[test]
+fn seams_are_balanced_and_crack_free() {
+    let patches = select(&camera());
+    let mut seams = 0;
+    for a in &patches {
+        for b in &patches {
+            if a.size > b.size || a == b {
+                continue;
+            }
+            for (edge, &(line, axis, start, end, bit)) in edges(a).iter().enumerate() {
+                // The edge of b facing a, on the same line and overlapping it.
+                let facing = edge ^ 1;
+                let (b_line, b_axis, b_start, b_end, b_bit) = edges(b)[facing];
+                let (from, to) = (start.max(b_start), end.min(b_end));
+                if b_axis != axis || (b_line - line).abs() > 1e-3 || to - from <= 1e-3 {
+                    continue;
+                }
+                seams += 1;
+                assert!(b.size <= 2.0 * a.size, "patch {a:?} borders {b:?}");
+                assert_eq!(a.stitch & bit != 0, b.size > a.size, "stitching of {a:?} towards {b:?}");
+                assert_eq!(b.stitch & b_bit, 0, "{b:?} stitched towards the smaller {a:?}");
+                assert_eq!(
+                    edge_vertices(a, edge, from, to),
+                    edge_vertices(b, facing, from, to),
+                    "vertices of {a:?} and {b:?} differ along their seam",
+                );
+            }
+        }
+    }
+    assert!(seams > 0);
+}
+
+#[test]
+fn patches_outside_the_view_are_culled() {
+    let camera = camera();
+    let patches = select(&camera);
+    let eye = camera.eye();
+    let forward = camera.0.inverse() * -na::Vector3::z();
+    for patch in &patches {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].map(|(x, z)| {
+            na::Point3::new(patch.origin[0] + x * patch.size, LEVEL, patch.origin[1] + z * patch.size)
+        });
+        assert!(corners.iter().any(|c| (c - eye).dot(&forward) > -patch.size), "patch {patch:?} is behind the camera");
+    }
+    let sky = look_at([0.0, 10.0, 0.0], [0.0, 20.0, -5.0]);
+    assert!(select(&sky).is_empty());
+}
@@ -0,0 +1,50 @@
+use water::{Rain, RainSettings};
+
+fn rain(intensity: f32) -> Rain {
+    let mut rain = Rain::new(RainSettings {
+        seed: 3,
+        ..Default::default()
+    });
+    rain.set_intensity(intensity);
+    rain
+}
+
+#[test]
+fn no_drops_without_intensity() {
+    let mut rain = rain(0.0);
+    assert!((0..100).all(|_| rain.update(0.1).is_empty()));
+}
+
+#[test]
+fn drop_count_follows_rate() {
+    let mut rain = rain(0.5);
+    let settings = *rain.settings();
+    let seconds = 10.0;
+    let count: usize = (0..600).map(|_| rain.update(seconds / 600.0).len()).sum();
+
+    let expected = settings.rate * settings.size * settings.size * 0.5 * seconds;
+    // Poisson counts have a standard deviation of the square root of their mean.
+    assert!((count as f32 - expected).abs() < 4.0 * expected.sqrt(), "{count} drops, expected {expected}");
+}
+
+#[test]
+fn drops_land_inside_area() {
+    let mut rain = rain(1.0);
+    let half = rain.settings().size * 0.5;
+    for drop in rain.update(1.0) {
+        assert!(drop.position.x.abs() <= half && drop.position.y.abs() <= half);
+        assert!((0.5..=1.0).contains(&drop.strength));
+    }
+}
+
+#[test]
+fn drops_do_not_depend_on_frame_rate() {
+    let (mut slow, mut fast) = (rain(1.0), rain(1.0));
+    let slow_drops: Vec<_> = (0..10).flat_map(|_| slow.update(0.1)).collect();
+    let fast_drops: Vec<_> = (0..100).flat_map(|_| fast.update(0.01)).collect();
+
+    // Rounding may move the last drop across the end of the second.
+    let count = slow_drops.len().min(fast_drops.len());
+    assert!(slow_drops.len().abs_diff(fast_drops.len()) <= 1);
+    assert_eq!(slow_drops[..count], fast_drops[..count]);
+}
@@ -0,0 +1,55 @@
+use water::{RippleSettings, Ripples, MAX_IMPULSES};
+
+mod common;
+
+const STRENGTH: f32 = 0.1;
+
+// Dispatches the ripples and reads back how high they reach.
+fn dispatch(device: &wgpu::Device, queue: &wgpu::Queue, ripples: &mut Ripples) -> f32 {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    ripples.dispatch(&mut encoder);
+    queue.submit(Some(encoder.finish()));
+    common::max_height(device, queue, &ripples.output.texture)
+}
+
+#[test]
+fn impulses_add_up_until_dispatched_once() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let mut ripples = Ripples::new(&device, RippleSettings::default());
+
+    // Two updates before a dispatch keep the impulses from both.
+    for _ in 0..2 {
+        ripples.add_impulse(nalgebra::Point2::origin(), 1.0, STRENGTH);
+        ripples.update(&queue, 1.0 / 60.0);
+    }
+    let height = dispatch(&device, &queue, &mut ripples);
+    assert!(height > 1.8 * STRENGTH && height < 2.05 * STRENGTH, "max height {height}");
+
+    // Dispatching again without an update injects nothing more.
+    let again = dispatch(&device, &queue, &mut ripples);
+    assert!(again <= height * 1.01, "max height {again} after {height}");
+}
+
+#[test]
+fn impulses_beyond_capacity_wait_for_the_next_update() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let mut ripples = Ripples::new(&device, RippleSettings::default());
+
+    // A full buffer of faint impulses, then a strong one elsewhere that has to wait.
+    for _ in 0..MAX_IMPULSES {
+        ripples.add_impulse(nalgebra::Point2::origin(), 1.0, 0.1 * STRENGTH / MAX_IMPULSES as f32);
+    }
+    ripples.add_impulse(nalgebra::Point2::new(8.0, 8.0), 1.0, STRENGTH);
+    ripples.update(&queue, 1.0 / 60.0);
+    let full = dispatch(&device, &queue, &mut ripples);
+    assert!(full < 0.2 * STRENGTH, "max height {full} before the strong impulse");
+    ripples.update(&queue, 1.0 / 60.0);
+    let rest = dispatch(&device, &queue, &mut ripples);
+    assert!(rest > 0.9 * STRENGTH, "max height {rest} after the strong impulse");
+}
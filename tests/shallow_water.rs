@@ -0,0 +1,79 @@
+use nalgebra as na;
+use water::{ShallowWater, ShallowWaterSettings, WaterSource};
+
+const STEPS: usize = 1000;
+
+fn settings() -> ShallowWaterSettings {
+    ShallowWaterSettings {
+        resolution: 32,
+        size: 8.0,
+        max_depth: 1.5,
+        ..Default::default()
+    }
+}
+
+fn assert_volume(water: &ShallowWater, expected: f64) {
+    let error = (water.volume() - expected).abs() / expected;
+    assert!(error < 1e-4, "expected {expected} m^3 of water, got {} ({:.4}% off)", water.volume(), error * 100.0);
+}
+
+#[test]
+fn dam_break_conserves_mass() {
+    let mut water = ShallowWater::from_fn(settings(), |p| (0.0, if p.x < 0.0 { 1.0 } else { 0.1 }));
+    let volume = water.volume();
+    for _ in 0..STEPS {
+        water.step();
+    }
+
+    assert_volume(&water, volume);
+    // The bore has reached the far wall.
+    let n = settings().resolution;
+    assert!(water.depth()[(n * n / 2 + n - 1) as usize] > 0.2);
+}
+
+#[test]
+fn flooding_over_dry_terrain_conserves_mass() {
+    // A reservoir at one end of a bumpy slope, with dry land below it.
+    let mut water = ShallowWater::from_fn(settings(), |p| {
+        let bed = 0.6 - 0.1 * p.y + 0.1 * (2.0 * p.x).sin() * (1.5 * p.y).cos();
+        (bed, (1.2 - bed).max(0.0) * if p.y < -2.0 { 1.0 } else { 0.0 })
+    });
+    let volume = water.volume();
+    for _ in 0..STEPS {
+        water.step();
+        assert!(water.depth().iter().all(|&depth| depth >= 0.0));
+    }
+
+    assert_volume(&water, volume);
+    // Cells under a millimetre deep count as dry.
+    assert!(water.depth().iter().any(|&depth| depth < 0.001), "expected a shoreline");
+}
+
+#[test]
+fn sources_add_their_rate() {
+    let settings = settings();
+    let mut water = ShallowWater::from_fn(settings, |_| (0.0, 0.5));
+    let volume = water.volume();
+    let rate = 0.2;
+    water.add_source(WaterSource { position: na::Point2::new(1.0, -1.0), radius: 0.5, rate });
+    for _ in 0..STEPS {
+        water.step();
+    }
+
+    assert_volume(&water, volume + (rate * settings.time_step() * STEPS as f32) as f64);
+}
+
+#[test]
+fn lake_at_rest_stays_at_rest() {
+    let mut water = ShallowWater::from_fn(settings(), |p| {
+        let bed = 0.3 * (p.x.sin() * p.y.cos() + 1.0);
+        (bed, (0.5 - bed).max(0.0))
+    });
+    let depth = water.depth().to_vec();
+    for _ in 0..STEPS {
+        water.step();
+    }
+
+    assert_eq!(water.depth(), depth.as_slice());
+    assert_eq!(water.velocity(10, 20), na::Vector2::zeros());
+}
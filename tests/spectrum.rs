@@ -0,0 +1,122 @@
+use std::f64::consts::{PI, TAU};
+
+use water::{SpectrumSettings, WaveSpectrum};
+
+const GRAVITY: f64 = 9.81;
+
+fn settings(spectrum: WaveSpectrum) -> SpectrumSettings {
+    SpectrumSettings {
+        spectrum,
+        wind_speed: 10.0,
+        ..Default::default()
+    }
+}
+
+// Sum of E(k) dk^2 over the same grid that `generate` uses.
+fn grid_energy(settings: &SpectrumSettings, resolution: u32, patch_size: f32) -> f64 {
+    let n = resolution as i32;
+    let dk = TAU / patch_size as f64;
+    let mut energy = 0.0;
+    for y in -n / 2..n / 2 {
+        for x in -n / 2..n / 2 {
+            let k = [(x as f64 * dk) as f32, (y as f64 * dk) as f32];
+            energy += settings.density(k) as f64 * dk * dk;
+        }
+    }
+    energy
+}
+
+// Simpson integration of the frequency spectrum S(omega).
+fn frequency_energy(settings: &SpectrumSettings) -> f64 {
+    let (start, end, steps) = (0.05, 30.0, 20_000);
+    let h = (end - start) / steps as f64;
+    (0..=steps).map(|i| {
+        let weight = if i == 0 || i == steps { 1.0 } else if i % 2 == 1 { 4.0 } else { 2.0 };
+        weight * settings.frequency_spectrum((start + i as f64 * h) as f32) as f64
+    }).sum::<f64>() * h / 3.0
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    let error = (actual - expected).abs() / expected;
+    assert!(error < tolerance, "expected {expected}, got {actual} ({:.1}% off)", error * 100.0);
+}
+
+#[test]
+fn pierson_moskowitz_matches_analytic_energy() {
+    let settings = settings(WaveSpectrum::PiersonMoskowitz);
+    let peak = settings.peak_frequency() as f64;
+    let expected = 8.1e-3 * GRAVITY * GRAVITY / (5.0 * peak.powi(4));
+
+    assert_close(frequency_energy(&settings), expected, 0.01);
+    assert_close(grid_energy(&settings, 512, 2000.0), expected, 0.03);
+}
+
+#[test]
+fn phillips_matches_analytic_energy() {
+    let amplitude = 0.0005;
+    let settings = settings(WaveSpectrum::Phillips { amplitude });
+    let l = settings.wind_speed as f64 * settings.wind_speed as f64 / GRAVITY;
+    let expected = amplitude as f64 * PI * l * l / 2.0;
+
+    assert_close(grid_energy(&settings, 512, 2000.0), expected, 0.03);
+}
+
+#[test]
+fn jonswap_grid_matches_frequency_spectrum() {
+    let settings = settings(WaveSpectrum::Jonswap { gamma: 3.3 });
+    let expected = frequency_energy(&settings);
+
+    assert_close(grid_energy(&settings, 512, 1000.0), expected, 0.03);
+
+    // The peak enhancement only adds energy compared to the same spectrum without it.
+    let flat = SpectrumSettings { spectrum: WaveSpectrum::Jonswap { gamma: 1.0 }, ..settings };
+    assert!(expected > frequency_energy(&flat));
+}
+
+#[test]
+fn directional_spreading_is_normalized() {
+    for spectrum in [WaveSpectrum::Phillips { amplitude: 1.0 }, WaveSpectrum::Jonswap { gamma: 3.3 }] {
+        for spreading in [1.0, 4.0, 16.0] {
+            let settings = SpectrumSettings { spreading, swell: 0.5, ..settings(spectrum) };
+            let steps = 3600;
+            let integral = (0..steps).map(|i| {
+                let theta = -PI + (i as f64 + 0.5) * TAU / steps as f64;
+                settings.directional_spreading(1.0, theta as f32) as f64
+            }).sum::<f64>() * TAU / steps as f64;
+            assert_close(integral, 1.0, 0.001);
+        }
+    }
+}
+
+#[test]
+fn generated_spectrum_energy_matches_density() {
+    let settings = settings(WaveSpectrum::Jonswap { gamma: 3.3 });
+    let (resolution, patch_size) = (256, 500.0);
+    let h0 = settings.generate(resolution, patch_size);
+
+    // Each h(k, t) receives E|h0(k)|^2 + E|h0(-k)|^2 = E(k) dk^2.
+    let energy = h0.iter().map(|h| 2.0 * (h[0] as f64 * h[0] as f64 + h[1] as f64 * h[1] as f64)).sum::<f64>();
+    assert_close(energy, grid_energy(&settings, resolution, patch_size), 0.1);
+}
+
+#[test]
+fn generated_spectrum_is_hermitian_paired() {
+    let n = 64;
+    let h0 = settings(WaveSpectrum::PiersonMoskowitz).generate(n, 200.0);
+    for y in 0..n as usize {
+        for x in 0..n as usize {
+            let mirrored = h0[(n as usize - y) % n as usize * n as usize + (n as usize - x) % n as usize];
+            let texel = h0[y * n as usize + x];
+            assert_eq!([texel[2], texel[3]], [mirrored[0], -mirrored[1]]);
+        }
+    }
+}
+
+#[test]
+fn generation_is_deterministic_from_seed() {
+    let settings = settings(WaveSpectrum::Jonswap { gamma: 3.3 });
+    let other = SpectrumSettings { seed: 1, ..settings };
+
+    assert_eq!(settings.generate(64, 200.0), settings.generate(64, 200.0));
+    assert_ne!(settings.generate(64, 200.0), other.generate(64, 200.0));
+}
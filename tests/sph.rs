@@ -0,0 +1,123 @@
+use nalgebra as na;
+use water::{GpuSph, Sph, SphParticle, SphSettings};
+
+mod common;
+
+fn dam_break() -> Sph {
+    let settings = SphSettings::default();
+    Sph::block(settings, na::Point3::new(-0.5, 0.0, -0.5), na::Point3::new(-0.2, 0.4, -0.2))
+}
+
+fn read_particles(device: &wgpu::Device, queue: &wgpu::Queue, sph: &GpuSph) -> Vec<SphParticle> {
+    let size = sph.particles.size();
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(&sph.particles, 0, &buffer, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+    buffer.slice(..).map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let particles = bytemuck::cast_slice(&buffer.slice(..).get_mapped_range()).to_vec();
+    particles
+}
+
+#[test]
+fn density_inside_block_is_near_rest() {
+    let mut sph = dam_break();
+    sph.step();
+    let settings = *sph.settings();
+    // The middle of the block has a full neighbourhood.
+    let center = na::Point3::new(-0.35, 0.2, -0.35);
+    let particle = sph.particles()
+        .iter()
+        .min_by(|a, b| {
+            let distance = |p: &SphParticle| (na::Point3::from(p.position) - center).norm();
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap();
+    let error = (particle.density - settings.rest_density).abs() / settings.rest_density;
+    assert!(error < 0.1, "density {} at rest density {}", particle.density, settings.rest_density);
+}
+
+#[test]
+fn particles_stay_inside_bounds() {
+    let mut sph = dam_break();
+    let settings = *sph.settings();
+    for _ in 0..300 {
+        sph.step();
+    }
+    for particle in sph.particles() {
+        let position = na::Point3::from(particle.position);
+        assert!(position.iter().all(|x| x.is_finite()), "particle escaped to {position}");
+        assert!((0..3).all(|axis| (settings.bounds_min[axis]..=settings.bounds_max[axis]).contains(&position[axis])));
+    }
+}
+
+#[test]
+fn column_collapses_and_spreads() {
+    let mut sph = dam_break();
+    let top = |sph: &Sph| sph.particles().iter().map(|p| p.position[1]).fold(f32::MIN, f32::max);
+    let reach = |sph: &Sph| sph.particles().iter().map(|p| p.position[0]).fold(f32::MIN, f32::max);
+    let (start_top, start_reach) = (top(&sph), reach(&sph));
+    for _ in 0..150 {
+        sph.step();
+    }
+    assert!(top(&sph) < start_top - 0.05, "column did not fall");
+    assert!(reach(&sph) > start_reach + 0.1, "fluid did not spread along the floor");
+}
+
+#[test]
+fn gpu_matches_cpu() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let mut cpu = dam_break();
+    let mut gpu = GpuSph::new(&device, &cpu);
+    let steps = 20;
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    for _ in 0..steps {
+        cpu.step();
+        gpu.step();
+    }
+    gpu.dispatch(&mut encoder);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    assert_matches(&read_particles(&device, &queue, &gpu), &cpu);
+}
+
+#[test]
+fn steps_queued_before_an_update_are_kept() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let mut cpu = dam_break();
+    let mut gpu = GpuSph::new(&device, &cpu);
+    for _ in 0..3 {
+        gpu.step();
+    }
+    // Time enough for three more steps.
+    gpu.update(3.5 * cpu.settings().time_step());
+    for _ in 0..6 {
+        cpu.step();
+    }
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    gpu.dispatch(&mut encoder);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    assert_matches(&read_particles(&device, &queue, &gpu), &cpu);
+}
+
+fn assert_matches(particles: &[SphParticle], cpu: &Sph) {
+    assert_eq!(particles.len(), cpu.particles().len());
+    for (gpu, cpu) in particles.iter().zip(cpu.particles()) {
+        let offset = na::Point3::from(gpu.position) - na::Point3::from(cpu.position);
+        assert!(offset.norm() < 1e-3, "gpu {:?}, cpu {:?}", gpu.position, cpu.position);
+        assert!((gpu.density - cpu.density).abs() < 1.0, "gpu density {}, cpu {}", gpu.density, cpu.density);
+    }
+}
@@ -0,0 +1,423 @@
+use wgpu::util::DeviceExt as _;
+use water::{
+    CameraUniform, FoamSettings, GerstnerWave, Ocean, OceanSettings, RippleSettings, Ripples, SpectrumSettings,
+    WakeSettings, Wakes, WaterSurface, WaveUniform,
+};
+
+mod common;
+
+const PROBE_WIDTH: u32 = 16;
+const PROBE_HEIGHT: u32 = 16;
+const TIME: f32 = 2.7;
+
+// Renders one point per probe with the water shader's own `displace` and `surface_normal`,
+// writing the displaced position and normal of each probe to its own pixel. Float bits go
+// through integer targets, since 32-bit float targets are not renderable everywhere.
+const PROBE_SHADER: &str = "
+struct Probe {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) @interpolate(flat) world_pos: vec3<f32>,
+    @location(1) @interpolate(flat) swell: vec3<f32>,
+    @location(2) @interpolate(flat) grid_pos: vec2<f32>,
+}
+
+struct ProbeOutput {
+    @location(0) position: vec4<u32>,
+    @location(1) normal: vec4<u32>,
+}
+
+@vertex
+fn vs_probe(vertex: Vertex, @builtin(vertex_index) index: u32) -> Probe {
+    let size = vec2(16.0, 16.0);
+    let pixel = vec2(f32(index % 16u), f32(index / 16u)) + 0.5;
+    let surface = displace(vertex.pos.xz);
+    var probe: Probe;
+    probe.pos = vec4(pixel / size * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.5, 1.0);
+    probe.world_pos = surface.position;
+    probe.swell = surface.normal;
+    probe.grid_pos = vertex.pos.xz;
+    return probe;
+}
+
+@fragment
+fn fs_probe(probe: Probe) -> ProbeOutput {
+    let normal = surface_normal(probe.swell, probe.grid_pos);
+    return ProbeOutput(bitcast<vec4<u32>>(vec4(probe.world_pos, 1.0)), bitcast<vec4<u32>>(vec4(normal, 0.0)));
+}
+";
+
+// Normals from finite differences of `displace` around each probe, `STEP` metres either side of
+// its rest position, next to the normal `surface_normal` gives.
+const DIFFERENCE_PROBE_SHADER: &str = "
+const STEP: f32 = 0.05;
+
+struct Probe {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) @interpolate(flat) difference: vec3<f32>,
+    @location(1) @interpolate(flat) swell: vec3<f32>,
+    @location(2) @interpolate(flat) grid_pos: vec2<f32>,
+}
+
+struct ProbeOutput {
+    @location(0) difference: vec4<u32>,
+    @location(1) normal: vec4<u32>,
+}
+
+@vertex
+fn vs_probe(vertex: Vertex, @builtin(vertex_index) index: u32) -> Probe {
+    let size = vec2(16.0, 16.0);
+    let pixel = vec2(f32(index % 16u), f32(index / 16u)) + 0.5;
+    let p = vertex.pos.xz;
+    let along_x = displace(p + vec2(STEP, 0.0)).position - displace(p - vec2(STEP, 0.0)).position;
+    let along_z = displace(p + vec2(0.0, STEP)).position - displace(p - vec2(0.0, STEP)).position;
+    var probe: Probe;
+    probe.pos = vec4(pixel / size * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.5, 1.0);
+    probe.difference = normalize(cross(along_z, along_x));
+    probe.swell = displace(p).normal;
+    probe.grid_pos = p;
+    return probe;
+}
+
+@fragment
+fn fs_probe(probe: Probe) -> ProbeOutput {
+    let normal = surface_normal(probe.swell, probe.grid_pos);
+    return ProbeOutput(bitcast<vec4<u32>>(vec4(probe.difference, 1.0)), bitcast<vec4<u32>>(vec4(normal, 0.0)));
+}
+";
+
+fn settings() -> OceanSettings {
+    OceanSettings {
+        resolution: 64,
+        patch_size: 24.0,
+        choppiness: 0.8,
+        spectrum: SpectrumSettings {
+            seed: 7,
+            ..Default::default()
+        },
+        foam: FoamSettings::default(),
+    }
+}
+
+// Next to no FFT ocean, leaving the swell on its own.
+fn calm_settings() -> OceanSettings {
+    OceanSettings {
+        spectrum: SpectrumSettings {
+            wind_speed: 0.01,
+            ..settings().spectrum
+        },
+        ..settings()
+    }
+}
+
+fn waves() -> Vec<GerstnerWave> {
+    vec![
+        GerstnerWave::new([1.0, 0.2], 0.2, 9.0, 0.6),
+        GerstnerWave::new([-0.4, 1.0], 0.1, 4.0, 0.4),
+    ]
+}
+
+// Rest positions scattered over more than one ocean patch, including negative coordinates.
+fn probe_positions() -> Vec<[f32; 2]> {
+    (0..PROBE_WIDTH * PROBE_HEIGHT).map(|i| {
+        let t = i as f32;
+        [(t * 7.31).sin() * 30.0 + (t * 0.37).cos(), (t * 3.17).cos() * 30.0 - t * 0.05]
+    }).collect()
+}
+
+// The ripple uniform, texture and sampler, then the wake uniform and texture, as the water shader
+// reads them.
+fn surface_detail_bind_group(device: &wgpu::Device, ripples: &Ripples, wakes: &Wakes) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let uniform = wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    };
+    let texture = wgpu::BindingType::Texture {
+        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        view_dimension: wgpu::TextureViewDimension::D2,
+        multisampled: false,
+    };
+    let sampler = wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering);
+    let entries = [uniform, texture, sampler, uniform, texture].into_iter().enumerate().map(|(binding, ty)| wgpu::BindGroupLayoutEntry {
+        binding: binding as u32,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty,
+        count: None,
+    }).collect::<Vec<_>>();
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: None, entries: &entries });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: ripples.uniform_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&ripples.output.view) },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&ripples.output.sampler) },
+            wgpu::BindGroupEntry { binding: 3, resource: wakes.uniform_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::TextureView(&wakes.output.view) },
+        ],
+    });
+    (layout, bind_group)
+}
+
+fn render_probes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    waves: &[GerstnerWave],
+    settings: OceanSettings,
+    probe_shader: &str,
+) -> (Vec<[f32; 4]>, Vec<[f32; 4]>) {
+    let mut ocean = Ocean::new(device, queue, settings);
+    ocean.update(queue, TIME);
+
+    let mut waves = WaveUniform::new(waves);
+    waves.time = TIME;
+    let wave_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::bytes_of(&waves),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::bytes_of(&CameraUniform::new(nalgebra::Matrix4::identity(), nalgebra::Matrix4::identity(), 0.0)),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let camera_layout = device.create_bind_group_layout(&CameraUniform::BIND_GROUP_LAYOUT_DESCRIPTOR);
+    let wave_layout = device.create_bind_group_layout(&WaveUniform::BIND_GROUP_LAYOUT_DESCRIPTOR);
+    let ocean_layout = device.create_bind_group_layout(&Ocean::BIND_GROUP_LAYOUT_DESCRIPTOR);
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &camera_layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: camera_buffer.as_entire_binding() }],
+    });
+    let wave_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &wave_layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: wave_buffer.as_entire_binding() }],
+    });
+    let ocean_bind_group = ocean.create_bind_group(device, &ocean_layout);
+    // Ripples and wakes are GPU-only, so leave them at rest.
+    let ripples = Ripples::new(device, RippleSettings::default());
+    let wakes = Wakes::new(device, WakeSettings::default());
+    let (surface_detail_layout, surface_detail_bind_group) = surface_detail_bind_group(device, &ripples, &wakes);
+
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(
+            [
+                include_str!("../src/camera.wgsl"),
+                include_str!("../src/water_shading.wgsl"),
+                include_str!("../src/water.wgsl"),
+                probe_shader,
+            ].concat().into(),
+        ),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&camera_layout, &wave_layout, &ocean_layout, &surface_detail_layout],
+        push_constant_ranges: &[],
+    });
+    let target = wgpu::ColorTargetState {
+        format: wgpu::TextureFormat::Rgba32Uint,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+    };
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader_module,
+            entry_point: Some("vs_probe"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: size_of::<[f32; 5]>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader_module,
+            entry_point: Some("fs_probe"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(target.clone()), Some(target)],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::PointList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let vertices = probe_positions().iter()
+        .flat_map(|p| [p[0], 0.0, p[1], 0.0, 0.0])
+        .collect::<Vec<f32>>();
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let size = wgpu::Extent3d { width: PROBE_WIDTH, height: PROBE_HEIGHT, depth_or_array_layers: 1 };
+    let targets = [0, 1].map(|_| device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Uint,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    }));
+    let views = targets.each_ref().map(|t| t.create_view(&wgpu::TextureViewDescriptor::default()));
+    let bytes_per_row = PROBE_WIDTH * size_of::<[f32; 4]>() as u32;
+    let readback = [0, 1].map(|_| device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * PROBE_HEIGHT) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    }));
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    ocean.dispatch(&mut encoder);
+    let attachments = views.each_ref().map(|view| Some(wgpu::RenderPassColorAttachment {
+        view,
+        resolve_target: None,
+        ops: wgpu::Operations {
+            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            store: wgpu::StoreOp::Store,
+        },
+    }));
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &attachments,
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    render_pass.set_pipeline(&pipeline);
+    render_pass.set_bind_group(0, &camera_bind_group, &[]);
+    render_pass.set_bind_group(1, &wave_bind_group, &[]);
+    render_pass.set_bind_group(2, &ocean_bind_group, &[]);
+    render_pass.set_bind_group(3, &surface_detail_bind_group, &[]);
+    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+    render_pass.draw(0..PROBE_WIDTH * PROBE_HEIGHT, 0..1);
+    drop(render_pass);
+    for (texture, buffer) in targets.iter().zip(&readback) {
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(PROBE_HEIGHT),
+                },
+            },
+            size,
+        );
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let [positions, normals] = readback.map(|buffer| {
+        buffer.slice(..).map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let data = bytemuck::cast_slice::<u8, [f32; 4]>(&buffer.slice(..).get_mapped_range()).to_vec();
+        data
+    });
+    (positions, normals)
+}
+
+#[test]
+fn cpu_surface_matches_gpu_vertices() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    let (positions, normals) = render_probes(&device, &queue, &waves(), settings(), PROBE_SHADER);
+
+    let mut surface = WaterSurface::new(&waves(), settings());
+    surface.update(TIME);
+
+    for ((p, gpu_position), gpu_normal) in probe_positions().iter().zip(&positions).zip(&normals) {
+        assert_eq!(gpu_position[3], 1.0, "probe at {p:?} was not rendered");
+        let gpu_position = nalgebra::Vector3::new(gpu_position[0], gpu_position[1], gpu_position[2]);
+        let gpu_normal = nalgebra::Vector3::new(gpu_normal[0], gpu_normal[1], gpu_normal[2]);
+
+        let displacement = surface.displacement_at(p[0], p[1]);
+        let expected = nalgebra::Vector3::new(p[0], 0.0, p[1]) + displacement;
+        assert!((expected - gpu_position).norm() < 5e-3, "position at {p:?}: cpu {expected}, gpu {gpu_position}");
+
+        let height = surface.height_at(gpu_position.x, gpu_position.z);
+        assert!((height - gpu_position.y).abs() < 5e-3, "height at {p:?}: cpu {height}, gpu {}", gpu_position.y);
+
+        let normal = surface.normal_at(gpu_position.x, gpu_position.z);
+        assert!((normal - gpu_normal).norm() < 1e-2, "normal at {p:?}: cpu {normal}, gpu {gpu_normal}");
+    }
+}
+
+#[test]
+fn height_query_inverts_horizontal_displacement() {
+    let mut surface = WaterSurface::new(&waves(), settings());
+    surface.update(TIME);
+
+    for p in probe_positions() {
+        let displaced = nalgebra::Vector3::new(p[0], 0.0, p[1]) + surface.displacement_at(p[0], p[1]);
+        let rest = surface.rest_position(displaced.x, displaced.z);
+        assert!((rest - nalgebra::Vector2::from(p)).norm() < 1e-3, "rest position {rest} for {p:?}");
+        assert!((surface.height_at(displaced.x, displaced.z) - displaced.y).abs() < 1e-3);
+    }
+}
+
+// The sum of each wave's normal is exact for a lone wave, so they are checked one at a time.
+#[test]
+fn gpu_swell_normal_matches_displaced_surface() {
+    let Some((device, queue)) = common::request_device() else {
+        eprintln!("No GPU adapter available, skipping");
+        return;
+    };
+    for wave in waves() {
+        let (differences, normals) = render_probes(&device, &queue, &[wave], calm_settings(), DIFFERENCE_PROBE_SHADER);
+        for ((p, difference), normal) in probe_positions().iter().zip(&differences).zip(&normals) {
+            assert_eq!(difference[3], 1.0, "probe at {p:?} was not rendered");
+            let difference = nalgebra::Vector3::new(difference[0], difference[1], difference[2]);
+            let normal = nalgebra::Vector3::new(normal[0], normal[1], normal[2]);
+            assert!((difference - normal).norm() < 5e-3, "normal at {p:?} of {wave:?}: {normal}, displaced surface {difference}");
+        }
+    }
+}
+
+#[test]
+fn cpu_normal_matches_displaced_surface() {
+    const STEP: f32 = 0.01;
+    for wave in waves() {
+        let mut surface = WaterSurface::new(&[wave], calm_settings());
+        surface.update(TIME);
+        let position = |x: f32, z: f32| nalgebra::Vector3::new(x, 0.0, z) + surface.displacement_at(x, z);
+        for [x, z] in probe_positions() {
+            let along_x = position(x + STEP, z) - position(x - STEP, z);
+            let along_z = position(x, z + STEP) - position(x, z - STEP);
+            let difference = along_z.cross(&along_x).normalize();
+            let displaced = position(x, z);
+            let normal = surface.normal_at(displaced.x, displaced.z);
+            assert!((difference - normal).norm() < 5e-3, "normal at {:?} of {wave:?}: {normal}, displaced surface {difference}", [x, z]);
+        }
+    }
+}
+
+#[test]
+fn wave_without_direction_runs_along_x() {
+    let wave = GerstnerWave::new([0.0, 0.0], 0.2, 9.0, 0.6);
+    assert_eq!(wave.direction, [1.0, 0.0]);
+    let mut surface = WaterSurface::new(&[wave], calm_settings());
+    surface.update(TIME);
+    for [x, z] in probe_positions() {
+        assert!(surface.height_at(x, z).is_finite() && surface.normal_at(x, z).iter().all(|n| n.is_finite()));
+    }
+}
+
+#[test]
+#[should_panic(expected = "Wavelength must be positive")]
+fn wave_without_wavelength_is_rejected() {
+    GerstnerWave::new([1.0, 0.0], 0.2, 0.0, 0.6);
+}